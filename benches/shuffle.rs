@@ -0,0 +1,129 @@
+//! Simple wall-clock benchmarks for the shuffling algorithms.
+//!
+//! Run with `cargo bench`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use shuffle::fy::FisherYates;
+use shuffle::irs::Irs;
+use shuffle::shuffler::Shuffler;
+
+const ITERATIONS: u32 = 20;
+
+/// The system allocator, counting the allocations.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn bench<F>(name: &str, mut f: F)
+where
+    F: FnMut(),
+{
+    f();
+    let mut total = Duration::ZERO;
+    let mut fastest = Duration::MAX;
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        f();
+        let elapsed = start.elapsed();
+        total += elapsed;
+        fastest = fastest.min(elapsed);
+    }
+    let per_iter = total / ITERATIONS;
+    println!("{:<40} {:>12.3?} (fastest {:.3?})", name, per_iter, fastest);
+}
+
+/// Prints how many allocations a call of `f` makes once warmed up.
+fn count_allocations<F>(name: &str, mut f: F)
+where
+    F: FnMut(),
+{
+    f();
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    println!(
+        "{:<40} {:>12} allocations per call",
+        name,
+        allocations as f64 / ITERATIONS as f64
+    );
+}
+
+fn bench_irs<T: Clone>(name: &str, input: Vec<T>) {
+    let mut rng = StdRng::seed_from_u64(42);
+    let mut irs = Irs::default();
+    let mut data = input;
+    bench(name, || irs.shuffle(&mut data, &mut rng).unwrap());
+    count_allocations(name, || irs.shuffle(&mut data, &mut rng).unwrap());
+}
+
+fn bench_low_memory(len: usize, max_scratch: usize) {
+    let mut rng = StdRng::seed_from_u64(42);
+    let mut irs = Irs::default();
+    let mut data: Vec<u32> = (0..len as u32).collect();
+    bench(
+        &format!("irs/low_memory/{} max {}", len, max_scratch),
+        || {
+            irs.shuffle_low_memory(&mut data, max_scratch, &mut rng)
+                .unwrap()
+        },
+    );
+}
+
+fn bench_shuffle_indices(len: usize, repeat: usize) {
+    let mut rng = StdRng::seed_from_u64(42);
+    let mut irs = Irs::<usize>::default();
+    // The generic way of getting a fresh permutation.
+    bench(&format!("irs/shuffle 0..{} x{}", len, repeat), || {
+        for _ in 0..repeat {
+            let mut permutation: Vec<usize> = (0..len).collect();
+            irs.shuffle(&mut permutation, &mut rng).unwrap();
+        }
+    });
+    bench(&format!("irs/shuffle_indices {} x{}", len, repeat), || {
+        for _ in 0..repeat {
+            irs.shuffle_indices(len, &mut rng).unwrap();
+        }
+    });
+}
+
+fn bench_fy<T: Clone>(name: &str, input: Vec<T>) {
+    let mut rng = StdRng::seed_from_u64(42);
+    let mut fy = FisherYates::default();
+    let mut data = input;
+    bench(name, || fy.shuffle(&mut data, &mut rng).unwrap());
+}
+
+fn main() {
+    bench_irs("irs/u32/52", (0..52u32).collect());
+    bench_irs("irs/u32/10k", (0..10_000u32).collect());
+    bench_irs("irs/u32/100k", (0..100_000u32).collect());
+    bench_irs("irs/[u64; 16]/100k", vec![[0u64; 16]; 100_000]);
+    bench_irs("irs/u32/1M", (0..1_000_000u32).collect());
+    bench_low_memory(1_000_000, 1 << 16);
+    bench_low_memory(1_000_000, 1 << 10);
+    bench_low_memory(1_000_000, 4);
+    bench_shuffle_indices(100_000, 1);
+    bench_shuffle_indices(100, 1000);
+    bench_fy("fy/[u64; 64]/200k", vec![[0u64; 64]; 200_000]);
+}
@@ -0,0 +1,111 @@
+//! Statistics describing how much a shuffle moved the elements around.
+//!
+//! The functions take a permutation in the form described in
+//! [`permutation`](crate::permutation), i.e. `perm[i]` is the original
+//! index of the element that ended up at position `i`. They are useful to
+//! compare how far different algorithms spread the data.
+
+/// Returns how far every element moved, i.e. `|perm[i] - i|` for each `i`.
+///
+/// # Examples
+/// ```
+/// use shuffle::analysis::move_distances;
+///
+/// assert_eq!(move_distances(&[2, 0, 1, 3]), vec![2, 1, 1, 0]);
+/// ```
+pub fn move_distances(perm: &[usize]) -> Vec<usize> {
+    perm.iter()
+        .enumerate()
+        .map(|(i, &source)| i.abs_diff(source))
+        .collect()
+}
+
+/// Returns the mean of [`move_distances`], or `0.0` for an empty
+/// permutation.
+///
+/// For a uniformly random permutation of `n` elements the expected value
+/// is `(n * n - 1) / (3 * n)`, so about `n / 3`.
+///
+/// # Examples
+/// ```
+/// use shuffle::analysis::mean_move_distance;
+///
+/// assert_eq!(mean_move_distance(&[2, 0, 1, 3]), 1.0);
+/// ```
+pub fn mean_move_distance(perm: &[usize]) -> f64 {
+    if perm.is_empty() {
+        return 0.0;
+    }
+    let total: usize = move_distances(perm).iter().sum();
+    total as f64 / perm.len() as f64
+}
+
+/// Returns the number of rising sequences of `perm`, or `0` for an empty
+/// permutation.
+///
+/// A rising sequence is a maximal run of consecutive original indices
+/// `a, a + 1, ..., b` that appear in this order, though not necessarily
+/// next to each other. The identity has a single one, and a riffle shuffle
+/// at most doubles their number, so this shows how far a deck is from
+/// being well mixed.
+///
+/// # Examples
+/// ```
+/// use shuffle::analysis::rising_sequences;
+///
+/// assert_eq!(rising_sequences(&[0, 1, 2, 3]), 1);
+/// assert_eq!(rising_sequences(&[0, 2, 1, 3]), 2);
+/// assert_eq!(rising_sequences(&[3, 2, 1, 0]), 4);
+/// ```
+pub fn rising_sequences(perm: &[usize]) -> usize {
+    if perm.is_empty() {
+        return 0;
+    }
+    let mut position = vec![0; perm.len()];
+    for (i, &source) in perm.iter().enumerate() {
+        position[source] = i;
+    }
+    1 + position.windows(2).filter(|w| w[1] < w[0]).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::irs::Irs;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_identity() {
+        let identity: Vec<usize> = (0..10).collect();
+        assert_eq!(move_distances(&identity), vec![0; 10]);
+        assert_eq!(mean_move_distance(&identity), 0.0);
+        assert_eq!(mean_move_distance(&[]), 0.0);
+        assert_eq!(rising_sequences(&identity), 1);
+        assert_eq!(rising_sequences(&[]), 0);
+    }
+
+    #[test]
+    fn test_reversal() {
+        let reversed: Vec<usize> = (0..6).rev().collect();
+        assert_eq!(move_distances(&reversed), vec![5, 3, 1, 1, 3, 5]);
+        assert_eq!(mean_move_distance(&reversed), 3.0);
+
+        let reversed: Vec<usize> = (0..5).rev().collect();
+        assert_eq!(move_distances(&reversed), vec![4, 2, 0, 2, 4]);
+        assert_eq!(rising_sequences(&reversed), 5);
+    }
+
+    #[test]
+    fn test_uniform_mean() {
+        let mut rng = StdRng::seed_from_u64(37);
+        let mut irs = Irs::<()>::default();
+        let n = 1000;
+        let trials = 100;
+        let total: f64 = (0..trials)
+            .map(|_| mean_move_distance(&irs.shuffle_indices(n, &mut rng).unwrap()))
+            .sum();
+        let expected = (n * n - 1) as f64 / (3 * n) as f64;
+        assert!((total / trials as f64 - expected).abs() < 3.0);
+    }
+}
@@ -0,0 +1,110 @@
+//! A shuffle whose strength is controlled by a temperature.
+//!
+//! Useful for simulated-annealing-style exploration, where one wants
+//! permutations that are close to the identity at low temperatures and
+//! fully random at high ones.
+
+use rand::Rng;
+
+use crate::shuffler::ShuffleError;
+
+/// Shuffles `data` applying only a fraction of the Fisher-Yates swaps.
+///
+/// Each swap of the Fisher-Yates algorithm is performed with probability
+/// `temperature`, so `0.0` leaves the data untouched and `1.0` is exactly
+/// a [`FisherYates`](crate::fy::FisherYates) shuffle.
+///
+/// For any temperature strictly between `0.0` and `1.0` the resulting
+/// permutation is **not** uniformly distributed: it is biased towards
+/// the identity, and the bias grows as the temperature goes down.
+///
+/// Returns an error if `temperature` is not within `[0, 1]`.
+///
+/// # Examples
+/// ```
+/// use shuffle::annealed;
+/// use rand::rngs::mock::StepRng;
+///
+/// let mut rng = StepRng::new(2, 13);
+/// let mut input = vec![1, 2, 3, 4, 5];
+///
+/// annealed::shuffle(&mut input, 0.0, &mut rng).unwrap();
+/// assert_eq!(&input, &[1, 2, 3, 4, 5]);
+/// ```
+pub fn shuffle<T, R>(data: &mut [T], temperature: f64, rng: &mut R) -> Result<(), ShuffleError>
+where
+    R: Rng + ?Sized,
+{
+    if !(0.0..=1.0).contains(&temperature) {
+        return Err(ShuffleError::InvalidArgument(
+            "temperature must be within [0, 1]",
+        ));
+    }
+    for i in (1..data.len()).rev() {
+        let apply = temperature >= 1.0 || (temperature > 0.0 && rng.gen_bool(temperature));
+        if apply {
+            let j = rng.gen_range(0..(i + 1));
+            data.swap(i, j);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fy::FisherYates;
+    use crate::shuffler::Shuffler;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_zero_temperature() {
+        let mut rng = StdRng::seed_from_u64(13);
+        let input_data: Vec<u32> = (0..100).collect();
+        let mut target = input_data.clone();
+        shuffle(&mut target, 0.0, &mut rng).unwrap();
+        assert_eq!(target, input_data);
+    }
+
+    #[test]
+    fn test_full_temperature() {
+        let input_data: Vec<u32> = (0..100).collect();
+
+        let mut target = input_data.clone();
+        shuffle(&mut target, 1.0, &mut StdRng::seed_from_u64(13)).unwrap();
+        let mut expected = input_data.clone();
+        FisherYates::default()
+            .shuffle(&mut expected, &mut StdRng::seed_from_u64(13))
+            .unwrap();
+        assert_eq!(target, expected);
+    }
+
+    #[test]
+    fn test_low_temperature_moves_fewer_elements() {
+        let mut rng = StdRng::seed_from_u64(13);
+        let input_data: Vec<u32> = (0..1000).collect();
+        let mut moved = Vec::new();
+        for &temperature in [0.05, 0.5, 1.0].iter() {
+            let mut target = input_data.clone();
+            shuffle(&mut target, temperature, &mut rng).unwrap();
+            moved.push(
+                target
+                    .iter()
+                    .zip(input_data.iter())
+                    .filter(|(a, b)| a != b)
+                    .count(),
+            );
+        }
+        assert!(moved[0] < moved[1] && moved[1] < moved[2]);
+    }
+
+    #[test]
+    fn test_invalid_temperature() {
+        let mut rng = StdRng::seed_from_u64(13);
+        let mut target = vec![1, 2, 3];
+        assert!(shuffle(&mut target, -0.1, &mut rng).is_err());
+        assert!(shuffle(&mut target, 1.1, &mut rng).is_err());
+        assert!(shuffle(&mut target, f64::NAN, &mut rng).is_err());
+    }
+}
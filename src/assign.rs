@@ -0,0 +1,90 @@
+//! Random assignments between collections.
+
+use rand::Rng;
+
+use crate::fy::FisherYates;
+use crate::shuffler::ShuffleError;
+
+/// Pairs every element of `left` with a different element of `right`,
+/// uniformly at random.
+///
+/// The pairs come in the order of `left`, and every one of the `n!`
+/// possible bijections is equally likely. Returns an error if the
+/// collections have different lengths.
+///
+/// # Examples
+/// ```
+/// use shuffle::assign::random_bijection;
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let mut rng = StdRng::seed_from_u64(42);
+/// let workers = ["alice", "bob", "carol"];
+/// let tasks = [1, 2, 3];
+///
+/// let assignment = random_bijection(&workers, &tasks, &mut rng).unwrap();
+/// assert_eq!(assignment.len(), 3);
+/// assert!(random_bijection(&workers, &tasks[..2], &mut rng).is_err());
+/// ```
+pub fn random_bijection<A, B, R>(
+    left: &[A],
+    right: &[B],
+    rng: &mut R,
+) -> Result<Vec<(A, B)>, ShuffleError>
+where
+    A: Clone,
+    B: Clone,
+    R: Rng + ?Sized,
+{
+    if left.len() != right.len() {
+        return Err(ShuffleError::LengthMismatch);
+    }
+    let permutation = FisherYates::default().shuffle_indices_vec(right.len(), rng);
+    Ok(left
+        .iter()
+        .zip(permutation)
+        .map(|(a, i)| (a.clone(), right[i].clone()))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_random_bijection() {
+        let left: Vec<u32> = (0..20).collect();
+        let right: Vec<String> = (0..20).map(|i| format!("task {}", i)).collect();
+        let mut pairings = HashSet::new();
+        for seed in 0..10 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let pairs = random_bijection(&left, &right, &mut rng).unwrap();
+
+            let lefts: Vec<u32> = pairs.iter().map(|(a, _)| *a).collect();
+            assert_eq!(lefts, left);
+            let mut rights: Vec<String> = pairs.iter().map(|(_, b)| b.clone()).collect();
+            rights.sort();
+            let mut expected = right.clone();
+            expected.sort();
+            assert_eq!(rights, expected);
+            pairings.insert(pairs);
+        }
+        assert_eq!(pairings.len(), 10);
+    }
+
+    #[test]
+    fn test_mismatched_lengths() {
+        let mut rng = StdRng::seed_from_u64(39);
+        assert_eq!(
+            random_bijection(&[1, 2], &[1], &mut rng),
+            Err(ShuffleError::LengthMismatch)
+        );
+        assert_eq!(
+            random_bijection::<u8, u8, _>(&[], &[], &mut rng),
+            Ok(vec![])
+        );
+    }
+}
@@ -0,0 +1,120 @@
+//! Shuffling fixed-size blocks of elements as units.
+
+use rand::Rng;
+
+use crate::fy::FisherYates;
+use crate::shuffler::ShuffleError;
+use crate::swap::SwapSlice;
+
+/// Shuffles the order of the consecutive blocks of `chunk_size` elements
+/// of `data`, keeping the elements of every block in their order.
+///
+/// This is useful for data stored interleaved, e.g. audio frames of
+/// `chunk_size` samples. The blocks are shuffled by Fisher-Yates, swapping
+/// two whole blocks at a time in place, so nothing is allocated. The order
+/// of the blocks is the same as that of
+/// [`FisherYates::shuffle_in_place`] on a slice of `data.len() / chunk_size`
+/// elements with the same `rng`.
+///
+/// Returns an error, leaving `data` unchanged, if `chunk_size` is zero or
+/// doesn't divide the length of `data`.
+///
+/// # Examples
+/// ```
+/// use shuffle::blocks::shuffle_chunks;
+/// use rand::rngs::mock::StepRng;
+///
+/// let mut rng = StepRng::new(2, 13);
+/// let mut frames = [1, 2, 3, 4, 5, 6, 7, 8];
+///
+/// shuffle_chunks(&mut frames, 2, &mut rng).unwrap();
+/// assert_eq!(frames, [3, 4, 5, 6, 7, 8, 1, 2]);
+/// assert!(shuffle_chunks(&mut frames, 3, &mut rng).is_err());
+/// ```
+pub fn shuffle_chunks<T, R>(
+    data: &mut [T],
+    chunk_size: usize,
+    rng: &mut R,
+) -> Result<(), ShuffleError>
+where
+    R: Rng + ?Sized,
+{
+    if chunk_size == 0 {
+        return Err(ShuffleError::InvalidArgument("chunk size must be non-zero"));
+    }
+    if !data.len().is_multiple_of(chunk_size) {
+        return Err(ShuffleError::InvalidArgument(
+            "length must be a multiple of the chunk size",
+        ));
+    }
+    let mut blocks = Blocks { data, chunk_size };
+    FisherYates::default().shuffle_in_place(&mut blocks, rng);
+    Ok(())
+}
+
+/// `data` seen as a sequence of blocks of `chunk_size` elements.
+struct Blocks<'a, T> {
+    data: &'a mut [T],
+    chunk_size: usize,
+}
+
+impl<T> SwapSlice for Blocks<'_, T> {
+    fn len(&self) -> usize {
+        self.data.len() / self.chunk_size
+    }
+
+    fn swap(&mut self, i: usize, j: usize) {
+        let (i, j) = (i.min(j), i.max(j));
+        if i == j {
+            return;
+        }
+        let size = self.chunk_size;
+        let (left, right) = self.data.split_at_mut(j * size);
+        left[i * size..(i + 1) * size].swap_with_slice(&mut right[..size]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_blocks_stay_intact() {
+        let input_data: Vec<u32> = (0..120).collect();
+        let mut orders = HashSet::new();
+        for seed in 0..20 {
+            let mut target = input_data.clone();
+            shuffle_chunks(&mut target, 6, &mut StdRng::seed_from_u64(seed)).unwrap();
+
+            let mut expected: Vec<usize> = (0..20).collect();
+            FisherYates::default()
+                .shuffle_in_place(&mut expected, &mut StdRng::seed_from_u64(seed));
+            for (chunk, &block) in target.chunks(6).zip(&expected) {
+                assert_eq!(chunk, &input_data[block * 6..block * 6 + 6]);
+            }
+            orders.insert(expected);
+        }
+        assert!(orders.len() > 15);
+    }
+
+    #[test]
+    fn test_degenerate_sizes() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut empty: [u32; 0] = [];
+        shuffle_chunks(&mut empty, 4, &mut rng).unwrap();
+
+        let mut data = [1, 2, 3, 4, 5, 6];
+        shuffle_chunks(&mut data, 6, &mut rng).unwrap();
+        assert_eq!(data, [1, 2, 3, 4, 5, 6]);
+        for chunk_size in [0, 4, 7] {
+            assert!(matches!(
+                shuffle_chunks(&mut data, chunk_size, &mut rng),
+                Err(ShuffleError::InvalidArgument(_))
+            ));
+            assert_eq!(data, [1, 2, 3, 4, 5, 6]);
+        }
+    }
+}
@@ -0,0 +1,318 @@
+//! Simulation of the way cards are shuffled by hand at casinos.
+//!
+//! A dealer doesn't run a single algorithm but a fixed script of simple
+//! moves. The moves are available on their own, so other scripts can be
+//! composed from them, and [`standard_procedure`] runs the usual one.
+//!
+//! Unlike the other shufflers, none of this produces a uniformly random
+//! permutation; it models how real decks get (imperfectly) mixed.
+
+use std::iter;
+
+use rand::Rng;
+
+use crate::irs::Irs;
+use crate::riffle::{binomial_half, RiffleShuffle};
+use crate::shuffler::{ShuffleError, Shuffler};
+
+/// Shuffles `data` the way a casino dealer would: a riffle, a strip,
+/// another riffle and a cut.
+///
+/// # Examples
+/// ```
+/// use shuffle::casino;
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let mut rng = StdRng::seed_from_u64(42);
+/// let mut deck: Vec<u32> = (0..52).collect();
+///
+/// casino::standard_procedure(&mut deck, &mut rng);
+/// deck.sort();
+/// assert_eq!(deck, (0..52).collect::<Vec<_>>());
+/// ```
+pub fn standard_procedure<T, R>(data: &mut [T], rng: &mut R)
+where
+    T: Clone,
+    R: Rng + ?Sized,
+{
+    riffle(data, rng);
+    strip(data, rng);
+    riffle(data, rng);
+    cut(data, rng);
+}
+
+/// A single riffle shuffle, in the Gilbert-Shannon-Reeds model.
+///
+/// The deck is cut in two packets, with the size of the first one
+/// following a binomial distribution, and then the cards are dropped
+/// from the packets one by one, each time from a packet chosen with
+/// probability proportional to its size.
+///
+/// This is a single pass of [`RiffleShuffle`], and the forward
+/// counterpart of a round of the [inverse riffle shuffle](crate::irs::Irs).
+pub fn riffle<T, R>(data: &mut [T], rng: &mut R)
+where
+    T: Clone,
+    R: Rng + ?Sized,
+{
+    RiffleShuffle::new(1)
+        .shuffle_slice(data, rng)
+        .expect("a riffle is a permutation");
+}
+
+/// A strip shuffle: packets of a few cards are peeled off the top of the
+/// deck and each is put on top of a new pile.
+///
+/// The packets are between one and a third of the deck long, so there
+/// are about six of them. Their order gets reversed, while the cards
+/// within each packet keep theirs.
+pub fn strip<T, R>(data: &mut [T], rng: &mut R)
+where
+    R: Rng + ?Sized,
+{
+    let max_packet = (data.len() / 3).max(1);
+    // Reversing the whole deck reverses the order of the packets, and
+    // reversing every packet afterwards restores the order within it.
+    data.reverse();
+    let mut end = data.len();
+    while end > 0 {
+        let start = end.saturating_sub(rng.gen_range(1..=max_packet));
+        data[start..end].reverse();
+        end = start;
+    }
+}
+
+/// A cut: the top part of the deck is moved to the bottom.
+///
+/// The size of the top part follows a binomial distribution, so the cut
+/// is usually close to the middle.
+pub fn cut<T, R>(data: &mut [T], rng: &mut R)
+where
+    R: Rng + ?Sized,
+{
+    let at = binomial_half(data.len(), rng);
+    data.rotate_left(at);
+}
+
+/// Riffles several decks together into a single shoe, taking all the
+/// cards out of `decks`.
+///
+/// This generalizes [`riffle`] to any number of packets: the cards are
+/// dropped from the decks one by one, each time from a deck chosen with
+/// probability proportional to the number of cards left in it, so every
+/// interleaving of the decks is equally likely. The interleaving is
+/// drawn as the inverse of such a merge, i.e. by shuffling the deck
+/// number of every card with the [inverse riffle shuffle](crate::irs::Irs).
+/// As in a real riffle, the cards of each deck keep their order, so
+/// shuffle the decks first if that matters.
+///
+/// Returns an error, leaving `decks` unchanged, if the inverse riffle
+/// shuffle fails.
+///
+/// # Examples
+/// ```
+/// use shuffle::casino;
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let mut rng = StdRng::seed_from_u64(42);
+/// let mut decks: Vec<Vec<u32>> = (0..6).map(|_| (0..52).collect()).collect();
+///
+/// let mut shoe = casino::shuffle_decks(&mut decks, &mut rng).unwrap();
+/// assert!(decks.iter().all(|deck| deck.is_empty()));
+/// assert_eq!(shoe.len(), 6 * 52);
+/// shoe.sort();
+/// assert!(shoe.chunks(6).zip(0..).all(|(copies, card)| copies == [card; 6]));
+/// ```
+pub fn shuffle_decks<T, R>(decks: &mut [Vec<T>], rng: &mut R) -> Result<Vec<T>, ShuffleError>
+where
+    R: Rng + ?Sized,
+{
+    let mut sources: Vec<usize> = decks
+        .iter()
+        .enumerate()
+        .flat_map(|(i, deck)| iter::repeat_n(i, deck.len()))
+        .collect();
+    Irs::default().shuffle(&mut sources, rng)?;
+
+    let mut decks: Vec<_> = decks.iter_mut().map(|deck| deck.drain(..)).collect();
+    Ok(sources
+        .into_iter()
+        .map(|i| decks[i].next().expect("a deck ran out of cards"))
+        .collect())
+}
+
+/// Riffles several decks together like [`shuffle_decks`], then deals the
+/// shoe back into `decks`, each getting as many cards as it had.
+///
+/// Returns an error, leaving `decks` unchanged, if the inverse riffle
+/// shuffle fails.
+///
+/// # Examples
+/// ```
+/// use shuffle::casino;
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let mut rng = StdRng::seed_from_u64(42);
+/// let mut decks = vec![vec!['a'; 3], vec!['b'; 5]];
+///
+/// casino::redistribute_decks(&mut decks, &mut rng).unwrap();
+/// assert_eq!(decks[0].len(), 3);
+/// assert_eq!(decks[1].len(), 5);
+/// ```
+pub fn redistribute_decks<T, R>(decks: &mut [Vec<T>], rng: &mut R) -> Result<(), ShuffleError>
+where
+    R: Rng + ?Sized,
+{
+    let lengths: Vec<usize> = decks.iter().map(Vec::len).collect();
+    let mut shoe = shuffle_decks(decks, rng)?.into_iter();
+    for (deck, len) in decks.iter_mut().zip(lengths) {
+        deck.extend(shoe.by_ref().take(len));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::mean_move_distance;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_steps_preserve_the_deck() {
+        let mut rng = StdRng::seed_from_u64(36);
+        for len in [0, 1, 2, 3, 52, 100] {
+            let input_data: Vec<usize> = (0..len).collect();
+            let steps: [fn(&mut [usize], &mut StdRng); 4] =
+                [riffle, strip, cut, standard_procedure];
+            for step in steps.iter() {
+                let mut target = input_data.clone();
+                step(&mut target, &mut rng);
+                target.sort_unstable();
+                assert_eq!(target, input_data);
+            }
+        }
+    }
+
+    #[test]
+    fn test_strip_keeps_packets_together() {
+        let mut rng = StdRng::seed_from_u64(36);
+        for _ in 0..20 {
+            let mut deck: Vec<usize> = (0..52).collect();
+            strip(&mut deck, &mut rng);
+
+            // The packets are the runs of consecutive cards, in reverse order.
+            let mut packets: Vec<&[usize]> = Vec::new();
+            let mut start = 0;
+            for i in 1..=deck.len() {
+                if i == deck.len() || deck[i - 1] + 1 != deck[i] {
+                    packets.push(&deck[start..i]);
+                    start = i;
+                }
+            }
+            assert!(packets.len() >= 4);
+            assert!(packets.iter().all(|packet| packet.len() <= 17));
+            assert!(packets.windows(2).all(|w| w[0][0] > w[1][0]));
+        }
+    }
+
+    #[test]
+    fn test_standard_procedure_mixes() {
+        let mut rng = StdRng::seed_from_u64(36);
+        let trials = 1000;
+        let mut total = 0.0;
+        for _ in 0..trials {
+            let mut deck: Vec<usize> = (0..52).collect();
+            standard_procedure(&mut deck, &mut rng);
+            total += mean_move_distance(&deck);
+        }
+        // A uniformly random deck has a mean displacement of about 17.
+        let average = total / trials as f64;
+        assert!(average > 12.0, "average displacement: {}", average);
+    }
+
+    #[test]
+    fn test_shuffle_decks_keeps_every_card() {
+        let mut rng = StdRng::seed_from_u64(37);
+        let mut decks: Vec<Vec<(usize, usize)>> = (0..6)
+            .map(|deck| (0..52).map(|card| (deck, card)).collect())
+            .collect();
+        decks.push(Vec::new());
+        let shoe = shuffle_decks(&mut decks, &mut rng).unwrap();
+        assert!(decks.iter().all(Vec::is_empty));
+
+        let mut sorted = shoe.clone();
+        sorted.sort_unstable();
+        let expected: Vec<_> = (0..6)
+            .flat_map(|deck| (0..52).map(move |card| (deck, card)))
+            .collect();
+        assert_eq!(sorted, expected);
+
+        // Each deck keeps its order, but the decks are mixed together.
+        for deck in 0..6 {
+            let cards: Vec<usize> = shoe
+                .iter()
+                .filter(|(d, _)| *d == deck)
+                .map(|&(_, card)| card)
+                .collect();
+            assert_eq!(cards, (0..52).collect::<Vec<_>>());
+        }
+        assert!(shoe[..52].iter().any(|(deck, _)| *deck != 0));
+
+        assert!(shuffle_decks::<u32, _>(&mut [], &mut rng)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_shuffle_decks_interleavings_are_uniform() {
+        let mut rng = StdRng::seed_from_u64(37);
+        let trials = 6000;
+        let mut counts: HashMap<Vec<char>, usize> = HashMap::new();
+        for _ in 0..trials {
+            let mut decks = vec![vec!['a', 'b'], vec!['x'], vec!['y']];
+            *counts
+                .entry(shuffle_decks(&mut decks, &mut rng).unwrap())
+                .or_insert(0) += 1;
+        }
+        // 4! / 2! interleavings.
+        assert_eq!(counts.len(), 12);
+        let expected = trials / 12;
+        for (shoe, count) in counts {
+            assert!(
+                count.abs_diff(expected) < expected / 5,
+                "{:?}: {}",
+                shoe,
+                count
+            );
+        }
+    }
+
+    #[test]
+    fn test_redistribute_decks() {
+        let mut rng = StdRng::seed_from_u64(37);
+        let mut decks: Vec<Vec<u32>> = vec![(0..10).collect(), Vec::new(), (10..40).collect()];
+        redistribute_decks(&mut decks, &mut rng).unwrap();
+        assert_eq!(
+            decks.iter().map(Vec::len).collect::<Vec<_>>(),
+            vec![10, 0, 30]
+        );
+        let mut all: Vec<u32> = decks.concat();
+        all.sort_unstable();
+        assert_eq!(all, (0..40).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_binomial_half() {
+        let mut rng = StdRng::seed_from_u64(36);
+        assert_eq!(binomial_half(0, &mut rng), 0);
+        let draws: Vec<usize> = (0..1000).map(|_| binomial_half(100, &mut rng)).collect();
+        assert!(draws.iter().all(|&k| k <= 100));
+        let mean = draws.iter().sum::<usize>() as f64 / draws.len() as f64;
+        assert!((mean - 50.0).abs() < 1.0);
+    }
+}
@@ -0,0 +1,198 @@
+//! Drawing random combinations without repetition.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use rand::{Rng, RngCore};
+
+/// The number of consecutive repeated draws, relative to the chance of
+/// a new draw, after which the randomness source is considered broken.
+const MAX_REJECTIONS: u128 = 128;
+
+/// An iterator over distinct random `k`-combinations of a slice.
+///
+/// Every call to `next` returns `k` different elements of the slice, in
+/// the order they appear in it, and no combination is returned twice.
+/// Each new combination is uniformly distributed among the ones that
+/// were not returned yet.
+///
+/// The iterator ends when all `C(n, k)` combinations were returned, when
+/// `budget` combinations were returned, or when `rng` keeps repeating
+/// itself so much that it looks broken.
+///
+/// # Memory
+///
+/// The combinations are drawn with Floyd's algorithm and the repeated
+/// ones are rejected, so every returned combination is remembered as
+/// a vector of `k` indices in a hash set. That is roughly
+/// `k * size_of::<usize>() + 32` bytes per combination, and the reason for
+/// the `budget`.
+///
+/// # Examples
+/// ```
+/// use shuffle::combinations::RandomCombinations;
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let mut rng = StdRng::seed_from_u64(42);
+/// let data = [1, 2, 3, 4];
+///
+/// let combinations: Vec<Vec<&i32>> = RandomCombinations::new(&data, 3, 10, &mut rng).collect();
+/// // There are only 4 combinations of 3 out of 4 elements.
+/// assert_eq!(combinations.len(), 4);
+/// ```
+pub struct RandomCombinations<'a, T> {
+    data: &'a [T],
+    k: usize,
+    rng: &'a mut dyn RngCore,
+    emitted: HashSet<Vec<usize>>,
+    limit: u128,
+    total: u128,
+}
+
+impl<'a, T> RandomCombinations<'a, T> {
+    /// Creates an iterator over at most `budget` distinct `k`-combinations
+    /// of `data`.
+    ///
+    /// If `k` is larger than the length of `data` there are no combinations.
+    pub fn new(data: &'a [T], k: usize, budget: usize, rng: &'a mut dyn RngCore) -> Self {
+        let total = binomial(data.len(), k);
+        RandomCombinations {
+            data,
+            k,
+            rng,
+            emitted: HashSet::new(),
+            limit: total.min(budget as u128),
+            total,
+        }
+    }
+
+    /// The number of combinations returned so far.
+    pub fn emitted(&self) -> usize {
+        self.emitted.len()
+    }
+
+    /// Draws `k` distinct indices from `0..n` with Floyd's algorithm,
+    /// sorted in increasing order.
+    fn draw(&mut self) -> Vec<usize> {
+        let n = self.data.len();
+        let mut chosen = Vec::with_capacity(self.k);
+        for j in (n - self.k)..n {
+            let t = self.rng.gen_range(0..=j);
+            chosen.push(if chosen.contains(&t) { j } else { t });
+        }
+        chosen.sort_unstable();
+        chosen
+    }
+}
+
+impl<'a, T> Iterator for RandomCombinations<'a, T> {
+    type Item = Vec<&'a T>;
+
+    fn next(&mut self) -> Option<Vec<&'a T>> {
+        let emitted = self.emitted.len() as u128;
+        if emitted >= self.limit {
+            return None;
+        }
+        // A draw is new with probability (total - emitted) / total.
+        let max_attempts = MAX_REJECTIONS.saturating_mul(self.total) / (self.total - emitted);
+        let mut attempts = 0;
+        while attempts < max_attempts {
+            let indices = self.draw();
+            if !self.emitted.contains(&indices) {
+                let data = self.data;
+                let combination = indices.iter().map(|&i| &data[i]).collect();
+                self.emitted.insert(indices);
+                return Some(combination);
+            }
+            attempts += 1;
+        }
+        self.limit = emitted;
+        None
+    }
+}
+
+impl<'a, T> fmt::Debug for RandomCombinations<'a, T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RandomCombinations")
+            .field("data", &self.data)
+            .field("k", &self.k)
+            .field("emitted", &self.emitted.len())
+            .field("limit", &self.limit)
+            .finish()
+    }
+}
+
+/// Returns `C(n, k)`, saturating at `u128::MAX`.
+fn binomial(n: usize, k: usize) -> u128 {
+    if k > n {
+        return 0;
+    }
+    let k = k.min(n - k);
+    let mut result: u128 = 1;
+    for i in 0..k {
+        // `result * (n - i)` is divisible by `i + 1`, as the result is
+        // then `C(n, i + 1) * (i + 1)`.
+        match result.checked_mul((n - i) as u128) {
+            Some(product) => result = product / (i as u128 + 1),
+            None => return u128::MAX,
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_distinct_combinations() {
+        let mut rng = StdRng::seed_from_u64(33);
+        let data: Vec<u32> = (0..20).collect();
+        let combinations: Vec<Vec<&u32>> =
+            RandomCombinations::new(&data, 5, 200, &mut rng).collect();
+
+        assert_eq!(combinations.len(), 200);
+        let distinct: HashSet<&Vec<&u32>> = combinations.iter().collect();
+        assert_eq!(distinct.len(), 200);
+        for combination in &combinations {
+            assert_eq!(combination.len(), 5);
+            assert!(combination.windows(2).all(|w| w[0] < w[1]));
+        }
+    }
+
+    #[test]
+    fn test_exhaustion() {
+        let mut rng = StdRng::seed_from_u64(33);
+        let data = ['a', 'b', 'c', 'd', 'e', 'f'];
+        let mut combinations = RandomCombinations::new(&data, 2, usize::MAX, &mut rng);
+        let distinct: HashSet<Vec<&char>> = combinations.by_ref().collect();
+        assert_eq!(distinct.len(), 15);
+        assert_eq!(combinations.emitted(), 15);
+        assert_eq!(combinations.next(), None);
+
+        assert_eq!(RandomCombinations::new(&data, 7, 10, &mut rng).count(), 0);
+        assert_eq!(RandomCombinations::new(&data, 0, 10, &mut rng).count(), 1);
+    }
+
+    #[test]
+    fn test_broken_rng_ends_the_iteration() {
+        let mut rng = rand::rngs::mock::StepRng::new(0, 0);
+        let data: Vec<u32> = (0..10).collect();
+        assert_eq!(RandomCombinations::new(&data, 3, 100, &mut rng).count(), 1);
+    }
+
+    #[test]
+    fn test_binomial() {
+        assert_eq!(binomial(5, 2), 10);
+        assert_eq!(binomial(52, 5), 2_598_960);
+        assert_eq!(binomial(3, 4), 0);
+        assert_eq!(binomial(10, 0), 1);
+        assert_eq!(binomial(1000, 500), u128::MAX);
+    }
+}
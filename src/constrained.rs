@@ -0,0 +1,110 @@
+//! Shuffling with forbidden positions.
+
+use std::collections::HashSet;
+
+use rand::Rng;
+
+use crate::fy::FisherYates;
+use crate::shuffler::{ShuffleError, Shuffler};
+
+/// The number of permutations tried before giving up.
+const MAX_ATTEMPTS: usize = 10_000;
+
+/// Shuffles `data` so that no element lands on a position forbidden for it.
+///
+/// Every pair `(element, position)` in `forbidden` means that the element
+/// at index `element` of the input must not end up at index `position`.
+/// A derangement, for example, forbids `(i, i)` for every `i`.
+///
+/// Permutations are drawn uniformly and rejected until one satisfies all
+/// the constraints, so the result is uniform over the allowed permutations.
+/// The expected number of attempts is the total number of permutations
+/// divided by the number of allowed ones, which grows exponentially with
+/// dense constraints. After a fixed number of attempts the constraints are
+/// considered infeasible and an error is returned, leaving `data` unchanged.
+/// An error is also returned if a pair refers to an index out of bounds.
+///
+/// # Examples
+/// ```
+/// use shuffle::constrained;
+/// use rand::rngs::mock::StepRng;
+///
+/// let mut rng = StepRng::new(2, 13);
+/// let mut input = vec!['a', 'b', 'c'];
+///
+/// constrained::shuffle(&mut input, &[(0, 0), (1, 1), (2, 2)], &mut rng).unwrap();
+/// assert_ne!(input[0], 'a');
+/// assert_ne!(input[1], 'b');
+/// assert_ne!(input[2], 'c');
+/// ```
+pub fn shuffle<T, R>(
+    data: &mut Vec<T>,
+    forbidden: &[(usize, usize)],
+    rng: &mut R,
+) -> Result<(), ShuffleError>
+where
+    T: Clone,
+    R: Rng + ?Sized,
+{
+    let len = data.len();
+    if forbidden.iter().any(|&(e, p)| e >= len || p >= len) {
+        return Err(ShuffleError::OutOfBounds);
+    }
+    let forbidden: HashSet<(usize, usize)> = forbidden.iter().copied().collect();
+
+    let mut fy = FisherYates::default();
+    let mut permutation: Vec<usize> = (0..len).collect();
+    for _ in 0..MAX_ATTEMPTS {
+        fy.shuffle(&mut permutation, rng)
+            .expect("Fisher-Yates never fails");
+        let allowed = permutation
+            .iter()
+            .enumerate()
+            .all(|(position, &element)| !forbidden.contains(&(element, position)));
+        if allowed {
+            *data = permutation.iter().map(|&i| data[i].clone()).collect();
+            return Ok(());
+        }
+    }
+    Err(ShuffleError::Infeasible("constraints are infeasible"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_constrained_shuffle() {
+        let mut rng = StdRng::seed_from_u64(21);
+        let forbidden = [(0, 0), (0, 1), (3, 5), (7, 2), (9, 9), (4, 0)];
+        let input_data: Vec<usize> = (0..10).collect();
+        for _ in 0..100 {
+            let mut target = input_data.clone();
+            shuffle(&mut target, &forbidden, &mut rng).unwrap();
+            for &(element, position) in forbidden.iter() {
+                assert_ne!(target[position], element);
+            }
+            target.sort_unstable();
+            assert_eq!(target, input_data);
+        }
+    }
+
+    #[test]
+    fn test_infeasible_constraints() {
+        let mut rng = StdRng::seed_from_u64(21);
+        let input_data = vec![1, 2, 3];
+        let mut target = input_data.clone();
+        let forbidden = [(0, 0), (0, 1), (0, 2)];
+        assert_eq!(
+            shuffle(&mut target, &forbidden, &mut rng),
+            Err(ShuffleError::Infeasible("constraints are infeasible"))
+        );
+        assert_eq!(
+            shuffle(&mut target, &[(0, 3)], &mut rng),
+            Err(ShuffleError::OutOfBounds)
+        );
+        assert_eq!(target, input_data);
+    }
+}
@@ -0,0 +1,159 @@
+//! A deck of cards with a draw pile and a discard pile, as in card games.
+
+use rand::Rng;
+
+use crate::fy::FisherYates;
+
+/// A deck made of a draw pile and a discard pile.
+///
+/// [`draw`](Deck::draw) takes cards from the draw pile and
+/// [`discard`](Deck::discard) puts them on the discard pile. Once the draw pile runs
+/// out, the discard pile is shuffled with Fisher-Yates and becomes the
+/// new draw pile, so no card is ever lost or duplicated.
+///
+/// # Examples
+/// ```
+/// use shuffle::deck::Deck;
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let mut rng = StdRng::seed_from_u64(42);
+/// let mut deck = Deck::new(vec!["strike", "defend", "bash"], &mut rng);
+///
+/// for _ in 0..3 {
+///     let card = deck.draw(&mut rng).unwrap();
+///     deck.discard(card);
+/// }
+/// // The draw pile is empty, so the discard pile gets reshuffled.
+/// assert!(deck.draw(&mut rng).is_some());
+/// assert_eq!(deck.draw_pile().len(), 2);
+/// ```
+#[derive(Debug)]
+pub struct Deck<T> {
+    draw: Vec<T>,
+    discard: Vec<T>,
+    fy: FisherYates,
+}
+
+impl<T> Deck<T> {
+    /// Creates a deck whose draw pile is `cards`, shuffled.
+    pub fn new<R>(mut cards: Vec<T>, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        let mut fy = FisherYates::default();
+        fy.shuffle_in_place(&mut cards, rng);
+        Deck {
+            draw: cards,
+            discard: Vec::new(),
+            fy,
+        }
+    }
+
+    /// Draws the top card of the draw pile.
+    ///
+    /// If the draw pile is empty, the discard pile is shuffled into it
+    /// first. Returns `None` if both piles are empty.
+    pub fn draw<R>(&mut self, rng: &mut R) -> Option<T>
+    where
+        R: Rng + ?Sized,
+    {
+        if self.draw.is_empty() {
+            std::mem::swap(&mut self.draw, &mut self.discard);
+            self.fy.shuffle_in_place(&mut self.draw, rng);
+        }
+        self.draw.pop()
+    }
+
+    /// Puts `card` on the discard pile.
+    pub fn discard(&mut self, card: T) {
+        self.discard.push(card);
+    }
+
+    /// Returns the draw pile; its last card is drawn next.
+    pub fn draw_pile(&self) -> &[T] {
+        &self.draw
+    }
+
+    /// Returns the discard pile, in the order the cards were discarded.
+    pub fn discard_pile(&self) -> &[T] {
+        &self.discard
+    }
+
+    /// Returns the number of cards in both piles.
+    pub fn len(&self) -> usize {
+        self.draw.len() + self.discard.len()
+    }
+
+    /// Tells whether both piles are empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// Not derived, as that would require `T: Default`.
+impl<T> Default for Deck<T> {
+    fn default() -> Self {
+        Deck {
+            draw: Vec::new(),
+            discard: Vec::new(),
+            fy: FisherYates::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_draw_and_reshuffle() {
+        let mut rng = StdRng::seed_from_u64(44);
+        let cards: Vec<u32> = (0..10).collect();
+        let mut deck = Deck::new(cards.clone(), &mut rng);
+        assert_ne!(deck.draw_pile(), &cards[..]);
+
+        let mut hand = Vec::new();
+        let mut drawn = Vec::new();
+        for _ in 0..35 {
+            let card = deck.draw(&mut rng).unwrap();
+            drawn.push(card);
+            hand.push(card);
+            // Keep the last three cards in hand.
+            if hand.len() > 3 {
+                deck.discard(hand.remove(0));
+            }
+            assert_eq!(deck.len() + hand.len(), cards.len());
+
+            let mut all: Vec<u32> = deck
+                .draw_pile()
+                .iter()
+                .chain(deck.discard_pile())
+                .chain(&hand)
+                .copied()
+                .collect();
+            all.sort_unstable();
+            assert_eq!(all, cards);
+        }
+        // The first reshuffle only came after all the cards had been drawn.
+        let mut first_round = drawn[..10].to_vec();
+        first_round.sort_unstable();
+        assert_eq!(first_round, cards);
+    }
+
+    #[test]
+    fn test_empty_deck() {
+        struct Card;
+        assert!(Deck::<Card>::default().is_empty());
+
+        let mut rng = StdRng::seed_from_u64(44);
+        let mut deck: Deck<u32> = Deck::new(Vec::new(), &mut rng);
+        assert!(deck.is_empty());
+        assert_eq!(deck.draw(&mut rng), None);
+        deck.discard(7);
+        assert_eq!(deck.draw(&mut rng), Some(7));
+        assert_eq!(deck.draw(&mut rng), None);
+    }
+}
@@ -0,0 +1,354 @@
+//! Random derangements, i.e. permutations without fixed points.
+
+use rand::{Rng, RngCore};
+
+use crate::permutation::apply_permutation;
+use crate::shuffler::{ShuffleError, Shuffler};
+
+/// The largest `n` for which the derangement number `D(n)` fits in a `u128`.
+const MAX_EXACT: usize = 34;
+
+/// A shuffler that produces uniformly random derangements, i.e.
+/// permutations in which no element stays at its position.
+///
+/// Unlike [`Sattolo`](crate::sattolo::Sattolo), which only produces
+/// permutations with a single cycle, every derangement of `n` elements is
+/// equally likely. It uses the early refusal method: a Fisher-Yates
+/// shuffle of the indices, started over as soon as an index lands at its
+/// own position. The fraction of permutations that are derangements is
+/// `D(n) / n!`, which quickly approaches `1 / e`, so on average about
+/// `e ≈ 2.72` attempts are needed, and the rejected ones are usually cut
+/// short. For a variant that never rejects, see [`shuffle_recursive`].
+///
+/// Empty data is left as is, and a single element is an error, as it has
+/// no derangements.
+///
+/// # Examples
+/// ```
+/// use shuffle::shuffler::Shuffler;
+/// use shuffle::derangement::Derangement;
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let mut rng = StdRng::seed_from_u64(42);
+/// let mut derangement = Derangement::default();
+///
+/// // Nobody draws their own name.
+/// let recipients =
+///     Shuffler::<()>::shuffle_indices(&mut derangement, 5, &mut rng).unwrap();
+/// for (giver, &recipient) in recipients.iter().enumerate() {
+///     assert_ne!(giver, recipient);
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct Derangement {}
+
+impl<T> Shuffler<T> for Derangement {
+    fn shuffle_slice<R>(&mut self, data: &mut [T], rng: &mut R) -> Result<(), ShuffleError>
+    where
+        T: Clone,
+        R: RngCore + ?Sized,
+    {
+        let permutation = early_refusal(data.len(), rng)?;
+        apply_permutation(data, &permutation)
+    }
+
+    fn shuffle_indices<R>(&mut self, len: usize, rng: &mut R) -> Result<Vec<usize>, ShuffleError>
+    where
+        R: RngCore + ?Sized,
+    {
+        early_refusal(len, rng)
+    }
+
+    /// Every element moves to a uniformly random other position, so
+    /// this is `(n + 1) / 3`, a bit more than for a uniform shuffle.
+    fn expected_mean_displacement(&self, n: usize) -> f64 {
+        if n < 2 {
+            return 0.0;
+        }
+        (n + 1) as f64 / 3.0
+    }
+}
+
+/// Returns a uniformly random derangement of `0..len`.
+fn early_refusal<R>(len: usize, rng: &mut R) -> Result<Vec<usize>, ShuffleError>
+where
+    R: Rng + ?Sized,
+{
+    if len == 1 {
+        return Err(ShuffleError::Infeasible(
+            "a single element has no derangement",
+        ));
+    }
+    let mut permutation: Vec<usize> = (0..len).collect();
+    'attempt: loop {
+        // Position `i` is final after its swap, so a fixed point there
+        // already dooms the attempt.
+        for i in (1..len).rev() {
+            let j = rng.gen_range(0..=i);
+            permutation.swap(i, j);
+            if permutation[i] == i {
+                continue 'attempt;
+            }
+        }
+        if len == 0 || permutation[0] != 0 {
+            return Ok(permutation);
+        }
+    }
+}
+
+/// Shuffles `data` so that no element stays at its position, with every
+/// such arrangement equally likely.
+///
+/// The simple way to get a derangement is to shuffle until there are no
+/// fixed points, e.g. with [`constrained::shuffle`](crate::constrained::shuffle)
+/// forbidding `(i, i)` for every `i`; about 63% of the attempts are then
+/// thrown away. This function implements the algorithm of Martínez,
+/// Panholzer and Prodinger instead, which builds the derangement directly:
+/// it walks the positions from the back, swaps every element that is not
+/// yet placed with a random earlier one that isn't placed either, and
+/// occasionally closes a cycle of length two. The candidates are kept in
+/// a pool, so it never rejects anything and runs in linear time.
+///
+/// Cycles are closed with a probability given by derangement numbers.
+/// Up to `n = 34` it is compared exactly in integers; above that the ratio
+/// is computed with floating point numbers, which is off by at most a few
+/// units in the last place.
+///
+/// Returns an error if `data` has exactly one element, as it has no
+/// derangements.
+///
+/// *Martínez, Conrado, Alois Panholzer, and Helmut Prodinger.
+/// "Generating random derangements." ANALCO (2008): 234-240.*
+///
+/// # Examples
+/// ```
+/// use shuffle::derangement;
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let mut rng = StdRng::seed_from_u64(5);
+/// let mut input = vec![0, 1, 2, 3, 4];
+///
+/// derangement::shuffle_recursive(&mut input, &mut rng).unwrap();
+/// for (i, &x) in input.iter().enumerate() {
+///     assert_ne!(i, x);
+/// }
+/// ```
+pub fn shuffle_recursive<T, R>(data: &mut [T], rng: &mut R) -> Result<(), ShuffleError>
+where
+    R: Rng + ?Sized,
+{
+    if data.len() == 1 {
+        return Err(ShuffleError::Infeasible(
+            "a single element has no derangement",
+        ));
+    }
+    let exact = derangement_numbers(data.len().min(MAX_EXACT));
+    let ratios = derangement_ratios(data.len(), &exact);
+    // The indices that are neither visited nor marked yet, i.e. the
+    // candidates for `j`, together with the position of each in the pool.
+    let mut pool: Vec<usize> = (0..data.len()).collect();
+    let mut position_in_pool: Vec<usize> = (0..data.len()).collect();
+    let mut marked = vec![false; data.len()];
+    let mut unplaced = data.len();
+    let mut i = data.len();
+    while unplaced >= 2 {
+        i -= 1;
+        if marked[i] {
+            continue;
+        }
+        remove_from_pool(&mut pool, &mut position_in_pool, i);
+        let j = pool[rng.gen_range(0..pool.len())];
+        data.swap(i, j);
+        if closes_cycle(unplaced, &exact, &ratios, rng) {
+            marked[j] = true;
+            remove_from_pool(&mut pool, &mut position_in_pool, j);
+            unplaced -= 1;
+        }
+        unplaced -= 1;
+    }
+    Ok(())
+}
+
+/// Removes `index` from `pool` in constant time.
+fn remove_from_pool(pool: &mut Vec<usize>, position_in_pool: &mut [usize], index: usize) {
+    let position = position_in_pool[index];
+    let last = pool[pool.len() - 1];
+    pool.swap_remove(position);
+    if last != index {
+        position_in_pool[last] = position;
+    }
+}
+
+/// Returns `D(0), ..., D(n)`.
+fn derangement_numbers(n: usize) -> Vec<u128> {
+    let mut numbers = vec![1, 0];
+    for k in 2..=n {
+        numbers.push((k as u128 - 1) * (numbers[k - 1] + numbers[k - 2]));
+    }
+    numbers
+}
+
+/// Returns `D(k) / D(k - 1)` for `k` from `MAX_EXACT` up to `n - 1`,
+/// using `D(k) = (k - 1) (D(k - 1) + D(k - 2))`.
+fn derangement_ratios(n: usize, exact: &[u128]) -> Vec<f64> {
+    let mut ratios = Vec::new();
+    if n > MAX_EXACT {
+        ratios.push(exact[MAX_EXACT] as f64 / exact[MAX_EXACT - 1] as f64);
+        for k in (MAX_EXACT + 1)..n {
+            let previous = ratios[ratios.len() - 1];
+            ratios.push((k - 1) as f64 * (1.0 + 1.0 / previous));
+        }
+    }
+    ratios
+}
+
+/// Decides with probability `(u - 1) D(u - 2) / D(u)`, which is equal to
+/// `D(u - 2) / (D(u - 1) + D(u - 2))`, whether a cycle is closed when
+/// `u` elements are still unplaced.
+fn closes_cycle<R>(u: usize, exact: &[u128], ratios: &[f64], rng: &mut R) -> bool
+where
+    R: Rng + ?Sized,
+{
+    if u <= MAX_EXACT {
+        let total = exact[u - 1] + exact[u - 2];
+        return rng.gen_range(0..total) < exact[u - 2];
+    }
+    let ratio = ratios[u - 1 - MAX_EXACT];
+    rng.gen_bool(1.0 / (1.0 + ratio))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constrained;
+    use crate::testing::{chi_square_critical_value, counts_chi_square};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_no_fixed_points() {
+        let mut rng = StdRng::seed_from_u64(32);
+        for &len in &[0, 2, 3, 10, 33, 34, 35, 100] {
+            for _ in 0..20 {
+                let mut target: Vec<usize> = (0..len).collect();
+                shuffle_recursive(&mut target, &mut rng).unwrap();
+                for (i, &x) in target.iter().enumerate() {
+                    assert_ne!(i, x);
+                }
+                target.sort_unstable();
+                assert_eq!(target, (0..len).collect::<Vec<_>>());
+            }
+        }
+        assert!(shuffle_recursive(&mut [1], &mut rng).is_err());
+    }
+
+    #[test]
+    fn test_constant_rng_terminates() {
+        // `gen_range` always returns the lowest value with this rng.
+        let mut rng = rand::rngs::mock::StepRng::new(2, 13);
+        let mut target: Vec<usize> = (0..5).collect();
+        shuffle_recursive(&mut target, &mut rng).unwrap();
+        for (i, &x) in target.iter().enumerate() {
+            assert_ne!(i, x);
+        }
+    }
+
+    #[test]
+    fn test_same_distribution_as_rejection() {
+        let mut rng = StdRng::seed_from_u64(32);
+        let trials = 18_000;
+        let forbidden: Vec<(usize, usize)> = (0..4).map(|i| (i, i)).collect();
+        let mut recursive = HashMap::new();
+        let mut rejection = HashMap::new();
+        for _ in 0..trials {
+            let mut target = vec![0, 1, 2, 3];
+            shuffle_recursive(&mut target, &mut rng).unwrap();
+            *recursive.entry(target).or_insert(0) += 1;
+
+            let mut target = vec![0, 1, 2, 3];
+            constrained::shuffle(&mut target, &forbidden, &mut rng).unwrap();
+            *rejection.entry(target).or_insert(0) += 1;
+        }
+
+        // There are D(4) = 9 derangements of 4 elements.
+        assert_eq!(recursive.len(), 9);
+        assert_eq!(rejection.len(), 9);
+        let critical = chi_square_critical_value(8, 0.999);
+        for counts in [recursive, rejection] {
+            let counts: Vec<usize> = counts.into_values().collect();
+            assert!(counts_chi_square(&counts) < critical);
+        }
+    }
+
+    #[test]
+    fn test_derangement_shuffler() {
+        let mut rng = StdRng::seed_from_u64(32);
+        let mut derangement = Derangement::default();
+        for &len in &[2, 3, 10, 100] {
+            for _ in 0..20 {
+                let perm =
+                    Shuffler::<()>::shuffle_indices(&mut derangement, len, &mut rng).unwrap();
+                assert!(perm.iter().enumerate().all(|(i, &p)| i != p));
+
+                let mut target: Vec<usize> = (0..len).collect();
+                derangement.shuffle(&mut target, &mut rng).unwrap();
+                assert!(target.iter().enumerate().all(|(i, &x)| i != x));
+                target.sort_unstable();
+                assert_eq!(target, (0..len).collect::<Vec<_>>());
+            }
+        }
+
+        let mut empty: Vec<u8> = vec![];
+        derangement.shuffle(&mut empty, &mut rng).unwrap();
+        assert_eq!(
+            derangement.shuffle(&mut vec![7], &mut rng),
+            Err(ShuffleError::Infeasible(
+                "a single element has no derangement"
+            ))
+        );
+        assert!(Shuffler::<()>::shuffle_indices(&mut derangement, 1, &mut rng).is_err());
+    }
+
+    #[test]
+    fn test_derangement_expected_mean_displacement() {
+        let mut rng = StdRng::seed_from_u64(32);
+        let mut derangement = Derangement::default();
+        let trials = 2000;
+        let mut total = 0.0;
+        for _ in 0..trials {
+            let perm = Shuffler::<()>::shuffle_indices(&mut derangement, 20, &mut rng).unwrap();
+            total += crate::analysis::mean_move_distance(&perm);
+        }
+        let expected = Shuffler::<()>::expected_mean_displacement(&derangement, 20);
+        assert!((total / trials as f64 - expected).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_derangement_shuffler_uniform() {
+        let mut rng = StdRng::seed_from_u64(32);
+        let mut derangement = Derangement::default();
+        let mut counts = HashMap::new();
+        for _ in 0..18_000 {
+            let mut target = vec![0, 1, 2, 3];
+            derangement.shuffle(&mut target, &mut rng).unwrap();
+            *counts.entry(target).or_insert(0) += 1;
+        }
+        // There are D(4) = 9 derangements of 4 elements.
+        assert_eq!(counts.len(), 9);
+        let counts: Vec<usize> = counts.into_values().collect();
+        assert!(counts_chi_square(&counts) < chi_square_critical_value(8, 0.999));
+    }
+
+    #[test]
+    fn test_derangement_numbers() {
+        assert_eq!(derangement_numbers(6), vec![1, 0, 1, 2, 9, 44, 265]);
+        let exact = derangement_numbers(MAX_EXACT);
+        let ratios = derangement_ratios(MAX_EXACT + 2, &exact);
+        let expected = exact[MAX_EXACT - 1] as f64 / exact[MAX_EXACT - 2] as f64;
+        let next = (MAX_EXACT - 1) as f64 * (1.0 + 1.0 / expected);
+        assert!((ratios[0] - next).abs() / next < 1e-12);
+    }
+}
@@ -0,0 +1,100 @@
+//! Helpers for randomized experimental design.
+
+use rand::Rng;
+
+use crate::fy::FisherYates;
+use crate::shuffler::Shuffler;
+
+/// Generates a random `n x n` Latin square over the symbols `0..n`.
+///
+/// The square is built from the cyclic square `(row + column) % n`
+/// by shuffling its rows and its columns, so every row and every
+/// column of the result is a permutation of `0..n`.
+///
+/// Note that this does not sample uniformly from all Latin squares
+/// of the given size, only from the ones isotopic to the cyclic one.
+///
+/// # Examples
+/// ```
+/// use shuffle::design::random_latin_square;
+/// use rand::rngs::mock::StepRng;
+///
+/// let mut rng = StepRng::new(2, 13);
+/// let square = random_latin_square(3, &mut rng);
+///
+/// assert_eq!(square.len(), 3);
+/// assert!(square.iter().all(|row| row.len() == 3));
+/// ```
+pub fn random_latin_square<R>(n: usize, rng: &mut R) -> Vec<Vec<usize>>
+where
+    R: Rng + ?Sized,
+{
+    let mut fy = FisherYates::default();
+    let mut rows: Vec<usize> = (0..n).collect();
+    let mut columns: Vec<usize> = (0..n).collect();
+    fy.shuffle(&mut rows, rng)
+        .expect("Fisher-Yates never fails");
+    fy.shuffle(&mut columns, rng)
+        .expect("Fisher-Yates never fails");
+
+    let square: Vec<Vec<usize>> = rows
+        .iter()
+        .map(|row| columns.iter().map(|column| (row + column) % n).collect())
+        .collect();
+    debug_assert!(is_latin_square(&square));
+    square
+}
+
+/// Checks whether every row and every column of `square` is
+/// a permutation of `0..square.len()`.
+fn is_latin_square(square: &[Vec<usize>]) -> bool {
+    let n = square.len();
+    square.iter().all(|row| row.len() == n)
+        && square
+            .iter()
+            .all(|row| is_line_permutation(row.iter().copied(), n))
+        && (0..n).all(|column| is_line_permutation(square.iter().map(|row| row[column]), n))
+}
+
+fn is_line_permutation<I>(line: I, n: usize) -> bool
+where
+    I: IntoIterator<Item = usize>,
+{
+    let mut seen = vec![false; n];
+    line.into_iter()
+        .all(|symbol| symbol < n && !std::mem::replace(&mut seen[symbol], true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_random_latin_square() {
+        let mut rng = StdRng::seed_from_u64(5);
+        for n in 0..12 {
+            let square = random_latin_square(n, &mut rng);
+            assert_eq!(square.len(), n);
+            for row in square.iter() {
+                let mut sorted = row.clone();
+                sorted.sort_unstable();
+                assert_eq!(sorted, (0..n).collect::<Vec<_>>());
+            }
+            for column in 0..n {
+                let mut sorted: Vec<usize> = square.iter().map(|row| row[column]).collect();
+                sorted.sort_unstable();
+                assert_eq!(sorted, (0..n).collect::<Vec<_>>());
+            }
+        }
+    }
+
+    #[test]
+    fn test_is_latin_square() {
+        assert!(is_latin_square(&[]));
+        assert!(is_latin_square(&[vec![0, 1], vec![1, 0]]));
+        assert!(!is_latin_square(&[vec![0, 1], vec![0, 1]]));
+        assert!(!is_latin_square(&[vec![0, 1], vec![1]]));
+    }
+}
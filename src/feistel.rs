@@ -0,0 +1,225 @@
+//! A pseudo-random permutation of `0..len` computed one index at a time.
+//!
+//! Unlike the shufflers, which rearrange a collection held in memory,
+//! [`FeistelPermuter`] maps every index to its position on demand, so it
+//! takes the same constant space for any `len`. This is useful to visit a
+//! huge range in a scattered order, e.g. to hand out unique but
+//! non-sequential identifiers.
+
+use rand::RngCore;
+
+/// The number of rounds of the Feistel network.
+const ROUNDS: usize = 8;
+
+/// A pseudo-random bijection of `0..len` onto itself.
+///
+/// The bijection is a balanced Feistel network over the smallest even
+/// number of bits `2h` with `2^(2h) >= len`, whose round function mixes
+/// a `h`-bit half with a random key. Every round is invertible, so the
+/// network permutes `0..2^(2h)`. Indices that end up at `len` or above are
+/// fed through the network again (cycle walking) until they land in
+/// `0..len`: as the network permutes a finite set, following it from any
+/// index of `0..len` must come back to `0..len`, and two indices can't
+/// meet on the way, so the result is a bijection of `0..len`. As
+/// `2^(2h) <= 4 * len`, at most 4 passes are needed on average.
+///
+/// The order is determined by the keys drawn from the generator, but it
+/// is not a uniformly random permutation: there are far more permutations
+/// of a large range than keys. Use a shuffler when the distribution
+/// matters.
+///
+/// # Examples
+/// ```
+/// use shuffle::feistel::FeistelPermuter;
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let mut rng = StdRng::seed_from_u64(42);
+/// let permuter = FeistelPermuter::new(1 << 30, &mut rng);
+///
+/// let first: Vec<usize> = (0..5).map(|i| permuter.permute(i)).collect();
+/// for &id in &first {
+///     assert!(id < 1 << 30);
+///     assert_eq!(permuter.permute(permuter.inverse(id)), id);
+/// }
+///
+/// let small = FeistelPermuter::new(10, &mut rng);
+/// let mut all: Vec<usize> = small.iter().collect();
+/// all.sort();
+/// assert_eq!(all, (0..10).collect::<Vec<_>>());
+/// ```
+#[derive(Clone, Debug)]
+pub struct FeistelPermuter {
+    len: usize,
+    half_bits: u32,
+    keys: [u64; ROUNDS],
+}
+
+impl FeistelPermuter {
+    /// Creates a permuter of `0..len` with keys drawn from `rng`.
+    pub fn new<R>(len: usize, rng: &mut R) -> Self
+    where
+        R: RngCore + ?Sized,
+    {
+        let bits = usize::BITS - len.saturating_sub(1).leading_zeros();
+        let mut keys = [0; ROUNDS];
+        keys.iter_mut().for_each(|key| *key = rng.next_u64());
+        Self {
+            len,
+            half_bits: bits.div_ceil(2).max(1),
+            keys,
+        }
+    }
+
+    /// Returns the length of the permuted range.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the permuted range is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the position of `index`.
+    ///
+    /// # Panics
+    ///
+    /// If `index` is not in `0..len`.
+    pub fn permute(&self, index: usize) -> usize {
+        assert!(index < self.len, "index {} out of range", index);
+        let mut value = index as u64;
+        loop {
+            value = self.encrypt(value);
+            if value < self.len as u64 {
+                return value as usize;
+            }
+        }
+    }
+
+    /// Returns the index whose position is `position`, i.e. the inverse of
+    /// [`permute`](FeistelPermuter::permute).
+    ///
+    /// # Panics
+    ///
+    /// If `position` is not in `0..len`.
+    pub fn inverse(&self, position: usize) -> usize {
+        assert!(position < self.len, "position {} out of range", position);
+        let mut value = position as u64;
+        loop {
+            value = self.decrypt(value);
+            if value < self.len as u64 {
+                return value as usize;
+            }
+        }
+    }
+
+    /// Returns the positions of `0..len` in order, i.e. the whole range in
+    /// the permuted order.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.len).map(move |index| self.permute(index))
+    }
+
+    fn mask(&self) -> u64 {
+        (1 << self.half_bits) - 1
+    }
+
+    fn encrypt(&self, value: u64) -> u64 {
+        let mask = self.mask();
+        let mut left = value >> self.half_bits;
+        let mut right = value & mask;
+        for &key in &self.keys {
+            let next = left ^ (round_function(right, key) & mask);
+            left = right;
+            right = next;
+        }
+        (left << self.half_bits) | right
+    }
+
+    fn decrypt(&self, value: u64) -> u64 {
+        let mask = self.mask();
+        let mut left = value >> self.half_bits;
+        let mut right = value & mask;
+        for &key in self.keys.iter().rev() {
+            let previous = right ^ (round_function(left, key) & mask);
+            right = left;
+            left = previous;
+        }
+        (left << self.half_bits) | right
+    }
+}
+
+/// Mixes `half` with `key`, using the finalizer of SplitMix64.
+fn round_function(half: u64, key: u64) -> u64 {
+    let mut z = half ^ key;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_bijection() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let lengths = (1..300).chain([511, 512, 513, 1023, 1024, 1025, 4096, 10_000]);
+        for len in lengths {
+            let permuter = FeistelPermuter::new(len, &mut rng);
+            let mut seen = vec![false; len];
+            for (index, position) in permuter.iter().enumerate() {
+                assert!(!seen[position], "len {}: {} hit twice", len, position);
+                seen[position] = true;
+                assert_eq!(permuter.inverse(position), index);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn test_huge_range() {
+        let mut rng = StdRng::seed_from_u64(42);
+        for &len in &[1usize << 40, (1 << 40) + 1, usize::MAX] {
+            let permuter = FeistelPermuter::new(len, &mut rng);
+            for index in (0..1000).chain(len - 1000..len) {
+                let position = permuter.permute(index);
+                assert!(position < len);
+                assert_eq!(permuter.inverse(position), index);
+            }
+        }
+    }
+
+    #[test]
+    fn test_keys_change_the_order() {
+        let first = FeistelPermuter::new(1000, &mut StdRng::seed_from_u64(1));
+        let again = FeistelPermuter::new(1000, &mut StdRng::seed_from_u64(1));
+        let second = FeistelPermuter::new(1000, &mut StdRng::seed_from_u64(2));
+        assert!(first.iter().eq(again.iter()));
+        assert!(!first.iter().eq(second.iter()));
+        // Far from the identity.
+        let fixed_points = first.iter().enumerate().filter(|(i, p)| i == p).count();
+        assert!(fixed_points < 10, "fixed points: {}", fixed_points);
+    }
+
+    #[test]
+    fn test_empty_and_single() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let empty = FeistelPermuter::new(0, &mut rng);
+        assert!(empty.is_empty());
+        assert_eq!(empty.iter().count(), 0);
+
+        let single = FeistelPermuter::new(1, &mut rng);
+        assert_eq!(single.len(), 1);
+        assert_eq!(single.permute(0), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn test_out_of_range() {
+        let mut rng = StdRng::seed_from_u64(42);
+        FeistelPermuter::new(10, &mut rng).permute(10);
+    }
+}
@@ -1,9 +1,21 @@
 //! Implementation of Fisher-Yates algorithm.
 //!
 
-use rand::Rng;
+use core::fmt;
+use core::iter::FusedIterator;
+use core::ops::Range;
+use core::slice::ChunksMut;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
 
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use rand::{Rng, RngCore};
+
+use crate::shuffler::ShuffleError;
+#[cfg(feature = "alloc")]
 use crate::shuffler::Shuffler;
+use crate::swap::SwapSlice;
 
 /// Implementation of Fisher-Yates algorithm.
 ///
@@ -18,24 +30,1296 @@ use crate::shuffler::Shuffler;
 ///
 /// let mut input = vec![1, 2, 3, 4, 5];
 ///
-/// fy.shuffle(&mut input, &mut rng);
+/// fy.shuffle(&mut input, &mut rng).unwrap();
 /// assert_eq!(&input, &[2, 3, 4, 5, 1]);
 /// ```
-#[derive(Debug, Default)]
-pub struct FisherYates {
-    buffer: [u8; std::mem::size_of::<usize>()],
+///
+/// # Trait bounds
+///
+/// The algorithm only swaps elements, so it places no requirements on them.
+/// `T: Clone` is required solely by the [`Shuffler`] trait signature.
+///
+/// # Compatibility with `rand`
+///
+/// [`rand::seq::SliceRandom::shuffle`] runs the same algorithm, swapping
+/// from the last position down to the first, so both are uniform and
+/// interchangeable. They differ in how an index is drawn: `rand` samples
+/// a `u32` whenever the bound fits, while [`shuffle`](Shuffler::shuffle)
+/// always samples a `usize`. Hence for the same seed the two produce
+/// different permutations and consume different amounts of randomness.
+/// Use [`shuffle_rand_compatible`](FisherYates::shuffle_rand_compatible)
+/// to get exactly the output of `rand`.
+///
+/// # Empty and single-element inputs
+///
+/// Shuffling fewer than two elements is a no-op that draws nothing from
+/// the generator, in every method, so the generator is left in the same
+/// state for whatever uses it next.
+///
+/// # Reproducibility
+///
+/// How `rand` turns random words into a `usize` in a range is not part of
+/// its stability guarantees and depends on the width of `usize`, so the
+/// output of [`shuffle`](Shuffler::shuffle) for a given seed may change
+/// between versions of `rand` or platforms. Use
+/// [`shuffle_deterministic`](FisherYates::shuffle_deterministic) when the
+/// permutation has to stay the same everywhere.
+///
+/// # Index sampling
+///
+/// Every swap draws a random index through an [`IndexSampler`]. The
+/// default one, [`GenRangeSampler`], uses [`Rng::gen_range`]; another
+/// method of sampling from a range can be plugged in with
+/// [`with_sampler`](FisherYates::with_sampler), e.g. to consume less
+/// randomness or to pin the method down for reproducibility. The
+/// methods that emulate a fixed algorithm,
+/// [`shuffle_rand_compatible`](FisherYates::shuffle_rand_compatible) and
+/// [`shuffle_deterministic`](FisherYates::shuffle_deterministic), don't
+/// use the sampler.
+#[derive(Debug)]
+pub struct FisherYates<S = GenRangeSampler> {
+    sampler: S,
+}
+
+// Not derived, so that `FisherYates::default()` needs no type annotation.
+impl Default for FisherYates {
+    fn default() -> Self {
+        FisherYates::with_sampler(GenRangeSampler)
+    }
+}
+
+/// A method of drawing a uniformly random index, used by [`FisherYates`]
+/// for every swap.
+///
+/// The shuffle is only uniform if the sampler is: every index in
+/// `0..=max` has to be equally likely, whatever indices were drawn
+/// before.
+///
+/// # Examples
+/// ```
+/// use shuffle::fy::{FisherYates, IndexSampler};
+/// use rand::rngs::StdRng;
+/// use rand::{RngCore, SeedableRng};
+///
+/// /// Masks random words to the bit length of `max`, rejecting the
+/// /// results above it.
+/// #[derive(Debug)]
+/// struct Bitmask;
+///
+/// impl IndexSampler for Bitmask {
+///     fn sample_index<R>(&mut self, max: usize, rng: &mut R) -> usize
+///     where
+///         R: RngCore + ?Sized,
+///     {
+///         let mask = u64::MAX >> (max as u64).leading_zeros();
+///         loop {
+///             let index = rng.next_u64() & mask;
+///             if index <= max as u64 {
+///                 return index as usize;
+///             }
+///         }
+///     }
+/// }
+///
+/// let mut rng = StdRng::seed_from_u64(42);
+/// let mut fy = FisherYates::with_sampler(Bitmask);
+///
+/// let mut input = [1, 2, 3, 4, 5];
+/// fy.shuffle_in_place(&mut input, &mut rng);
+/// input.sort();
+/// assert_eq!(input, [1, 2, 3, 4, 5]);
+/// ```
+pub trait IndexSampler {
+    /// Returns a uniformly random index in `0..=max`.
+    fn sample_index<R>(&mut self, max: usize, rng: &mut R) -> usize
+    where
+        R: RngCore + ?Sized;
 }
 
-impl<T> Shuffler<T> for FisherYates {
-    fn shuffle<R>(&mut self, data: &mut Vec<T>, rng: &mut R) -> Result<(), &str>
+/// The default [`IndexSampler`], which draws with [`Rng::gen_range`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GenRangeSampler;
+
+impl IndexSampler for GenRangeSampler {
+    fn sample_index<R>(&mut self, max: usize, rng: &mut R) -> usize
+    where
+        R: RngCore + ?Sized,
+    {
+        rng.gen_range(0..(max + 1))
+    }
+}
+
+/// Reusable scratch space for
+/// [`FisherYates::shuffle_indices_into`].
+///
+/// Keeping one of these around and passing it to every call avoids
+/// allocating a new vector for each permutation.
+///
+/// Available with the `alloc` feature.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug, Default)]
+pub struct ShuffleBuffers {
+    /// The permutation computed by the last call.
+    pub perm: Vec<usize>,
+}
+
+#[cfg(feature = "alloc")]
+impl<T, S> Shuffler<T> for FisherYates<S>
+where
+    S: IndexSampler,
+{
+    fn shuffle_slice<R>(&mut self, data: &mut [T], rng: &mut R) -> Result<(), ShuffleError>
     where
         T: Clone,
+        R: RngCore + ?Sized,
+    {
+        fisher_yates(data, &mut self.sampler, rng);
+        Ok(())
+    }
+
+    fn shuffle_indices<R>(&mut self, len: usize, rng: &mut R) -> Result<Vec<usize>, ShuffleError>
+    where
+        R: RngCore + ?Sized,
+    {
+        Ok(self.shuffle_indices_vec(len, rng))
+    }
+}
+
+impl<S> FisherYates<S>
+where
+    S: IndexSampler,
+{
+    /// Creates a `FisherYates` that draws the indices with `sampler`.
+    pub fn with_sampler(sampler: S) -> Self {
+        Self { sampler }
+    }
+
+    /// Shuffles `data` in place. It can be any [`SwapSlice`], e.g. a slice,
+    /// an array or a `VecDeque`.
+    ///
+    /// This performs the same swaps as [`shuffle`](Shuffler::shuffle), but
+    /// doesn't need the `alloc` feature, so it is the way to shuffle in
+    /// `core`-only environments.
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::fy::FisherYates;
+    /// use rand::rngs::mock::StepRng;
+    ///
+    /// let mut rng = StepRng::new(2, 13);
+    /// let mut fy = FisherYates::default();
+    ///
+    /// let mut input = [1, 2, 3, 4, 5];
+    /// fy.shuffle_in_place(&mut input, &mut rng);
+    /// assert_eq!(&input, &[2, 3, 4, 5, 1]);
+    /// ```
+    pub fn shuffle_in_place<D, R>(&mut self, data: &mut D, rng: &mut R)
+    where
+        D: SwapSlice + ?Sized,
         R: Rng + ?Sized,
     {
+        fisher_yates(data, &mut self.sampler, rng);
+    }
+
+    /// Computes a random permutation of `0..len` without shuffling any data.
+    ///
+    /// This is the same sequence of swaps [`shuffle`](Shuffler::shuffle)
+    /// performs, applied to the indices instead of the elements.
+    ///
+    /// Available with the `alloc` feature.
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::fy::FisherYates;
+    /// use rand::rngs::mock::StepRng;
+    ///
+    /// let mut rng = StepRng::new(2, 13);
+    /// let mut fy = FisherYates::default();
+    ///
+    /// let permutation = fy.shuffle_indices_vec(5, &mut rng);
+    /// assert_eq!(&permutation, &[1, 2, 3, 4, 0]);
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn shuffle_indices_vec<R>(&mut self, len: usize, rng: &mut R) -> Vec<usize>
+    where
+        R: Rng + ?Sized,
+    {
+        let mut permutation: Vec<usize> = (0..len).collect();
+        fisher_yates(&mut permutation, &mut self.sampler, rng);
+        permutation
+    }
+
+    /// Computes a random permutation of `0..len` into `buffers.perm`,
+    /// like [`shuffle_indices_vec`](FisherYates::shuffle_indices_vec).
+    ///
+    /// The previous contents of `buffers.perm` are overwritten, and its
+    /// allocation is reused, so once it has grown to the largest `len`
+    /// nothing is allocated anymore.
+    ///
+    /// Available with the `alloc` feature.
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::fy::{FisherYates, ShuffleBuffers};
+    /// use rand::rngs::mock::StepRng;
+    ///
+    /// let mut rng = StepRng::new(2, 13);
+    /// let mut fy = FisherYates::default();
+    /// let mut buffers = ShuffleBuffers::default();
+    ///
+    /// fy.shuffle_indices_into(&mut buffers, 5, &mut rng);
+    /// assert_eq!(&buffers.perm, &[1, 2, 3, 4, 0]);
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn shuffle_indices_into<R>(&mut self, buffers: &mut ShuffleBuffers, len: usize, rng: &mut R)
+    where
+        R: Rng + ?Sized,
+    {
+        buffers.perm.clear();
+        buffers.perm.extend(0..len);
+        fisher_yates(&mut buffers.perm, &mut self.sampler, rng);
+    }
+
+    /// Fills `dst` with a shuffled copy of `src`, leaving `src` as it is.
+    ///
+    /// This is the inside-out variant of Fisher-Yates: every element of
+    /// `src` is appended to `dst` and swapped with a random one before it,
+    /// so each element is cloned exactly once and nothing is written
+    /// twice. The previous contents of `dst` are dropped, but its
+    /// allocation is reused.
+    ///
+    /// Available with the `alloc` feature.
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::fy::FisherYates;
+    /// use rand::rngs::StdRng;
+    /// use rand::SeedableRng;
+    ///
+    /// let mut rng = StdRng::seed_from_u64(42);
+    /// let mut fy = FisherYates::default();
+    ///
+    /// let src = ["a", "b", "c", "d"];
+    /// let mut dst = Vec::new();
+    /// fy.shuffle_into(&src, &mut dst, &mut rng);
+    /// dst.sort();
+    /// assert_eq!(dst, src);
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn shuffle_into<T, R>(&mut self, src: &[T], dst: &mut Vec<T>, rng: &mut R)
+    where
+        T: Clone,
+        R: Rng + ?Sized,
+    {
+        dst.clear();
+        dst.reserve(src.len());
+        for (i, element) in src.iter().enumerate() {
+            let j = self.sampler.sample_index(i, rng);
+            dst.push(element.clone());
+            dst.swap(i, j);
+        }
+    }
+
+    /// Shuffles only the first `k` positions of `data`, so that `data[..k]`
+    /// is a uniformly random selection of `k` of its elements, in a random
+    /// order.
+    ///
+    /// This runs just `k` steps of Fisher-Yates, each picking a random
+    /// element among the ones not selected yet, so it is much cheaper than
+    /// a full shuffle for a small `k`. The rest of the elements end up in
+    /// `data[k..]`, in an unspecified order. If `k` is at least the length
+    /// of `data`, all of it is shuffled.
+    ///
+    /// Returns the selected part and the rest, both borrowing `data`, so
+    /// nothing is allocated and the rest can be dealt from again.
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::fy::FisherYates;
+    /// use rand::rngs::StdRng;
+    /// use rand::SeedableRng;
+    ///
+    /// let mut rng = StdRng::seed_from_u64(42);
+    /// let mut fy = FisherYates::default();
+    ///
+    /// let mut input: Vec<u32> = (0..1000).collect();
+    /// let (top, rest) = fy.partial_shuffle(&mut input, 3, &mut rng);
+    /// assert_eq!(top.len(), 3);
+    /// assert_eq!(rest.len(), 997);
+    /// ```
+    pub fn partial_shuffle<'a, T, R>(
+        &mut self,
+        data: &'a mut [T],
+        k: usize,
+        rng: &mut R,
+    ) -> (&'a mut [T], &'a mut [T])
+    where
+        R: Rng + ?Sized,
+    {
+        let k = k.min(data.len());
+        // The last element of a full shuffle has nothing to pick from.
+        for i in 0..k.min(data.len().saturating_sub(1)) {
+            let j = i + self.sampler.sample_index(data.len() - 1 - i, rng);
+            data.swap(i, j);
+        }
+        data.split_at_mut(k)
+    }
+
+    /// Returns an iterator over the elements of `data` in a random order,
+    /// which shuffles lazily.
+    ///
+    /// Every call of `next` is one step of
+    /// [`partial_shuffle`](FisherYates::partial_shuffle): it swaps a random
+    /// element of the rest of `data` to the front of it and yields it. So
+    /// taking `k` elements costs `O(k)`, they are a uniformly random
+    /// selection in a random order, and a fully consumed iterator leaves
+    /// `data` just as `partial_shuffle` with `k = data.len()` does.
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::fy::FisherYates;
+    /// use rand::rngs::StdRng;
+    /// use rand::SeedableRng;
+    ///
+    /// let mut rng = StdRng::seed_from_u64(42);
+    /// let mut fy = FisherYates::default();
+    ///
+    /// let mut deck: Vec<u32> = (0..52).collect();
+    /// let hand: Vec<u32> = fy.shuffle_iter(&mut deck, &mut rng).take(5).map(|card| *card).collect();
+    /// assert_eq!(hand.len(), 5);
+    /// assert_eq!(&deck[..5], &hand[..]);
+    /// ```
+    pub fn shuffle_iter<'a, T, R>(
+        &'a mut self,
+        data: &'a mut [T],
+        rng: &'a mut R,
+    ) -> ShuffleIter<'a, T, R, S>
+    where
+        R: Rng + ?Sized,
+    {
+        ShuffleIter {
+            rest: data,
+            rng,
+            sampler: &mut self.sampler,
+        }
+    }
+
+    /// Returns a shuffled copy of `data` together with a map from the
+    /// original index of every element to its index in the copy.
+    ///
+    /// This is the inverse of the permutation returned by
+    /// [`shuffle_tracked_owned`](Shuffler::shuffle_tracked_owned), in the
+    /// form of a lookup table, so `shuffled[map[&i]]` is `data[i]`.
+    ///
+    /// Available with the `std` feature.
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::fy::FisherYates;
+    /// use rand::rngs::mock::StepRng;
+    ///
+    /// let mut rng = StepRng::new(2, 13);
+    /// let mut fy = FisherYates::default();
+    ///
+    /// let input = ['a', 'b', 'c', 'd', 'e'];
+    /// let (shuffled, new_index) = fy.shuffle_to_map(&input, &mut rng);
+    /// assert_eq!(shuffled, vec!['b', 'c', 'd', 'e', 'a']);
+    /// assert_eq!(new_index[&0], 4);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn shuffle_to_map<T, R>(
+        &mut self,
+        data: &[T],
+        rng: &mut R,
+    ) -> (Vec<T>, HashMap<usize, usize>)
+    where
+        T: Clone,
+        R: Rng + ?Sized,
+    {
+        let permutation = self.shuffle_indices_vec(data.len(), rng);
+        let shuffled = permutation.iter().map(|&i| data[i].clone()).collect();
+        let new_index = permutation
+            .iter()
+            .enumerate()
+            .map(|(new, &original)| (original, new))
+            .collect();
+        (shuffled, new_index)
+    }
+}
+
+impl<S> FisherYates<S>
+where
+    S: IndexSampler,
+{
+    /// Shuffles `data` in place and returns an iterator over batches of
+    /// `chunk_size` of its elements.
+    ///
+    /// The batches borrow the shuffled data, so nothing is allocated. As
+    /// with [`slice::chunks_mut`], the last batch is shorter if `chunk_size`
+    /// doesn't divide the length of `data`.
+    ///
+    /// Returns an error, leaving `data` unchanged, if `chunk_size` is zero.
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::fy::FisherYates;
+    /// use rand::rngs::mock::StepRng;
+    ///
+    /// let mut rng = StepRng::new(2, 13);
+    /// let mut fy = FisherYates::default();
+    ///
+    /// let mut input = vec![1, 2, 3, 4, 5];
+    /// let sizes: Vec<usize> = fy
+    ///     .shuffle_chunks(&mut input, 2, &mut rng)
+    ///     .unwrap()
+    ///     .map(|batch| batch.len())
+    ///     .collect();
+    /// assert_eq!(sizes, vec![2, 2, 1]);
+    /// ```
+    pub fn shuffle_chunks<'a, T, R>(
+        &mut self,
+        data: &'a mut [T],
+        chunk_size: usize,
+        rng: &mut R,
+    ) -> Result<ChunksMut<'a, T>, ShuffleError>
+    where
+        R: Rng + ?Sized,
+    {
+        if chunk_size == 0 {
+            return Err(ShuffleError::InvalidArgument("chunk size must be non-zero"));
+        }
+        fisher_yates(data, &mut self.sampler, rng);
+        Ok(data.chunks_mut(chunk_size))
+    }
+
+    /// Shuffles `data` like [`shuffle`](Shuffler::shuffle) and returns the
+    /// number of inversions of the applied permutation, see
+    /// [`permutation::inversions`](crate::permutation::inversions).
+    ///
+    /// The permutation is tracked in a vector of indices shuffled along
+    /// with the data.
+    ///
+    /// Available with the `std` feature.
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::fy::FisherYates;
+    /// use rand::rngs::mock::StepRng;
+    ///
+    /// let mut rng = StepRng::new(2, 13);
+    /// let mut fy = FisherYates::default();
+    ///
+    /// let mut input = vec![1, 2, 3, 4, 5];
+    /// let inversions = fy.shuffle_with_inversions(&mut input, &mut rng);
+    /// assert_eq!(&input, &[2, 3, 4, 5, 1]);
+    /// assert_eq!(inversions, 4);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn shuffle_with_inversions<T, R>(&mut self, data: &mut [T], rng: &mut R) -> usize
+    where
+        R: Rng + ?Sized,
+    {
+        let mut permutation: Vec<usize> = (0..data.len()).collect();
         for i in (1..data.len()).rev() {
-            let j = rng.gen_range(0..(i + 1));
+            let j = self.sampler.sample_index(i, rng);
+            data.swap(i, j);
+            permutation.swap(i, j);
+        }
+        crate::permutation::inversions(&permutation)
+    }
+
+    /// Shuffles `data` with a generator seeded from `seed`.
+    ///
+    /// The generator is `ChaCha8Rng` from `rand_chacha`, as everywhere in
+    /// [`seeded`](crate::seeded). Its output for a given seed is fixed, so
+    /// the permutation depends on `seed` only and stays the same across
+    /// runs and platforms.
+    /// This is handy in tests: looping over the seeds `0..n` gives `n`
+    /// reproducible permutations without building a generator for each.
+    /// It is not meant for anything where the shuffle has to be
+    /// unpredictable, as there are only `2^64` seeds.
+    ///
+    /// Available with the `seeded` feature.
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::fy::FisherYates;
+    ///
+    /// let mut fy = FisherYates::default();
+    ///
+    /// let mut first = vec![1, 2, 3, 4, 5];
+    /// let mut second = vec![1, 2, 3, 4, 5];
+    /// fy.shuffle_seeded_in_place(&mut first, 7);
+    /// fy.shuffle_seeded_in_place(&mut second, 7);
+    /// assert_eq!(first, second);
+    /// ```
+    #[cfg(feature = "seeded")]
+    pub fn shuffle_seeded_in_place<T>(&mut self, data: &mut [T], seed: u64) {
+        fisher_yates(
+            data,
+            &mut self.sampler,
+            &mut crate::seeded::rng_from_seed(seed),
+        );
+    }
+
+    /// Shuffles `data` with exactly the same swaps as
+    /// [`rand::seq::SliceRandom::shuffle`] does for the same `rng`.
+    ///
+    /// This is meant for code migrating from `rand` that has to reproduce
+    /// previously generated permutations.
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::fy::FisherYates;
+    /// use rand::rngs::StdRng;
+    /// use rand::seq::SliceRandom;
+    /// use rand::SeedableRng;
+    ///
+    /// let mut input = vec![1, 2, 3, 4, 5];
+    /// FisherYates::default().shuffle_rand_compatible(&mut input, &mut StdRng::seed_from_u64(42));
+    ///
+    /// let mut expected = vec![1, 2, 3, 4, 5];
+    /// expected.shuffle(&mut StdRng::seed_from_u64(42));
+    /// assert_eq!(input, expected);
+    /// ```
+    pub fn shuffle_rand_compatible<T, R>(&mut self, data: &mut [T], rng: &mut R)
+    where
+        R: Rng + ?Sized,
+    {
+        for i in (1..data.len()).rev() {
+            let j = if i < u32::MAX as usize {
+                rng.gen_range(0..(i as u32 + 1)) as usize
+            } else {
+                rng.gen_range(0..(i + 1))
+            };
             data.swap(i, j);
         }
+    }
+
+    /// Shuffles `data` with a permutation that only depends on the bytes
+    /// returned by `rng.fill_bytes`.
+    ///
+    /// Unlike [`shuffle`](Shuffler::shuffle), this doesn't rely on how
+    /// `rand` samples from a range, so for the same stream of bytes the
+    /// result is the same on every platform and with every version of
+    /// `rand`. Changing it is a breaking change of this crate.
+    ///
+    /// The algorithm is pinned down as follows. For `i` from `len - 1`
+    /// down to `1`, 8 bytes are drawn with `fill_bytes` and read as
+    /// a little-endian `u64`, which is masked to the bit length of `i`.
+    /// The draw is repeated until the result `j` is at most `i`, and then
+    /// `data[i]` and `data[j]` are swapped.
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::fy::FisherYates;
+    /// use rand::rngs::mock::StepRng;
+    ///
+    /// let mut rng = StepRng::new(3, 0x0101_0101_0101_0101);
+    /// let mut fy = FisherYates::default();
+    ///
+    /// let mut input = vec![1, 2, 3, 4, 5];
+    /// fy.shuffle_deterministic(&mut input, &mut rng);
+    /// assert_eq!(&input, &[3, 5, 2, 1, 4]);
+    /// ```
+    pub fn shuffle_deterministic<T, R>(&mut self, data: &mut [T], rng: &mut R)
+    where
+        R: RngCore + ?Sized,
+    {
+        for i in (1..data.len()).rev() {
+            let i = i as u64;
+            let mask = u64::MAX >> i.leading_zeros();
+            let j = loop {
+                let mut bytes = [0; 8];
+                rng.fill_bytes(&mut bytes);
+                let j = u64::from_le_bytes(bytes) & mask;
+                if j <= i {
+                    break j;
+                }
+            };
+            data.swap(i as usize, j as usize);
+        }
+    }
+
+    /// Shuffles the elements of `data[range]` in place, leaving the rest of
+    /// `data` untouched.
+    ///
+    /// Returns an error, without modifying `data`, if `range.start` is past
+    /// `range.end` or `range.end` is past the end of `data`.
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::fy::FisherYates;
+    /// use rand::rngs::mock::StepRng;
+    ///
+    /// let mut rng = StepRng::new(2, 13);
+    /// let mut fy = FisherYates::default();
+    ///
+    /// let mut input = vec![1, 2, 3, 4, 5, 6, 7];
+    /// fy.shuffle_range(&mut input, 2..6, &mut rng).unwrap();
+    /// assert_eq!(&input, &[1, 2, 4, 5, 6, 3, 7]);
+    /// assert!(fy.shuffle_range(&mut input, 2..8, &mut rng).is_err());
+    /// ```
+    pub fn shuffle_range<T, R>(
+        &mut self,
+        data: &mut [T],
+        range: Range<usize>,
+        rng: &mut R,
+    ) -> Result<(), ShuffleError>
+    where
+        R: Rng + ?Sized,
+    {
+        let sub = data.get_mut(range).ok_or(ShuffleError::OutOfBounds)?;
+        fisher_yates(sub, &mut self.sampler, rng);
+        Ok(())
+    }
+
+    /// Shuffles each of the `regions` of `data` independently.
+    ///
+    /// The elements never leave the region they are in, and the elements
+    /// outside of all regions are left untouched. Returns an error, without
+    /// modifying `data`, if any region is out of bounds or if two regions
+    /// overlap.
+    ///
+    /// Available with the `alloc` feature.
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::fy::FisherYates;
+    /// use rand::rngs::mock::StepRng;
+    ///
+    /// let mut rng = StepRng::new(2, 13);
+    /// let mut fy = FisherYates::default();
+    ///
+    /// let mut input = vec![1, 2, 3, 4, 5, 6, 7];
+    /// fy.shuffle_regions(&mut input, &[0..3, 4..7], &mut rng).unwrap();
+    /// assert_eq!(input[3], 4);
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn shuffle_regions<T, R>(
+        &mut self,
+        data: &mut [T],
+        regions: &[Range<usize>],
+        rng: &mut R,
+    ) -> Result<(), ShuffleError>
+    where
+        R: Rng + ?Sized,
+    {
+        if regions
+            .iter()
+            .any(|r| r.start > r.end || r.end > data.len())
+        {
+            return Err(ShuffleError::OutOfBounds);
+        }
+        let mut sorted: Vec<&Range<usize>> = regions.iter().filter(|r| !r.is_empty()).collect();
+        sorted.sort_unstable_by_key(|r| r.start);
+        if sorted.windows(2).any(|w| w[0].end > w[1].start) {
+            return Err(ShuffleError::InvalidArgument("overlapping regions"));
+        }
+
+        for region in regions {
+            fisher_yates(&mut data[region.clone()], &mut self.sampler, rng);
+        }
         Ok(())
     }
 }
+
+/// An iterator yielding the elements of a slice in a random order,
+/// created by [`FisherYates::shuffle_iter`].
+pub struct ShuffleIter<'a, T, R, S = GenRangeSampler>
+where
+    R: ?Sized,
+{
+    rest: &'a mut [T],
+    rng: &'a mut R,
+    sampler: &'a mut S,
+}
+
+impl<'a, T, R, S> ShuffleIter<'a, T, R, S>
+where
+    R: ?Sized,
+{
+    /// Returns the elements that haven't been yielded yet, in an
+    /// unspecified order.
+    pub fn into_remaining(self) -> &'a mut [T] {
+        self.rest
+    }
+}
+
+impl<'a, T, R, S> Iterator for ShuffleIter<'a, T, R, S>
+where
+    R: Rng + ?Sized,
+    S: IndexSampler,
+{
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = core::mem::take(&mut self.rest);
+        // The last element has nothing to pick from.
+        if rest.len() > 1 {
+            let j = self.sampler.sample_index(rest.len() - 1, self.rng);
+            rest.swap(0, j);
+        }
+        let (first, tail) = rest.split_first_mut()?;
+        self.rest = tail;
+        Some(first)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.rest.len(), Some(self.rest.len()))
+    }
+}
+
+impl<'a, T, R, S> ExactSizeIterator for ShuffleIter<'a, T, R, S>
+where
+    R: Rng + ?Sized,
+    S: IndexSampler,
+{
+}
+
+impl<'a, T, R, S> FusedIterator for ShuffleIter<'a, T, R, S>
+where
+    R: Rng + ?Sized,
+    S: IndexSampler,
+{
+}
+
+// Not derived, as the generator doesn't have to implement `Debug`.
+impl<'a, T, R, S> fmt::Debug for ShuffleIter<'a, T, R, S>
+where
+    R: ?Sized,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShuffleIter")
+            .field("remaining", &self.rest.len())
+            .finish()
+    }
+}
+
+/// Shuffles the array `data` in place with Fisher-Yates.
+///
+/// This is the same shuffle as [`Shuffler::shuffle_slice`] of
+/// [`FisherYates`], but it neither allocates nor needs `T: Clone`, so it
+/// fits e.g. a fixed lookup table in firmware without an allocator.
+///
+/// # Examples
+/// ```
+/// use shuffle::fy::shuffle_array;
+/// use rand::rngs::mock::StepRng;
+///
+/// let mut rng = StepRng::new(2, 13);
+/// let mut table = [1u8, 2, 3, 4, 5];
+///
+/// shuffle_array(&mut table, &mut rng);
+/// assert_eq!(table, [2, 3, 4, 5, 1]);
+/// ```
+pub fn shuffle_array<T, const N: usize, R>(data: &mut [T; N], rng: &mut R)
+where
+    R: Rng + ?Sized,
+{
+    fisher_yates(data, &mut GenRangeSampler, rng);
+}
+
+/// The plain Fisher-Yates shuffle, drawing the indices with `sampler`.
+fn fisher_yates<D, S, R>(data: &mut D, sampler: &mut S, rng: &mut R)
+where
+    D: SwapSlice + ?Sized,
+    S: IndexSampler + ?Sized,
+    R: RngCore + ?Sized,
+{
+    if data.len() <= 1 {
+        return;
+    }
+    for i in (1..data.len()).rev() {
+        let j = sampler.sample_index(i, rng);
+        data.swap(i, j);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{chi_square_critical_value, counts_chi_square, permutation_chi_square};
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+    use std::collections::HashMap;
+
+    /// Hands out fixed bytes, and only through `fill_bytes`.
+    struct ByteStream<'a>(&'a [u8]);
+
+    impl<'a> RngCore for ByteStream<'a> {
+        fn next_u32(&mut self) -> u32 {
+            unimplemented!("only fill_bytes may be used")
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            unimplemented!("only fill_bytes may be used")
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            let (head, tail) = self.0.split_at(dest.len());
+            dest.copy_from_slice(head);
+            self.0 = tail;
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_small_inputs_draw_nothing() {
+        let mut fy = FisherYates::default();
+        // Panics on any draw.
+        let mut rng = ByteStream(&[]);
+        for len in [0, 1] {
+            let input_data: Vec<u32> = (0..len).collect();
+            let mut target = input_data.clone();
+            fy.shuffle(&mut target, &mut rng).unwrap();
+            fy.shuffle_in_place(&mut target, &mut rng);
+            fy.shuffle_rand_compatible(&mut target, &mut rng);
+            fy.shuffle_deterministic(&mut target, &mut rng);
+            fy.partial_shuffle(&mut target, 1, &mut rng);
+            assert_eq!(fy.shuffle_iter(&mut target, &mut rng).count(), len as usize);
+            assert_eq!(target, input_data);
+            assert_eq!(
+                fy.shuffle_indices_vec(len as usize, &mut rng),
+                input_data.iter().map(|&i| i as usize).collect::<Vec<_>>()
+            );
+        }
+    }
+
+    /// Records the bounds it is asked for and always picks `max`.
+    #[derive(Debug, Default)]
+    struct RecordingSampler(Vec<usize>);
+
+    impl IndexSampler for RecordingSampler {
+        fn sample_index<R>(&mut self, max: usize, _rng: &mut R) -> usize
+        where
+            R: RngCore + ?Sized,
+        {
+            self.0.push(max);
+            max
+        }
+    }
+
+    #[test]
+    fn test_with_sampler() {
+        let mut rng = ByteStream(&[]);
+        let mut fy = FisherYates::with_sampler(RecordingSampler::default());
+        let mut input: Vec<u32> = (0..5).collect();
+        fy.shuffle(&mut input, &mut rng).unwrap();
+        assert_eq!(fy.sampler.0, [4, 3, 2, 1]);
+        // Picking `max` every time swaps every element with itself.
+        assert_eq!(input, [0, 1, 2, 3, 4]);
+
+        fy.sampler.0.clear();
+        fy.partial_shuffle(&mut input, 2, &mut rng);
+        assert_eq!(fy.sampler.0, [4, 3]);
+        assert_eq!(input, [4, 0, 2, 3, 1]);
+
+        fy.sampler.0.clear();
+        assert_eq!(fy.shuffle_iter(&mut input, &mut rng).count(), 5);
+        assert_eq!(fy.sampler.0, [4, 3, 2, 1]);
+    }
+
+    #[test]
+    fn test_default_sampler_draws_like_gen_range() {
+        let mut fy = FisherYates::default();
+        let input_data: Vec<u32> = (0..200).collect();
+
+        let mut target = input_data.clone();
+        fy.partial_shuffle(&mut target, 50, &mut StdRng::seed_from_u64(20));
+        let mut expected = input_data.clone();
+        let mut rng = StdRng::seed_from_u64(20);
+        for i in 0..50 {
+            let j = rng.gen_range(i..expected.len());
+            expected.swap(i, j);
+        }
+        assert_eq!(target, expected);
+
+        let mut target = input_data.clone();
+        fy.shuffle(&mut target, &mut StdRng::seed_from_u64(20))
+            .unwrap();
+        let mut expected = input_data;
+        let mut rng = StdRng::seed_from_u64(20);
+        for i in (1..expected.len()).rev() {
+            let j = rng.gen_range(0..(i + 1));
+            expected.swap(i, j);
+        }
+        assert_eq!(target, expected);
+    }
+
+    #[test]
+    fn test_same_distribution_as_rand() {
+        let mut rng = StdRng::seed_from_u64(19);
+        let critical = chi_square_critical_value(23, 0.999);
+        let statistic = permutation_chi_square(&mut FisherYates::default(), 4, 24_000, &mut rng);
+        assert!(statistic < critical);
+
+        let mut theirs = HashMap::new();
+        for _ in 0..24_000 {
+            let mut target = vec![0, 1, 2, 3];
+            target.shuffle(&mut rng);
+            *theirs.entry(target).or_insert(0) += 1;
+        }
+        assert_eq!(theirs.len(), 24);
+        let theirs: Vec<usize> = theirs.into_values().collect();
+        assert!(counts_chi_square(&theirs) < critical);
+    }
+
+    #[cfg(feature = "seeded")]
+    #[test]
+    fn test_shuffle_seeded_in_place() {
+        let mut fy = FisherYates::default();
+        let input_data: Vec<u32> = (0..10).collect();
+
+        let mut first = input_data.clone();
+        fy.shuffle_seeded_in_place(&mut first, 0);
+        let mut second = input_data.clone();
+        fy.shuffle_seeded_in_place(&mut second, 1);
+        assert_ne!(first, second);
+
+        // Pinned, so that a change in the generator doesn't go unnoticed.
+        assert_eq!(first, vec![2, 1, 9, 5, 8, 3, 6, 0, 4, 7]);
+    }
+
+    #[test]
+    fn test_shuffle_rand_compatible() {
+        for seed in 0..20 {
+            let len = seed as u32 * 5;
+            let mut target: Vec<u32> = (0..len).collect();
+            FisherYates::default()
+                .shuffle_rand_compatible(&mut target, &mut StdRng::seed_from_u64(seed));
+            let mut expected: Vec<u32> = (0..len).collect();
+            expected.shuffle(&mut StdRng::seed_from_u64(seed));
+            assert_eq!(target, expected);
+        }
+    }
+
+    #[test]
+    fn test_shuffle_with_inversions() {
+        let input_data: Vec<u32> = (0..200).collect();
+        let mut target = input_data.clone();
+        let count = FisherYates::default()
+            .shuffle_with_inversions(&mut target, &mut StdRng::seed_from_u64(40));
+
+        let mut expected = input_data.clone();
+        FisherYates::default()
+            .shuffle(&mut expected, &mut StdRng::seed_from_u64(40))
+            .unwrap();
+        assert_eq!(target, expected);
+        let permutation: Vec<usize> = target.iter().map(|&x| x as usize).collect();
+        assert_eq!(count, crate::permutation::inversions(&permutation));
+        assert!(count > 0);
+    }
+
+    #[test]
+    fn test_shuffle_chunks() {
+        let mut rng = StdRng::seed_from_u64(39);
+        let input_data: Vec<u32> = (0..100).collect();
+        let mut target = input_data.clone();
+        let mut seen = Vec::new();
+        for chunk in FisherYates::default()
+            .shuffle_chunks(&mut target, 7, &mut rng)
+            .unwrap()
+        {
+            assert!(chunk.len() == 7 || chunk.len() == 100 % 7);
+            seen.extend_from_slice(chunk);
+        }
+        assert_ne!(seen, input_data);
+        assert_eq!(seen, target);
+        seen.sort_unstable();
+        assert_eq!(seen, input_data);
+
+        assert!(FisherYates::default()
+            .shuffle_chunks(&mut target, 0, &mut rng)
+            .is_err());
+    }
+
+    #[test]
+    fn test_shuffle_indices_into() {
+        let mut fy = FisherYates::default();
+        let mut buffers = ShuffleBuffers::default();
+        fy.shuffle_indices_into(&mut buffers, 100, &mut StdRng::seed_from_u64(25));
+        let expected = fy.shuffle_indices_vec(100, &mut StdRng::seed_from_u64(25));
+        assert_eq!(buffers.perm, expected);
+
+        let mut rng = StdRng::seed_from_u64(25);
+        let address = buffers.perm.as_ptr();
+        let capacity = buffers.perm.capacity();
+        for len in [100, 0, 37, 1, 99] {
+            fy.shuffle_indices_into(&mut buffers, len, &mut rng);
+            assert_eq!(buffers.perm.as_ptr(), address);
+            assert_eq!(buffers.perm.capacity(), capacity);
+            let mut sorted = buffers.perm.clone();
+            sorted.sort_unstable();
+            assert_eq!(sorted, (0..len).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn test_shuffle_array() {
+        let mut table = [0u8; 256];
+        for (i, entry) in table.iter_mut().enumerate() {
+            *entry = i as u8;
+        }
+        let mut expected = table.to_vec();
+        FisherYates::default()
+            .shuffle(&mut expected, &mut StdRng::seed_from_u64(27))
+            .unwrap();
+        shuffle_array(&mut table, &mut StdRng::seed_from_u64(27));
+        assert_eq!(table.to_vec(), expected);
+
+        let mut empty: [u8; 0] = [];
+        shuffle_array(&mut empty, &mut StdRng::seed_from_u64(27));
+    }
+
+    #[test]
+    fn test_deterministic_golden_vectors() {
+        let bytes: Vec<u8> = (0u32..4096)
+            .map(|i| (i.wrapping_mul(0x9e37_79b9) >> 24) as u8)
+            .collect();
+        let mut fy = FisherYates::default();
+        let mut target: Vec<u32> = (0..5).collect();
+        fy.shuffle_deterministic(&mut target, &mut ByteStream(&bytes));
+        assert_eq!(target, vec![2, 4, 3, 1, 0]);
+
+        let mut target: Vec<u32> = (0..16).collect();
+        fy.shuffle_deterministic(&mut target, &mut ByteStream(&bytes));
+        assert_eq!(
+            target,
+            vec![12, 7, 9, 10, 2, 15, 11, 4, 13, 14, 8, 6, 5, 3, 1, 0]
+        );
+    }
+
+    #[test]
+    fn test_deterministic_uniform() {
+        let mut rng = StdRng::seed_from_u64(28);
+        let mut fy = FisherYates::default();
+        let trials = 24_000;
+        let mut counts = HashMap::new();
+        for _ in 0..trials {
+            let mut target = vec![0, 1, 2, 3];
+            fy.shuffle_deterministic(&mut target, &mut rng);
+            *counts.entry(target).or_insert(0) += 1;
+        }
+        assert_eq!(counts.len(), 24);
+        let counts: Vec<usize> = counts.into_values().collect();
+        assert!(counts_chi_square(&counts) < chi_square_critical_value(23, 0.999));
+    }
+
+    #[test]
+    fn test_shuffle_into() {
+        let mut rng = StdRng::seed_from_u64(26);
+        let mut fy = FisherYates::default();
+        let src = [0, 1, 2, 3];
+        let mut dst = vec![9; 10];
+        let trials = 24_000;
+        let mut counts = HashMap::new();
+        for _ in 0..trials {
+            fy.shuffle_into(&src, &mut dst, &mut rng);
+            *counts.entry(dst.clone()).or_insert(0) += 1;
+        }
+        assert_eq!(src, [0, 1, 2, 3]);
+        assert_eq!(counts.len(), 24);
+
+        // Pearson's chi-squared test with 23 degrees of freedom; the
+        // critical value for p = 0.001 is 49.7.
+        let expected = trials as f64 / 24.0;
+        let chi_squared: f64 = counts
+            .values()
+            .map(|&count| (count as f64 - expected).powi(2) / expected)
+            .sum();
+        assert!(chi_squared < 49.7, "chi-squared: {}", chi_squared);
+
+        fy.shuffle_into(&[], &mut dst, &mut rng);
+        assert!(dst.is_empty());
+    }
+
+    #[test]
+    fn test_partial_shuffle() {
+        let mut rng = StdRng::seed_from_u64(24);
+        let mut fy = FisherYates::default();
+        let input_data: Vec<u32> = (0..100).collect();
+
+        let mut target = input_data.clone();
+        let (selected, rest) = fy.partial_shuffle(&mut target, 0, &mut rng);
+        assert!(selected.is_empty());
+        assert_eq!(rest, &input_data[..]);
+
+        for k in [1, 10, 99, 100, 1000] {
+            let mut target = input_data.clone();
+            let (selected, rest) = fy.partial_shuffle(&mut target, k, &mut rng);
+            assert_eq!(selected.len(), k.min(100));
+            assert_eq!(rest.len(), 100 - k.min(100));
+            target.sort_unstable();
+            assert_eq!(target, input_data);
+        }
+
+        // Dealing on from the rest splits the same buffer further.
+        let mut target = input_data.clone();
+        let (first, rest) = fy.partial_shuffle(&mut target, 5, &mut rng);
+        let first = first.to_vec();
+        let (second, rest) = fy.partial_shuffle(rest, 5, &mut rng);
+        let mut all: Vec<u32> = first
+            .iter()
+            .chain(second.iter())
+            .chain(rest.iter())
+            .copied()
+            .collect();
+        assert_eq!(rest.len(), 90);
+        assert_eq!(&target[..5], &first[..]);
+        all.sort_unstable();
+        assert_eq!(all, input_data);
+
+        let mut counts = HashMap::new();
+        let trials = 24_000;
+        for _ in 0..trials {
+            let mut target = vec![0, 1, 2, 3, 4];
+            let (selected, _) = fy.partial_shuffle(&mut target, 2, &mut rng);
+            *counts.entry(selected.to_vec()).or_insert(0) += 1;
+        }
+        // There are 5 * 4 = 20 ordered selections of 2 out of 5.
+        assert_eq!(counts.len(), 20);
+        let counts: Vec<usize> = counts.into_values().collect();
+        assert!(counts_chi_square(&counts) < chi_square_critical_value(19, 0.999));
+    }
+
+    #[test]
+    fn test_shuffle_iter() {
+        let mut fy = FisherYates::default();
+        for len in [0, 1, 2, 10, 100] {
+            let mut expected: Vec<u32> = (0..len).collect();
+            fy.partial_shuffle(&mut expected, len as usize, &mut StdRng::seed_from_u64(29));
+
+            let mut target: Vec<u32> = (0..len).collect();
+            let mut rng = StdRng::seed_from_u64(29);
+            let iter = fy.shuffle_iter(&mut target, &mut rng);
+            assert_eq!(iter.len(), len as usize);
+            let yielded: Vec<u32> = iter.map(|x| *x).collect();
+            assert_eq!(yielded, expected);
+            assert_eq!(target, expected);
+        }
+
+        let mut rng = StdRng::seed_from_u64(29);
+        let mut counts = HashMap::new();
+        let trials = 24_000;
+        for _ in 0..trials {
+            let mut target = vec![0, 1, 2, 3, 4];
+            let prefix: Vec<u32> = fy
+                .shuffle_iter(&mut target, &mut rng)
+                .take(2)
+                .map(|x| *x)
+                .collect();
+            *counts.entry(prefix).or_insert(0) += 1;
+        }
+        // There are 5 * 4 = 20 ordered selections of 2 out of 5.
+        assert_eq!(counts.len(), 20);
+        let counts: Vec<usize> = counts.into_values().collect();
+        assert!(counts_chi_square(&counts) < chi_square_critical_value(19, 0.999));
+
+        let mut target = vec![0, 1, 2, 3, 4];
+        let mut iter = fy.shuffle_iter(&mut target, &mut rng);
+        iter.next();
+        let rest = iter.into_remaining().to_vec();
+        assert_eq!(rest.len(), 4);
+        assert!(!rest.contains(&target[0]));
+    }
+
+    #[test]
+    fn test_shuffle_to_map() {
+        let mut rng = StdRng::seed_from_u64(23);
+        let input_data: Vec<String> = (0..30).map(|i| format!("sample-{}", i)).collect();
+
+        let (shuffled, new_index) = FisherYates::default().shuffle_to_map(&input_data, &mut rng);
+        assert_ne!(shuffled, input_data);
+        assert_eq!(new_index.len(), input_data.len());
+        for (original, element) in input_data.iter().enumerate() {
+            assert_eq!(&shuffled[new_index[&original]], element);
+        }
+
+        let (shuffled, new_index) = FisherYates::default().shuffle_to_map::<u8, _>(&[], &mut rng);
+        assert!(shuffled.is_empty());
+        assert!(new_index.is_empty());
+    }
+
+    #[test]
+    fn test_shuffle_regions() {
+        let mut rng = StdRng::seed_from_u64(18);
+        let input_data: Vec<u32> = (0..100).collect();
+        let mut target = input_data.clone();
+        FisherYates::default()
+            .shuffle_regions(&mut target, &[60..100, 10..40], &mut rng)
+            .unwrap();
+
+        assert_eq!(target[..10], input_data[..10]);
+        assert_eq!(target[40..60], input_data[40..60]);
+        for region in [10..40, 60..100] {
+            assert_ne!(target[region.clone()], input_data[region.clone()]);
+            let mut sorted = target[region.clone()].to_vec();
+            sorted.sort_unstable();
+            assert_eq!(sorted, input_data[region]);
+        }
+    }
+
+    #[test]
+    fn test_shuffle_range() {
+        let mut rng = StdRng::seed_from_u64(19);
+        let mut fy = FisherYates::default();
+        let input_data: Vec<u32> = (0..100).collect();
+        let mut target = input_data.clone();
+        fy.shuffle_range(&mut target, 30..70, &mut rng).unwrap();
+
+        assert_eq!(target[..30], input_data[..30]);
+        assert_eq!(target[70..], input_data[70..]);
+        assert_ne!(target[30..70], input_data[30..70]);
+        let mut sorted = target[30..70].to_vec();
+        sorted.sort_unstable();
+        assert_eq!(sorted, input_data[30..70]);
+
+        // Same draws as shuffling the sub-slice directly.
+        let mut expected = input_data.clone();
+        fy.shuffle_in_place(&mut expected[30..70], &mut StdRng::seed_from_u64(19));
+        assert_eq!(target, expected);
+
+        for range in [0..0, 100..100, 0..100] {
+            assert!(fy.shuffle_range(&mut target, range, &mut rng).is_ok());
+        }
+    }
+
+    #[test]
+    #[allow(clippy::reversed_empty_ranges)]
+    fn test_shuffle_range_invalid() {
+        let mut rng = StdRng::seed_from_u64(19);
+        let mut fy = FisherYates::default();
+        let input_data: Vec<u32> = (0..10).collect();
+        let mut target = input_data.clone();
+        for range in [5..3, 0..11, 10..11, 11..11] {
+            assert_eq!(
+                fy.shuffle_range(&mut target, range, &mut rng),
+                Err(ShuffleError::OutOfBounds)
+            );
+            assert_eq!(target, input_data);
+        }
+    }
+
+    #[test]
+    fn test_shuffle_regions_invalid() {
+        let mut rng = StdRng::seed_from_u64(18);
+        let mut fy = FisherYates::default();
+        let input_data: Vec<u32> = (0..10).collect();
+        let mut target = input_data.clone();
+        #[allow(clippy::reversed_empty_ranges, clippy::single_range_in_vec_init)]
+        let invalid: [&[Range<usize>]; 4] = [&[0..11], &[5..3], &[0..5, 4..8], &[2..6, 0..3]];
+        for regions in invalid {
+            assert!(fy.shuffle_regions(&mut target, regions, &mut rng).is_err());
+            assert_eq!(target, input_data);
+        }
+        assert!(fy
+            .shuffle_regions(&mut target, &[0..5, 5..10, 3..3], &mut rng)
+            .is_ok());
+    }
+}
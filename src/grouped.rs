@@ -0,0 +1,107 @@
+//! Shuffling that keeps related elements together.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use rand::Rng;
+
+use crate::fy::FisherYates;
+
+/// Shuffles `data` so that the elements with equal keys stay contiguous.
+///
+/// The elements are grouped by `key`, e.g. by the variant of an enum,
+/// then every group is shuffled on its own and the groups are put back
+/// in a random order. Both the order of the groups and the order within
+/// each group are uniformly distributed, and independent.
+///
+/// # Examples
+/// ```
+/// use shuffle::grouped;
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// #[derive(Debug, PartialEq)]
+/// enum Card {
+///     Hearts(u8),
+///     Spades(u8),
+/// }
+///
+/// let mut rng = StdRng::seed_from_u64(42);
+/// let mut deck = vec![Card::Hearts(1), Card::Spades(1), Card::Hearts(2), Card::Spades(2)];
+///
+/// grouped::shuffle_by_discriminant(&mut deck, std::mem::discriminant, &mut rng);
+/// let suits_change = deck
+///     .windows(2)
+///     .filter(|w| std::mem::discriminant(&w[0]) != std::mem::discriminant(&w[1]))
+///     .count();
+/// assert_eq!(suits_change, 1);
+/// ```
+pub fn shuffle_by_discriminant<T, K, F, R>(data: &mut Vec<T>, mut key: F, rng: &mut R)
+where
+    K: Eq + Hash,
+    F: FnMut(&T) -> K,
+    R: Rng + ?Sized,
+{
+    let mut group_of_key = HashMap::new();
+    let mut groups: Vec<Vec<T>> = Vec::new();
+    for item in std::mem::take(data) {
+        let group = *group_of_key.entry(key(&item)).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[group].push(item);
+    }
+
+    let mut fy = FisherYates::default();
+    fy.shuffle_in_place(&mut groups, rng);
+    for mut group in groups {
+        fy.shuffle_in_place(&mut group, rng);
+        data.append(&mut group);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_groups_stay_contiguous() {
+        let input_data: Vec<u32> = (0..60).collect();
+        let mut block_orders = HashSet::new();
+        let mut inner_orders = HashSet::new();
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut target = input_data.clone();
+            shuffle_by_discriminant(&mut target, |x| x % 3, &mut rng);
+
+            let mut sorted = target.clone();
+            sorted.sort_unstable();
+            assert_eq!(sorted, input_data);
+
+            let blocks: Vec<&[u32]> = target.chunks(20).collect();
+            for block in blocks.iter() {
+                assert!(block.iter().all(|x| x % 3 == block[0] % 3));
+                assert!(block.windows(2).any(|w| w[0] > w[1]));
+            }
+            block_orders.insert(blocks.iter().map(|b| b[0] % 3).collect::<Vec<_>>());
+            inner_orders.insert(blocks[0].to_vec());
+        }
+        assert!(block_orders.len() > 1);
+        assert_eq!(inner_orders.len(), 20);
+    }
+
+    #[test]
+    fn test_empty_and_single_group() {
+        let mut rng = StdRng::seed_from_u64(41);
+        let mut target: Vec<u32> = Vec::new();
+        shuffle_by_discriminant(&mut target, |&x| x, &mut rng);
+        assert!(target.is_empty());
+
+        let mut target: Vec<u32> = (0..10).collect();
+        shuffle_by_discriminant(&mut target, |_| (), &mut rng);
+        assert_ne!(target, (0..10).collect::<Vec<_>>());
+    }
+}
@@ -9,17 +9,29 @@
 //! *Aldous, David, and Persi Diaconis. "Shuffling cards and stopping times."
 //! The American Mathematical Monthly 93.5 (1986): 333-348.*
 
-use bitvec::order::Lsb0;
-use bitvec::prelude::*;
+use core::marker::PhantomData;
+
+use alloc::vec;
+use alloc::vec::Vec;
 use rand::RngCore;
 
-use crate::shuffler::Shuffler;
+use crate::fy::FisherYates;
+use crate::shuffler::{ShuffleError, Shuffler};
+
+/// A single slot of the shuffling state: the random key assembled
+/// so far and the index of the input element the key belongs to.
+///
+/// Keeping both in one array means that a round only walks a single
+/// contiguous buffer; the data itself is moved just once, at the end.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default)]
+struct Slot {
+    key: u64,
+    index: usize,
+}
 
 #[derive(Eq, PartialEq, Debug)]
 struct Context<T> {
     data_cp: Vec<T>,
-    bit_slots: Vec<u64>,
-    bit_slots_cp: Vec<u64>,
 }
 
 impl<T> Context<T>
@@ -29,50 +41,326 @@ where
     fn new(data: &[T]) -> Context<T> {
         Context {
             data_cp: data.to_vec(),
-            bit_slots: vec![0; data.len()],
-            bit_slots_cp: vec![0; data.len()],
         }
     }
+
+    /// Makes the copy hold `data`, reusing its allocation.
+    fn reset(&mut self, data: &[T]) {
+        let common = data.len().min(self.data_cp.len());
+        self.data_cp.truncate(common);
+        self.data_cp.clone_from_slice(&data[..common]);
+        self.data_cp.extend_from_slice(&data[common..]);
+    }
+}
+
+/// The slots sorted by the rounds and the scratch buffer for a round.
+///
+/// Kept apart from the [`Context`], as computing a bare permutation
+/// needs the slots but no copy of the data.
+#[derive(Eq, PartialEq, Debug, Default)]
+struct Slots {
+    slots: Vec<Slot>,
+    slots_cp: Vec<Slot>,
+}
+
+impl Slots {
+    /// Prepares the buffers for `len` elements, reusing their allocations.
+    fn reset(&mut self, len: usize) {
+        self.slots.clear();
+        self.slots
+            .extend((0..len).map(|index| Slot { key: 0, index }));
+        // `one_round` overwrites all of `slots_cp`, so only its length matters.
+        self.slots_cp.resize(len, Slot::default());
+    }
+}
+
+/// The order in which the bits of a single byte are handed out.
+///
+/// The bits are taken a 64-bit word at a time: [`load`](BitIndexing::load)
+/// packs 8 bytes into a word, and [`take`](BitIndexing::take) then hands
+/// out its bits one by one, in the order of the bytes and in this order
+/// within every byte.
+trait BitIndexing {
+    /// Packs `bytes` into a word for [`take`](BitIndexing::take).
+    fn load(bytes: [u8; 8]) -> u64;
+
+    /// Removes the next bit from `word` and returns it.
+    fn take(word: &mut u64) -> bool;
+}
+
+/// Least significant bit first.
+struct Lsb0;
+
+impl BitIndexing for Lsb0 {
+    #[inline]
+    fn load(bytes: [u8; 8]) -> u64 {
+        // The first byte ends up in the lowest bits.
+        u64::from_le_bytes(bytes)
+    }
+
+    #[inline]
+    fn take(word: &mut u64) -> bool {
+        let bit = *word & 1 == 1;
+        *word >>= 1;
+        bit
+    }
+}
+
+/// Most significant bit first.
+struct Msb0;
+
+impl BitIndexing for Msb0 {
+    #[inline]
+    fn load(bytes: [u8; 8]) -> u64 {
+        // The first byte ends up in the highest bits.
+        u64::from_be_bytes(bytes)
+    }
+
+    #[inline]
+    fn take(word: &mut u64) -> bool {
+        let bit = *word >> 63 == 1;
+        *word <<= 1;
+        bit
+    }
+}
+
+/// Anything that can hand out random bits to the shuffling rounds.
+trait BitSource {
+    fn next_bit(&mut self) -> bool;
+}
+
+impl<F> BitSource for F
+where
+    F: FnMut() -> bool,
+{
+    fn next_bit(&mut self) -> bool {
+        self()
+    }
 }
 
-struct InfiniteBitIter<'a, R>
+/// Hands out the bits of a buffer of random bytes in the order `O`,
+/// refilling the buffer from `rng` whenever it runs out.
+struct InfiniteBitIter<'a, O, R>
 where
+    O: BitIndexing,
     R: ?Sized,
 {
-    buffer: BitVec<Lsb0, u8>,
+    buffer: Vec<u8>,
+    byte_order: ByteOrder,
     rng: &'a mut R,
-    index: usize,
+    /// The bits of the buffer that were loaded but not handed out yet.
+    word: u64,
+    /// The number of bits left in `word`.
+    word_bits: u32,
+    /// The offset of the next word in the buffer.
+    next_word: usize,
+    refills: u64,
+    order: PhantomData<O>,
 }
 
-impl<'a, R> InfiniteBitIter<'a, R>
+impl<'a, O, R> InfiniteBitIter<'a, O, R>
 where
+    O: BitIndexing,
     R: ?Sized,
 {
-    fn new(buffer: Vec<u8>, rng: &'a mut R) -> Self {
+    /// Creates an iterator that hands out `buffer` first and then refills
+    /// it from `rng`, with the bytes of every refill in `byte_order`.
+    ///
+    /// The length of `buffer` must be a non-zero multiple of 8.
+    fn new(mut buffer: Vec<u8>, byte_order: ByteOrder, rng: &'a mut R) -> Self {
+        debug_assert!(!buffer.is_empty() && buffer.len().is_multiple_of(8));
+        byte_order.arrange(&mut buffer);
         Self {
-            buffer: BitVec::from_vec(buffer),
+            buffer,
+            byte_order,
             rng,
-            index: 0,
+            word: 0,
+            word_bits: 0,
+            next_word: 0,
+            refills: 0,
+            order: PhantomData,
         }
     }
+
+    /// Returns the number of random bits drawn so far, including the
+    /// initial `buffer`.
+    fn bits_drawn(&self) -> u64 {
+        (1 + self.refills) * self.buffer_bits()
+    }
+
+    /// Returns the number of bits handed out so far.
+    fn bits_used(&self) -> u64 {
+        self.refills * self.buffer_bits() + self.next_word as u64 * 8 - self.word_bits as u64
+    }
+
+    fn buffer_bits(&self) -> u64 {
+        self.buffer.len() as u64 * 8
+    }
+
+    /// Returns the buffer, to reuse its allocation.
+    fn into_bytes(self) -> Vec<u8> {
+        self.buffer
+    }
 }
 
-impl<'a, R> InfiniteBitIter<'a, R>
+impl<'a, O, R> BitSource for InfiniteBitIter<'a, O, R>
 where
+    O: BitIndexing,
     R: RngCore + ?Sized,
 {
+    #[inline]
     fn next_bit(&mut self) -> bool {
-        let cbuf_bits = self.buffer.len() * 8;
-        if self.index == cbuf_bits {
-            self.index = 0;
-            self.rng.fill_bytes(self.buffer.as_mut_slice());
+        if self.word_bits == 0 {
+            if self.next_word == self.buffer.len() {
+                self.next_word = 0;
+                self.refills += 1;
+                self.rng.fill_bytes(&mut self.buffer);
+                self.byte_order.arrange(&mut self.buffer);
+            }
+            let bytes = &self.buffer[self.next_word..self.next_word + 8];
+            self.word = O::load([
+                bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7],
+            ]);
+            self.word_bits = u64::BITS;
+            self.next_word += 8;
         }
-        // This is safe because we manually check whether the index
-        // is still in the range.
-        let result = unsafe { self.buffer.get_unchecked(self.index) };
-        self.index += 1;
-        *result
+        self.word_bits -= 1;
+        O::take(&mut self.word)
+    }
+}
+
+/// The order in which `Irs` consumes the bits of each random byte.
+///
+/// Changing the order changes the permutation produced for a given
+/// stream of random bytes, but not the quality of the shuffle.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum BitOrder {
+    /// Least significant bit first. This is the default.
+    #[default]
+    Lsb0,
+    /// Most significant bit first.
+    Msb0,
+}
+
+/// The order in which `Irs` consumes the bytes of each 32-bit word of
+/// random bytes.
+///
+/// The generators of `rand` produce 32-bit words and `fill_bytes` hands
+/// them out in little-endian order, so with
+/// [`LittleEndian`](ByteOrder::LittleEndian) the bytes are consumed as
+/// they come. [`BigEndian`](ByteOrder::BigEndian) reverses every group of
+/// four bytes, as if the words had been written out big-endian.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ByteOrder {
+    /// The bytes in the order `fill_bytes` returns them. This is the default.
+    #[default]
+    LittleEndian,
+    /// The bytes of every 32-bit word reversed.
+    BigEndian,
+}
+
+impl ByteOrder {
+    /// Puts freshly drawn `bytes` in this order; their length must be
+    /// a multiple of 4.
+    fn arrange(self, bytes: &mut [u8]) {
+        if self == ByteOrder::BigEndian {
+            bytes.chunks_exact_mut(4).for_each(|word| word.reverse());
+        }
+    }
+}
+
+/// Exactly how `Irs` turns the output of `fill_bytes` into bits.
+///
+/// The random bytes are consumed in the [`ByteOrder`] and each byte bit
+/// by bit in the [`BitOrder`]. Together they give four ways of reading
+/// a 32-bit word `w` that `rand` hands out as `w.to_le_bytes()`:
+///
+/// | `byte_order`   | `bit_order` | Bits of `w` consumed                          |
+/// |----------------|-------------|-----------------------------------------------|
+/// | `LittleEndian` | `Lsb0`      | 0, 1, ..., 31: the whole word LSB first       |
+/// | `LittleEndian` | `Msb0`      | 7, 6, ..., 0, 15, 14, ..., 8, ...             |
+/// | `BigEndian`    | `Lsb0`      | 24, 25, ..., 31, 16, 17, ..., 23, ...         |
+/// | `BigEndian`    | `Msb0`      | 31, 30, ..., 0: the whole word MSB first      |
+///
+/// The default is `LittleEndian` with `Lsb0`. To match an implementation
+/// that reads the random words MSB first, use `BigEndian` with `Msb0`.
+///
+/// Changing the configuration changes the permutation produced for
+/// a given stream of random bytes, but not the quality of the shuffle.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct BitConsumption {
+    /// The order of the bytes within every 32-bit word.
+    pub byte_order: ByteOrder,
+    /// The order of the bits within every byte.
+    pub bit_order: BitOrder,
+}
+
+/// The number of random bytes the rounds draw at a time are a multiple
+/// of this, see [`bit_buffer_len`].
+const BIT_BUFFER_CHUNK: usize = 32;
+
+/// Returns the number of random bytes the rounds of a shuffle of `len`
+/// elements draw at a time: the smallest multiple of
+/// [`BIT_BUFFER_CHUNK`] that holds a bit for every element, so that
+/// a round needs at most one refill.
+fn bit_buffer_len(len: usize) -> usize {
+    len.div_ceil(BIT_BUFFER_CHUNK * 8).max(1) * BIT_BUFFER_CHUNK
+}
+
+/// The default number of rounds after which `Irs` gives up on the keys
+/// becoming distinct, see [`Irs::with_max_rounds`].
+const DEFAULT_MAX_ROUNDS: usize = 128;
+
+/// How many rounds past [`expected_rounds`] a run with a fixed number
+/// of rounds needs to be considered well mixed.
+const MIXING_MARGIN: usize = 2;
+
+/// The number of inverse riffle shuffle rounds needed to mix `len`
+/// elements, i.e. `⌈(3/2)·log2(len)⌉`.
+///
+/// After about this many rounds the distribution of the permutation
+/// gets close to uniform, as shown by Bayer and Diaconis for riffle
+/// shuffles. A regular shuffle runs until all the keys are distinct,
+/// which usually takes a couple of rounds more.
+///
+/// # Examples
+/// ```
+/// use shuffle::irs::expected_rounds;
+///
+/// assert_eq!(expected_rounds(1), 0);
+/// assert_eq!(expected_rounds(52), 9);
+/// ```
+pub fn expected_rounds(len: usize) -> usize {
+    if len < 2 {
+        return 0;
     }
+    // The smallest `r` with `2^r >= len^(3/2)`, i.e. `4^r >= len^3`, is
+    // `⌈c / 2⌉` for `c = ⌈log2(len^3)⌉`. The cube can take up to 192 bits,
+    // so it is computed in two parts: its 128 high and 64 low bits.
+    let len = len as u128;
+    let square = len * len;
+    let low = (square & u64::MAX as u128) * len;
+    let high = (square >> 64) * len + (low >> 64);
+    let bits = if high != 0 {
+        64 + 128 - high.leading_zeros()
+    } else {
+        64 - (low as u64).leading_zeros()
+    };
+    // `bits` is `⌊log2(len^3)⌋ + 1`, one too many for a power of two.
+    let log2_ceil = if len.is_power_of_two() {
+        bits - 1
+    } else {
+        bits
+    };
+    (log2_ceil as usize).div_ceil(2)
+}
+
+/// Statistics of the last run of `Irs`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct LastRun {
+    len: usize,
+    rounds: usize,
+    distinct: bool,
 }
 
 /// Implementation of Inverse Riffle Shuffle.
@@ -81,161 +369,2399 @@ where
 /// ```
 /// use shuffle::shuffler::Shuffler;
 /// use shuffle::irs::Irs;
-/// use rand::rngs::mock::StepRng;
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
 ///
-/// let mut rng = StepRng::new(2, 13);
+/// let mut rng = StdRng::seed_from_u64(42);
 /// let mut irs = Irs::default();
 ///
 /// let mut input = vec![1, 2, 3, 4, 5];
 ///
-/// irs.shuffle(&mut input, &mut rng);
-/// assert_eq!(&input, &[4, 1, 5, 3, 2]);
+/// irs.shuffle(&mut input, &mut rng).unwrap();
+/// assert_eq!(&input, &[5, 2, 4, 3, 1]);
 /// ```
-#[derive(Debug, Default)]
+///
+/// # Trait bounds
+///
+/// The shuffled elements only have to implement `Clone`, as the
+/// algorithm keeps a copy of the data to rearrange it.
+/// Neither `Default` nor `Copy` is required.
+///
+/// For `Copy` elements the clones cost nothing extra: the calls are
+/// resolved at compile time, and the copy of the data is taken with
+/// `clone_from_slice` and `extend_from_slice`, which the standard library
+/// turns into plain memory copies for such types. So
+/// [`shuffle_copy`](Irs::shuffle_copy) is only a way to state the bound.
+///
+/// # Performance
+///
+/// Every round only moves the random keys along with the indices of the
+/// elements; the data is rearranged once, after the last round. This
+/// makes shuffling large elements several times faster than moving them
+/// in every round, but for small elements such as `u32` it is about 10%
+/// slower, as the final gather through the indices costs more than the
+/// saved copies.
+///
+/// The keys that tell the elements apart are 64-bit, but whenever they
+/// run out of room they are renumbered to smaller values that keep them
+/// apart just as well. So there is no limit on the number of rounds a
+/// shuffle may need, and thus on the length of the data, besides the
+/// cap on rounds that catches broken randomness sources. This costs a
+/// single pass over the slots every few dozen rounds, which only happens
+/// for billions of elements or for fixed runs of more than 64 rounds.
+///
+/// # Empty and single-element inputs
+///
+/// There is nothing to shuffle in fewer than two elements, so such
+/// a shuffle succeeds right away, running no rounds and drawing nothing
+/// from the generator, and leaves it in the same state for whatever uses
+/// it next.
+///
+/// # Allocations
+///
+/// The copy of the data and the other buffers are kept in the `Irs`
+/// between the shuffles and only ever grow, so shuffling inputs of
+/// varying lengths allocates only when an input is longer than all the
+/// ones before it.
+///
+/// # Reproducibility
+///
+/// The permutation only depends on the bytes drawn with `fill_bytes`,
+/// read in the configured [`BitConsumption`]. They are consumed bit by
+/// bit, so nothing depends on the endianness of the platform, the width
+/// of `usize` or the way `rand` samples numbers, and the same bytes give
+/// the same permutation everywhere. The bytes are requested in buffers
+/// of a multiple of 32 bytes, with room for a bit per element, which
+/// makes no difference for the generators of `rand`, as they hand out the
+/// same stream however it is split. The unused rest of the last buffer is
+/// drawn too, though, so the state a generator is left in also depends on
+/// the size of the buffers. Changing the permutation for given bytes is
+/// a breaking change of this crate.
+///
+/// This covers [`shuffle`](Shuffler::shuffle),
+/// [`shuffle_indices`](Shuffler::shuffle_indices) and
+/// [`shuffle_from_bytes`](Irs::shuffle_from_bytes), but not
+/// [`shuffle_low_memory`](Irs::shuffle_low_memory), which also draws
+/// whole words with `next_u64`.
+#[derive(Debug)]
 pub struct Irs<T> {
     context: Option<Context<T>>,
+    slots: Slots,
+    bit_consumption: BitConsumption,
+    max_rounds: usize,
+    fallback: bool,
+    bit_buffer: Vec<u8>,
+    last_run: Option<LastRun>,
+    last_run_fell_back: bool,
+    last_bits_consumed: Option<u64>,
+    /// The number of the drawn bits the last run of rounds used, which
+    /// tells [`shuffle_from_bytes`](Irs::shuffle_from_bytes) whether the
+    /// given bytes were enough.
+    last_bits_used: u64,
+    last_permutation: Option<Vec<usize>>,
+}
+
+// Not derived, as deriving would require `T: Default`.
+impl<T> Default for Irs<T> {
+    fn default() -> Self {
+        Self {
+            context: None,
+            slots: Slots::default(),
+            bit_consumption: BitConsumption::default(),
+            max_rounds: DEFAULT_MAX_ROUNDS,
+            fallback: false,
+            bit_buffer: Vec::new(),
+            last_run: None,
+            last_run_fell_back: false,
+            last_bits_consumed: None,
+            last_bits_used: 0,
+            last_permutation: None,
+        }
+    }
+}
+
+/// A builder for an [`Irs`] with a non-default configuration.
+///
+/// # Examples
+/// ```
+/// use shuffle::shuffler::Shuffler;
+/// use shuffle::irs::{BitConsumption, BitOrder, ByteOrder, Irs, IrsBuilder};
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let mut rng = StdRng::seed_from_u64(42);
+/// let mut irs: Irs<u32> = IrsBuilder::new()
+///     .bit_consumption(BitConsumption {
+///         byte_order: ByteOrder::BigEndian,
+///         bit_order: BitOrder::Msb0,
+///     })
+///     .build();
+///
+/// let mut input = vec![1, 2, 3, 4, 5];
+/// irs.shuffle(&mut input, &mut rng).unwrap();
+/// input.sort();
+/// assert_eq!(&input, &[1, 2, 3, 4, 5]);
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct IrsBuilder {
+    bit_consumption: BitConsumption,
+}
+
+impl IrsBuilder {
+    /// Creates a builder with the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how the random bytes are turned into bits, see [`BitConsumption`].
+    pub fn bit_consumption(mut self, bit_consumption: BitConsumption) -> Self {
+        self.bit_consumption = bit_consumption;
+        self
+    }
+
+    /// Creates the configured `Irs`.
+    pub fn build<T>(self) -> Irs<T> {
+        Irs {
+            bit_consumption: self.bit_consumption,
+            ..Irs::default()
+        }
+    }
 }
 
 impl<T> Shuffler<T> for Irs<T> {
-    fn shuffle<R>(&mut self, data: &mut Vec<T>, rng: &mut R) -> Result<(), &str>
+    fn shuffle_slice<R>(&mut self, data: &mut [T], rng: &mut R) -> Result<(), ShuffleError>
     where
         T: Clone,
         R: RngCore + ?Sized,
     {
-        let mut context = self.get_reset_context(data);
-        let mut initial_buffer = vec![0; 32];
-        rng.fill_bytes(&mut initial_buffer);
-        let mut rand_bit_iter = InfiniteBitIter::new(initial_buffer, rng);
+        self.shuffle_data(data, rng)
+    }
 
-        for _ in 0..128 {
-            self.one_round(&mut context, data, &mut rand_bit_iter);
-            if all_distinct(&context.bit_slots[..]) {
-                self.context = Some(context);
-                return Ok(());
-            }
-        }
-        Err("bad randomness source")
+    fn shuffle_indices<R>(&mut self, len: usize, rng: &mut R) -> Result<Vec<usize>, ShuffleError>
+    where
+        R: RngCore + ?Sized,
+    {
+        Irs::shuffle_indices(self, len, rng)
     }
 }
 
 impl<T> Irs<T> {
-    /// A function that allows us to reuse the old context
-    /// with no new allocations as long as it may be safely used.
+    /// Sets the order in which the bits of the random bytes are consumed.
     ///
-    /// If the old context can be reused, then this function will
-    /// reinitialize it cheaply and move it out of the `Irs`.
+    /// This is mostly useful to reproduce the output of other
+    /// implementations. Note that the permutation produced for a given
+    /// randomness source depends on the order. The order of the bytes
+    /// can be set with [`IrsBuilder::bit_consumption`].
     ///
-    /// If it cannot be used, then this function will create a new
-    /// context and set the internal `context` to `None`.
+    /// # Examples
+    /// ```
+    /// use shuffle::shuffler::Shuffler;
+    /// use shuffle::irs::{BitOrder, Irs};
+    /// use rand::rngs::StdRng;
+    /// use rand::SeedableRng;
     ///
-    /// The function does not return a mutable reference to make
-    /// the borrow checker easier to deal with (as returning the
-    /// reference would create a long-lived mutable reference to `*self`,
-    /// which is bad).
+    /// let mut rng = StdRng::seed_from_u64(42);
+    /// let mut irs = Irs::default().with_bit_order(BitOrder::Msb0);
     ///
-    /// This behavior is kinda lame, so it may change in the future.
-    fn get_reset_context(&mut self, data: &[T]) -> Context<T>
+    /// let mut input = vec![1, 2, 3, 4, 5];
+    ///
+    /// irs.shuffle(&mut input, &mut rng).unwrap();
+    /// assert_eq!(&input, &[5, 1, 4, 2, 3]);
+    /// ```
+    pub fn with_bit_order(mut self, bit_order: BitOrder) -> Self {
+        self.bit_consumption.bit_order = bit_order;
+        self
+    }
+
+    /// Sets the number of rounds after which a shuffle gives up on the
+    /// keys becoming distinct and fails with
+    /// [`BadRandomness`](ShuffleError::BadRandomness). The default is 128.
+    ///
+    /// A working randomness source needs about `log2(len)` rounds, plus
+    /// a few more, so the default is plenty for any length that fits in
+    /// memory. A lower limit makes a broken source, e.g. a mock in
+    /// a test, fail sooner. With a limit of `0` every shuffle fails, or
+    /// falls back to Fisher-Yates, see [`with_fallback`](Irs::with_fallback).
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::shuffler::Shuffler;
+    /// use shuffle::irs::Irs;
+    /// use rand::rngs::StdRng;
+    /// use rand::SeedableRng;
+    ///
+    /// let mut rng = StdRng::seed_from_u64(42);
+    /// let mut irs = Irs::default().with_max_rounds(256);
+    ///
+    /// let mut input: Vec<u32> = (0..1000).collect();
+    /// irs.shuffle(&mut input, &mut rng).unwrap();
+    /// assert!(irs.last_round_count().unwrap() <= 256);
+    /// ```
+    pub fn with_max_rounds(mut self, max_rounds: usize) -> Self {
+        self.max_rounds = max_rounds;
+        self
+    }
+
+    /// Makes a shuffle that runs out of rounds fall back to a single
+    /// Fisher-Yates pass with the same generator, instead of failing with
+    /// [`BadRandomness`](ShuffleError::BadRandomness).
+    ///
+    /// The result is then always a permutation, but the guarantees of the
+    /// inverse riffle shuffle are lost: keys that never became distinct
+    /// mean the generator is broken, and Fisher-Yates driven by a broken
+    /// generator is no more uniform, e.g. a constant one gives the same
+    /// permutation every time. [`last_run_fell_back`](Irs::last_run_fell_back)
+    /// tells when this happened. Only shuffles that draw from a generator
+    /// can fall back, so [`shuffle_with_bit_fn`](Irs::shuffle_with_bit_fn)
+    /// still fails, and so do the `strict` checks, which run before the
+    /// rounds.
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::shuffler::Shuffler;
+    /// use shuffle::irs::Irs;
+    /// use rand::rngs::StdRng;
+    /// use rand::SeedableRng;
+    ///
+    /// let mut rng = StdRng::seed_from_u64(42);
+    /// // Every shuffle runs out of rounds right away.
+    /// let mut irs = Irs::default().with_max_rounds(0).with_fallback();
+    ///
+    /// let mut input = vec![1, 2, 3, 4, 5];
+    /// irs.shuffle(&mut input, &mut rng).unwrap();
+    /// assert!(irs.last_run_fell_back());
+    /// input.sort();
+    /// assert_eq!(input, vec![1, 2, 3, 4, 5]);
+    /// ```
+    pub fn with_fallback(mut self) -> Self {
+        self.fallback = true;
+        self
+    }
+
+    /// Returns `true` if the last shuffle ran out of rounds and fell back
+    /// to Fisher-Yates, see [`with_fallback`](Irs::with_fallback).
+    pub fn last_run_fell_back(&self) -> bool {
+        self.last_run_fell_back
+    }
+
+    /// Computes a random permutation of `0..len` without shuffling any data.
+    ///
+    /// The result is exactly what [`shuffle`](Shuffler::shuffle) would
+    /// produce for the input `(0..len).collect::<Vec<_>>()` and the same
+    /// `rng`, but there is no copy of the data to keep and gather from.
+    /// The slot buffers are kept in the `Irs` and reused across calls, so
+    /// the returned vector is the only allocation. Note that most of the
+    /// time goes into the rounds, which are the same for both methods.
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::irs::Irs;
+    /// use rand::rngs::StdRng;
+    /// use rand::SeedableRng;
+    ///
+    /// let mut rng = StdRng::seed_from_u64(42);
+    /// let mut irs = Irs::<()>::default();
+    ///
+    /// let permutation = irs.shuffle_indices(5, &mut rng).unwrap();
+    /// assert_eq!(&permutation, &[4, 1, 3, 2, 0]);
+    /// ```
+    pub fn shuffle_indices<R>(
+        &mut self,
+        len: usize,
+        rng: &mut R,
+    ) -> Result<Vec<usize>, ShuffleError>
+    where
+        R: RngCore + ?Sized,
+    {
+        self.last_permutation = None;
+        let mut slots = core::mem::take(&mut self.slots);
+        slots.reset(len);
+        let result = self.sort_slots(&mut slots.slots, &mut slots.slots_cp, rng, None, &never);
+        let permutation = slots.slots.iter().map(|s| s.index).collect();
+        self.slots = slots;
+        result.map(|()| permutation)
+    }
+
+    /// Shuffles `data` just like [`shuffle`](Shuffler::shuffle), but also
+    /// returns every byte that was drawn from `rng` in the process.
+    ///
+    /// The returned bytes can be later passed to
+    /// [`shuffle_from_bytes`](Irs::shuffle_from_bytes) to reproduce
+    /// exactly the same permutation.
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::irs::Irs;
+    /// use rand::rngs::StdRng;
+    /// use rand::SeedableRng;
+    ///
+    /// let mut rng = StdRng::seed_from_u64(42);
+    /// let mut irs = Irs::default();
+    ///
+    /// let mut input = vec![1, 2, 3, 4, 5];
+    /// let bytes = irs.shuffle_recording(&mut input, &mut rng).unwrap();
+    ///
+    /// let mut replayed = vec![1, 2, 3, 4, 5];
+    /// irs.shuffle_from_bytes(&mut replayed, &bytes).unwrap();
+    /// assert_eq!(input, replayed);
+    /// ```
+    pub fn shuffle_recording<R>(
+        &mut self,
+        data: &mut Vec<T>,
+        rng: &mut R,
+    ) -> Result<Vec<u8>, ShuffleError>
     where
         T: Clone,
+        R: RngCore + ?Sized,
     {
-        match &mut self.context {
-            Some(c) => {
-                if data.len() != c.data_cp.len() {
-                    self.context = Some(Context::new(data));
-                } else {
-                    c.data_cp.as_mut_slice().clone_from_slice(data);
-                    c.bit_slots.iter_mut().for_each(|c| *c = 0);
-                }
-            }
-            None => {
-                self.context = Some(Context::new(data));
-            }
+        let mut recording_rng = RecordingRng {
+            inner: rng,
+            recorded: Vec::new(),
         };
-        std::mem::replace(&mut self.context, None).unwrap()
+        self.shuffle(data, &mut recording_rng)?;
+        Ok(recording_rng.recorded)
     }
 
-    fn one_round<R>(
-        &self,
-        ctx: &mut Context<T>,
-        data: &mut Vec<T>,
-        rand_bit_iter: &mut InfiniteBitIter<R>,
-    ) where
+    /// Shuffles `data` like [`shuffle_slice`](Shuffler::shuffle_slice),
+    /// for elements that implement `Copy`.
+    ///
+    /// The result is the same as with `shuffle_slice`, which already moves
+    /// `Copy` elements as plain memory copies, see the
+    /// [trait bounds](Irs#trait-bounds). This just spells out the bound,
+    /// e.g. for code that should only ever shuffle numbers.
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::irs::Irs;
+    /// use rand::rngs::StdRng;
+    /// use rand::SeedableRng;
+    ///
+    /// let mut rng = StdRng::seed_from_u64(42);
+    /// let mut irs = Irs::default();
+    ///
+    /// let mut samples = [0.5f32, 1.5, 2.5, 3.5];
+    /// irs.shuffle_copy(&mut samples, &mut rng).unwrap();
+    /// assert!(samples.contains(&2.5));
+    /// ```
+    pub fn shuffle_copy<R>(&mut self, data: &mut [T], rng: &mut R) -> Result<(), ShuffleError>
+    where
+        T: Copy,
+        R: RngCore + ?Sized,
+    {
+        self.shuffle_data(data, rng)
+    }
+
+    /// Shuffles `data` like [`shuffle`](Shuffler::shuffle), but takes
+    /// ownership of it and returns it.
+    ///
+    /// On failure the error comes together with `data`, unchanged, so
+    /// nothing is lost and it is clear who owns the elements.
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::irs::Irs;
+    /// use rand::rngs::mock::StepRng;
+    ///
+    /// // Never produces a single one bit.
+    /// let mut rng = StepRng::new(0, 0);
+    /// let mut irs = Irs::default();
+    ///
+    /// let (_, input) = irs.shuffle_owned(vec![1, 2, 3], &mut rng).unwrap_err();
+    /// assert_eq!(input, vec![1, 2, 3]);
+    /// ```
+    pub fn shuffle_owned<R>(
+        &mut self,
+        mut data: Vec<T>,
+        rng: &mut R,
+    ) -> Result<Vec<T>, (ShuffleError, Vec<T>)>
+    where
         T: Clone,
         R: RngCore + ?Sized,
     {
-        let mut odd_count = 0;
-        for slot in ctx.bit_slots.iter_mut() {
-            *slot = (*slot << 1) | (rand_bit_iter.next_bit() as u64);
-            odd_count += (*slot & 1) as usize;
+        match self.shuffle_data(&mut data, rng) {
+            Ok(()) => Ok(data),
+            Err(error) => Err((error, data)),
         }
+    }
 
-        let mut odd_moved = 0;
-        let mut even_moved = 0;
-
-        let size = data.len();
+    /// Shuffles `data` using scratch memory for at most `max_scratch`
+    /// elements, trading time for space.
+    ///
+    /// [`shuffle`](Shuffler::shuffle) keeps a copy of the data and two
+    /// slot buffers of 16 bytes per element. Here the data is first split
+    /// in place by random bits, like in the rounds, into parts of at most
+    /// `max_scratch` elements (the Rao-Sandelius method), and then each
+    /// part is shuffled on its own and rearranged without copying the
+    /// elements. The result is still uniformly distributed, and besides
+    /// `data` only about `32 * max_scratch` bytes are used.
+    ///
+    /// There are about `log2(len / max_scratch)` splits, each a pass over
+    /// the data, but every split takes the place of a round and the parts
+    /// soon fit in the cache, so the throughput doesn't suffer much: for
+    /// a million `u32` it is about the same as that of
+    /// [`shuffle`](Shuffler::shuffle) even with `max_scratch` as low as 4.
+    /// If `max_scratch` is at least `data.len()`, the result is the same
+    /// as with `shuffle` for the same `rng`.
+    ///
+    /// On error `data` still holds the same elements, but in an
+    /// unspecified order.
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::irs::Irs;
+    /// use rand::rngs::StdRng;
+    /// use rand::SeedableRng;
+    ///
+    /// let mut rng = StdRng::seed_from_u64(42);
+    /// let mut irs = Irs::default();
+    ///
+    /// let mut input: Vec<u32> = (0..1000).collect();
+    /// irs.shuffle_low_memory(&mut input, 100, &mut rng).unwrap();
+    /// input.sort();
+    /// assert_eq!(input, (0..1000).collect::<Vec<_>>());
+    /// ```
+    pub fn shuffle_low_memory<R>(
+        &mut self,
+        data: &mut [T],
+        max_scratch: usize,
+        rng: &mut R,
+    ) -> Result<(), ShuffleError>
+    where
+        R: RngCore + ?Sized,
+    {
+        let max_scratch = max_scratch.max(1);
+        let mut bits = 0;
+        let result = self.split_and_shuffle(data, max_scratch, rng, &mut bits);
+        self.last_bits_consumed = Some(bits);
+        result
+    }
 
-        for i in (0..size).rev() {
-            let current = ctx.bit_slots[i];
-            let position = if current % 2 == 1 {
-                odd_moved += 1;
-                size - odd_moved
+    /// The loop of [`shuffle_low_memory`](Irs::shuffle_low_memory), adding
+    /// the number of random bits it draws to `bits`.
+    fn split_and_shuffle<R>(
+        &mut self,
+        data: &mut [T],
+        max_scratch: usize,
+        rng: &mut R,
+        bits: &mut u64,
+    ) -> Result<(), ShuffleError>
+    where
+        R: RngCore + ?Sized,
+    {
+        // The parts left to split or shuffle, with the number of times
+        // in a row their split left one side empty.
+        let mut pending = vec![(0, data.len(), 0)];
+        let mut fell_back = false;
+        while let Some((start, end, failed)) = pending.pop() {
+            let part = &mut data[start..end];
+            if part.len() <= max_scratch {
+                let result = self.shuffle_in_place(part, rng);
+                *bits += self.last_bits_consumed.unwrap_or(0);
+                fell_back |= self.last_run_fell_back;
+                result?;
+                continue;
+            }
+            if failed == self.max_rounds {
+                if !self.fallback {
+                    return Err(ShuffleError::BadRandomness);
+                }
+                FisherYates::default().shuffle_in_place(part, rng);
+                fell_back = true;
+                continue;
+            }
+            *bits += part.len().div_ceil(64) as u64 * 64;
+            let middle = start + split_by_random_bits(part, rng);
+            if middle == start || middle == end {
+                pending.push((start, end, failed + 1));
             } else {
-                even_moved += 1;
-                size - odd_count - even_moved
-            };
-            ctx.bit_slots_cp[position] = current;
-            ctx.data_cp[position] = data[i].clone();
+                pending.push((middle, end, 0));
+                pending.push((start, middle, 0));
+            }
         }
-
-        std::mem::swap(&mut ctx.data_cp, data);
-        std::mem::swap(&mut ctx.bit_slots_cp, &mut ctx.bit_slots);
+        self.last_run_fell_back = fell_back;
+        Ok(())
     }
-}
-
-/// Tests whether all elements in a sorted slice are unique.
-///
-/// For a sorted slice this can be done in linear time, by
-/// comparing each two consequtive items in the slice.
-///
-fn all_distinct<T>(sorted_data: &[T]) -> bool
-where
-    T: Eq,
-{
-    !sorted_data.windows(2).any(|s| s[0] == s[1])
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_irs() {
-        let mut irs = Irs::default();
-        let mut rng = rand::rngs::mock::StepRng::new(1, 1);
 
-        let input_data = vec![1, 2, 3, 4];
-        let mut target = input_data.clone();
-        irs.shuffle(&mut target, &mut rng).unwrap();
-        assert_eq!(target.len(), input_data.len());
-        assert_ne!(target, input_data);
-        assert!(target.iter().all(|n| input_data.contains(n)));
+    /// Shuffles `data` using `bytes` as the only source of randomness.
+    ///
+    /// If the shuffle needs more randomness than `bytes` provide, counted
+    /// in whole 32-bit words, an error is returned and `data` is left
+    /// unchanged. Bytes past the ones the rounds used are ignored, even
+    /// though they may have been drawn into the buffer.
+    pub fn shuffle_from_bytes(&mut self, data: &mut [T], bytes: &[u8]) -> Result<(), ShuffleError>
+    where
+        T: Clone,
+    {
+        let mut replay_rng = ReplayRng {
+            bytes,
+            exhausted: false,
+        };
+        let result = self.shuffle_data(data, &mut replay_rng);
+        let bytes_used = self.last_bits_used.div_ceil(32) * 4;
+        if replay_rng.exhausted && bytes_used > bytes.len() as u64 {
+            // After a successful shuffle the context holds the original data.
+            if let (Ok(()), Some(context)) = (result, &self.context) {
+                data.clone_from_slice(&context.data_cp);
+            }
+            return Err(ShuffleError::NotEnoughRandomBytes);
+        }
+        result
     }
 
-    #[test]
-    fn test_get_reset_context() {
-        let mut irs = Irs::default();
+    /// Shuffles `data` using the bits returned by `bit_fn` as the only
+    /// source of randomness.
+    ///
+    /// This is the most general way to drive the shuffle, e.g. from dice
+    /// rolls or a hardware entropy source, and it does not involve `rand`
+    /// at all. `bit_fn` is called once per element in every round, until
+    /// the permutation is settled. The configured bit consumption and the
+    /// `strict` checks only apply to random bytes, so they are ignored here.
+    ///
+    /// If the bits don't settle the permutation within the round limit
+    /// (e.g. `bit_fn` always returns `false`), an error is returned and
+    /// `data` is left unchanged.
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::irs::Irs;
+    ///
+    /// let mut bits = [true, false, true, true, false, false].iter().cycle();
+    /// let mut irs = Irs::default();
+    ///
+    /// let mut input = vec![1, 2, 3];
+    /// irs.shuffle_with_bit_fn(&mut input, || *bits.next().unwrap()).unwrap();
+    /// assert_eq!(&input, &[3, 1, 2]);
+    /// ```
+    pub fn shuffle_with_bit_fn<F>(
+        &mut self,
+        data: &mut [T],
+        mut bit_fn: F,
+    ) -> Result<(), ShuffleError>
+    where
+        T: Clone,
+        F: FnMut() -> bool,
+    {
+        self.shuffle_data_with(data, |irs, slots, slots_cp| {
+            let result = irs.run_rounds(slots, slots_cp, &mut bit_fn, None, &never);
+            // `bit_fn` is called once per slot in every round.
+            irs.last_bits_consumed = irs.last_run.map(|run| (run.rounds * run.len) as u64);
+            result
+        })
+    }
 
-        let input_data = vec![1, 2, 3, 4];
+    /// Shuffles `data` running exactly `rounds` rounds of the algorithm,
+    /// no matter whether the keys became distinct.
+    ///
+    /// With too few rounds the resulting permutation is **not** uniformly
+    /// distributed; use [`last_run_mixed_well`](Irs::last_run_mixed_well)
+    /// to check the run. This is mostly useful for experiments.
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::irs::Irs;
+    /// use rand::rngs::StdRng;
+    /// use rand::SeedableRng;
+    ///
+    /// let mut rng = StdRng::seed_from_u64(42);
+    /// let mut irs = Irs::default();
+    ///
+    /// let mut input = vec![1, 2, 3, 4, 5];
+    /// irs.shuffle_fixed_rounds(&mut input, 0, &mut rng).unwrap();
+    /// assert_eq!(&input, &[1, 2, 3, 4, 5]);
+    /// ```
+    pub fn shuffle_fixed_rounds<R>(
+        &mut self,
+        data: &mut [T],
+        rounds: usize,
+        rng: &mut R,
+    ) -> Result<(), ShuffleError>
+    where
+        T: Clone,
+        R: RngCore + ?Sized,
+    {
+        self.shuffle_data_with(data, |irs, slots, slots_cp| {
+            irs.sort_slots(slots, slots_cp, rng, Some(rounds), &never)
+        })
+    }
+
+    /// Shuffles `data` like [`shuffle`](Shuffler::shuffle), but gives up
+    /// as soon as `should_cancel` returns `true`.
+    ///
+    /// `should_cancel` is called before every round, so a cancellation is
+    /// noticed within a round, i.e. a single pass over the slots. A
+    /// cancelled shuffle returns [`ShuffleError::Cancelled`] and leaves
+    /// `data` in its original order, as the data is only rearranged after
+    /// the last round.
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::irs::Irs;
+    /// use shuffle::shuffler::ShuffleError;
+    /// use rand::rngs::StdRng;
+    /// use rand::SeedableRng;
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    ///
+    /// let mut rng = StdRng::seed_from_u64(42);
+    /// let mut irs = Irs::default();
+    /// let shutting_down = AtomicBool::new(true);
+    ///
+    /// let mut input = vec![1, 2, 3, 4, 5];
+    /// let result = irs.shuffle_cancellable(&mut input, &mut rng, || {
+    ///     shutting_down.load(Ordering::Relaxed)
+    /// });
+    /// assert_eq!(result, Err(ShuffleError::Cancelled));
+    /// assert_eq!(&input, &[1, 2, 3, 4, 5]);
+    /// ```
+    pub fn shuffle_cancellable<R, F>(
+        &mut self,
+        data: &mut [T],
+        rng: &mut R,
+        should_cancel: F,
+    ) -> Result<(), ShuffleError>
+    where
+        T: Clone,
+        R: RngCore + ?Sized,
+        F: Fn() -> bool,
+    {
+        self.shuffle_data_with(data, |irs, slots, slots_cp| {
+            irs.sort_slots(slots, slots_cp, rng, None, &should_cancel)
+        })
+    }
+
+    /// Tells whether the last shuffle mixed the data well enough for the
+    /// permutation to be uniformly distributed.
+    ///
+    /// A run that stopped on distinct keys always mixed well, as the
+    /// permutation is then exactly uniform. A run with a fixed number of
+    /// rounds (see [`shuffle_fixed_rounds`](Irs::shuffle_fixed_rounds))
+    /// mixed well if its keys ended up distinct anyway, or if it ran
+    /// a margin of rounds past [`expected_rounds`].
+    ///
+    /// Returns `None` if nothing was shuffled yet.
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::shuffler::Shuffler;
+    /// use shuffle::irs::Irs;
+    /// use rand::rngs::StdRng;
+    /// use rand::SeedableRng;
+    ///
+    /// let mut rng = StdRng::seed_from_u64(42);
+    /// let mut irs = Irs::default();
+    /// assert_eq!(irs.last_run_mixed_well(), None);
+    ///
+    /// let mut input = vec![1, 2, 3, 4, 5];
+    /// irs.shuffle(&mut input, &mut rng).unwrap();
+    /// assert_eq!(irs.last_run_mixed_well(), Some(true));
+    /// ```
+    pub fn last_run_mixed_well(&self) -> Option<bool> {
+        self.last_run
+            .map(|run| run.distinct || run.rounds >= expected_rounds(run.len) + MIXING_MARGIN)
+    }
+
+    /// Returns the number of rounds the last shuffle ran, including
+    /// a shuffle that failed after running out of rounds.
+    ///
+    /// Every round consumes one random bit per element, so this tells
+    /// how much randomness the shuffle used. Returns `None` if nothing was
+    /// shuffled yet or the last shuffle was cancelled. For
+    /// [`shuffle_low_memory`](Irs::shuffle_low_memory) it is the count
+    /// of the last part that was shuffled.
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::shuffler::Shuffler;
+    /// use shuffle::irs::Irs;
+    /// use rand::rngs::StdRng;
+    /// use rand::SeedableRng;
+    ///
+    /// let mut rng = StdRng::seed_from_u64(42);
+    /// let mut irs = Irs::default();
+    /// assert_eq!(irs.last_round_count(), None);
+    ///
+    /// let mut input: Vec<u32> = (0..1000).collect();
+    /// irs.shuffle(&mut input, &mut rng).unwrap();
+    /// assert!(irs.last_round_count().unwrap() >= 10);
+    /// ```
+    pub fn last_round_count(&self) -> Option<usize> {
+        self.last_run.map(|run| run.rounds)
+    }
+
+    /// Returns the number of random bits the last shuffle drew, or `None`
+    /// if nothing was shuffled yet or the last shuffle was cancelled.
+    ///
+    /// The random bytes are drawn with `fill_bytes` into a buffer of
+    /// a multiple of 32 bytes with room for a bit per element, so this is
+    /// a multiple of the size of the buffer, which counts the bits left
+    /// unused in the last one too. The rounds use one bit per element each, so at least
+    /// about `rounds * len` of them are needed. For
+    /// [`shuffle_with_bit_fn`](Irs::shuffle_with_bit_fn) it is the number
+    /// of calls of the function, and for
+    /// [`shuffle_low_memory`](Irs::shuffle_low_memory) the total over all
+    /// splits and parts, each split drawing a 64-bit word per 64 elements.
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::shuffler::Shuffler;
+    /// use shuffle::irs::Irs;
+    /// use rand::rngs::StdRng;
+    /// use rand::SeedableRng;
+    ///
+    /// let mut rng = StdRng::seed_from_u64(42);
+    /// let mut irs = Irs::default();
+    /// assert_eq!(irs.last_bits_consumed(), None);
+    ///
+    /// let mut input: Vec<u32> = (0..1000).collect();
+    /// irs.shuffle(&mut input, &mut rng).unwrap();
+    /// let rounds = irs.last_round_count().unwrap() as u64;
+    /// let bits = irs.last_bits_consumed().unwrap();
+    /// // The buffer has room for 1024 bits.
+    /// assert_eq!(bits % 1024, 0);
+    /// assert!(bits >= rounds * 1000 && bits < rounds * 1000 + 1024);
+    /// ```
+    pub fn last_bits_consumed(&self) -> Option<u64> {
+        self.last_bits_consumed
+    }
+
+    /// Returns the permutation applied by the last shuffle, without
+    /// copying it.
+    ///
+    /// The element at position `i` of the shuffled data is the one that
+    /// was at position `permutation[i]` before. The slice borrows the
+    /// `Irs`, so it has to be dropped before the next shuffle, which
+    /// overwrites it.
+    ///
+    /// Returns `None` if nothing was shuffled yet or the last shuffle
+    /// failed. The methods that don't copy the data, i.e.
+    /// [`shuffle_indices`](Irs::shuffle_indices) (which returns the
+    /// permutation anyway) and [`shuffle_low_memory`](Irs::shuffle_low_memory),
+    /// don't keep it either and return `None` here.
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::shuffler::Shuffler;
+    /// use shuffle::irs::Irs;
+    /// use rand::rngs::StdRng;
+    /// use rand::SeedableRng;
+    ///
+    /// let mut rng = StdRng::seed_from_u64(42);
+    /// let mut irs = Irs::default();
+    ///
+    /// let mut input = vec![1, 2, 3, 4, 5];
+    /// irs.shuffle(&mut input, &mut rng).unwrap();
+    /// assert_eq!(irs.last_permutation(), Some(&[4, 1, 3, 2, 0][..]));
+    /// ```
+    pub fn last_permutation(&self) -> Option<&[usize]> {
+        self.last_permutation.as_deref()
+    }
+}
+
+impl<T> Irs<T> {
+    /// A function that allows us to reuse the old context, so that
+    /// nothing is allocated unless `data` is longer than any data before.
+    ///
+    /// The old context, if any, is reinitialized with `data` and moved
+    /// out of the `Irs`, leaving the internal `context` as `None`.
+    /// Otherwise a new context is created.
+    ///
+    /// The function does not return a mutable reference to make
+    /// the borrow checker easier to deal with (as returning the
+    /// reference would create a long-lived mutable reference to `*self`,
+    /// which is bad).
+    fn get_reset_context(&mut self, data: &[T]) -> Context<T>
+    where
+        T: Clone,
+    {
+        match self.context.take() {
+            Some(mut context) => {
+                context.reset(data);
+                context
+            }
+            None => Context::new(data),
+        }
+    }
+
+    /// The actual implementation of [`shuffle`](Shuffler::shuffle).
+    fn shuffle_data<R>(&mut self, data: &mut [T], rng: &mut R) -> Result<(), ShuffleError>
+    where
+        T: Clone,
+        R: RngCore + ?Sized,
+    {
+        self.shuffle_data_with(data, |irs, slots, slots_cp| {
+            irs.sort_slots(slots, slots_cp, rng, None, &never)
+        })
+    }
+
+    /// Rearranges `data` according to the slots sorted by `sort`.
+    ///
+    /// `data` is left unchanged if `sort` fails.
+    fn shuffle_data_with<F>(&mut self, data: &mut [T], sort: F) -> Result<(), ShuffleError>
+    where
+        T: Clone,
+        F: FnOnce(&mut Self, &mut Vec<Slot>, &mut Vec<Slot>) -> Result<(), ShuffleError>,
+    {
+        let context = self.get_reset_context(data);
+        let mut slots = core::mem::take(&mut self.slots);
+        let mut permutation = self.last_permutation.take().unwrap_or_default();
+        slots.reset(data.len());
+        let result = sort(self, &mut slots.slots, &mut slots.slots_cp);
+        if result.is_ok() {
+            // The gather below would silently skip elements otherwise.
+            debug_assert_eq!(slots.slots.len(), data.len());
+            debug_assert_eq!(context.data_cp.len(), data.len());
+            permutation.clear();
+            for (target, slot) in data.iter_mut().zip(slots.slots.iter()) {
+                *target = context.data_cp[slot.index].clone();
+                permutation.push(slot.index);
+            }
+            self.last_permutation = Some(permutation);
+        }
+        self.slots = slots;
+        self.context = Some(context);
+        result
+    }
+
+    /// Shuffles `data` by sorting the cached slots and moving the elements
+    /// along the cycles of the resulting permutation.
+    ///
+    /// Unlike [`shuffle_data`](Irs::shuffle_data) this doesn't copy the
+    /// data, so it works for any element type.
+    fn shuffle_in_place<U, R>(&mut self, data: &mut [U], rng: &mut R) -> Result<(), ShuffleError>
+    where
+        R: RngCore + ?Sized,
+    {
+        self.last_permutation = None;
+        let mut slots = core::mem::take(&mut self.slots);
+        slots.reset(data.len());
+        let result = self.sort_slots(&mut slots.slots, &mut slots.slots_cp, rng, None, &never);
+        if result.is_ok() {
+            let permutation = &mut slots.slots;
+            for start in 0..permutation.len() {
+                let mut position = start;
+                loop {
+                    let source = permutation[position].index;
+                    // Marks the position as done.
+                    permutation[position].index = position;
+                    if source == start {
+                        break;
+                    }
+                    data.swap(position, source);
+                    position = source;
+                }
+            }
+        }
+        self.slots = slots;
+        result
+    }
+
+    /// Runs rounds of the inverse riffle shuffle over `slots` until all
+    /// the keys are distinct, or exactly `fixed_rounds` of them if given.
+    ///
+    /// Returns an error if the keys didn't become distinct within the
+    /// round limit, or if `should_cancel` returned `true` before a round.
+    fn sort_slots<R>(
+        &mut self,
+        slots: &mut Vec<Slot>,
+        slots_cp: &mut Vec<Slot>,
+        rng: &mut R,
+        fixed_rounds: Option<usize>,
+        should_cancel: &dyn Fn() -> bool,
+    ) -> Result<(), ShuffleError>
+    where
+        R: RngCore + ?Sized,
+    {
+        self.last_run_fell_back = false;
+        if slots.len() <= 1 {
+            let mut no_bits = || -> bool { unreachable!("nothing to shuffle") };
+            let result =
+                self.run_rounds(slots, slots_cp, &mut no_bits, fixed_rounds, should_cancel);
+            self.last_bits_consumed = Some(0);
+            self.last_bits_used = 0;
+            return result;
+        }
+        // The buffer is kept in `self` between the calls, so that it is
+        // allocated only once.
+        let mut initial_buffer = core::mem::take(&mut self.bit_buffer);
+        initial_buffer.resize(bit_buffer_len(slots.len()), 0);
+        rng.fill_bytes(&mut initial_buffer);
+        #[cfg(feature = "strict")]
+        {
+            if looks_degenerate(&initial_buffer[..BIT_BUFFER_CHUNK]) {
+                self.last_bits_consumed = Some(initial_buffer.len() as u64 * 8);
+                self.last_bits_used = BIT_BUFFER_CHUNK as u64 * 8;
+                self.bit_buffer = initial_buffer;
+                return Err(ShuffleError::DegenerateRandomness);
+            }
+        }
+        let BitConsumption {
+            byte_order,
+            bit_order,
+        } = self.bit_consumption;
+        let (result, bits_drawn, bits_used, buffer) = match bit_order {
+            BitOrder::Lsb0 => {
+                let mut bits = InfiniteBitIter::<Lsb0, R>::new(initial_buffer, byte_order, rng);
+                let result =
+                    self.run_rounds(slots, slots_cp, &mut bits, fixed_rounds, should_cancel);
+                (
+                    result,
+                    bits.bits_drawn(),
+                    bits.bits_used(),
+                    bits.into_bytes(),
+                )
+            }
+            BitOrder::Msb0 => {
+                let mut bits = InfiniteBitIter::<Msb0, R>::new(initial_buffer, byte_order, rng);
+                let result =
+                    self.run_rounds(slots, slots_cp, &mut bits, fixed_rounds, should_cancel);
+                (
+                    result,
+                    bits.bits_drawn(),
+                    bits.bits_used(),
+                    bits.into_bytes(),
+                )
+            }
+        };
+        self.bit_buffer = buffer;
+        self.last_bits_consumed = self.last_run.map(|_| bits_drawn);
+        self.last_bits_used = bits_used;
+        if self.fallback && result == Err(ShuffleError::BadRandomness) {
+            for (index, slot) in slots.iter_mut().enumerate() {
+                *slot = Slot { key: 0, index };
+            }
+            FisherYates::default().shuffle_in_place(slots.as_mut_slice(), rng);
+            self.last_run_fell_back = true;
+            return Ok(());
+        }
+        result
+    }
+
+    /// The rounds loop of [`sort_slots`](Irs::sort_slots).
+    ///
+    /// Every run is recorded in `last_run`, except for a cancelled one,
+    /// which clears it.
+    fn run_rounds<B>(
+        &mut self,
+        slots: &mut Vec<Slot>,
+        slots_cp: &mut Vec<Slot>,
+        rand_bit_iter: &mut B,
+        fixed_rounds: Option<usize>,
+        should_cancel: &dyn Fn() -> bool,
+    ) -> Result<(), ShuffleError>
+    where
+        B: BitSource,
+    {
+        let len = slots.len();
+        if len <= 1 {
+            self.last_run = Some(LastRun {
+                len,
+                rounds: 0,
+                distinct: true,
+            });
+            return Ok(());
+        }
+        let mut key_bits = 0;
+        if let Some(rounds) = fixed_rounds {
+            for _ in 0..rounds {
+                if should_cancel() {
+                    self.last_run = None;
+                    return Err(ShuffleError::Cancelled);
+                }
+                self.next_round(slots, slots_cp, rand_bit_iter, &mut key_bits);
+            }
+            let distinct = all_distinct(slots.iter().map(|s| s.key));
+            self.last_run = Some(LastRun {
+                len,
+                rounds,
+                distinct,
+            });
+            return Ok(());
+        }
+        for rounds in 1..=self.max_rounds {
+            if should_cancel() {
+                self.last_run = None;
+                return Err(ShuffleError::Cancelled);
+            }
+            self.next_round(slots, slots_cp, rand_bit_iter, &mut key_bits);
+            if can_be_distinct(rounds, len) && all_distinct(slots.iter().map(|s| s.key)) {
+                self.last_run = Some(LastRun {
+                    len,
+                    rounds,
+                    distinct: true,
+                });
+                return Ok(());
+            }
+        }
+        self.last_run = Some(LastRun {
+            len,
+            rounds: self.max_rounds,
+            distinct: false,
+        });
+        Err(ShuffleError::BadRandomness)
+    }
+
+    /// Performs a round like [`one_round`](Irs::one_round), first
+    /// compacting the keys if they have no room left for another bit.
+    ///
+    /// `key_bits` is the number of bits the keys may occupy, so a run
+    /// that needs more than 64 rounds, e.g. for a huge deck, keeps
+    /// telling apart all the elements whose bits ever differed. Below
+    /// 64 rounds this is just a counter.
+    fn next_round<B>(
+        &self,
+        slots: &mut Vec<Slot>,
+        slots_cp: &mut Vec<Slot>,
+        rand_bit_iter: &mut B,
+        key_bits: &mut u32,
+    ) where
+        B: BitSource,
+    {
+        if *key_bits == u64::BITS {
+            *key_bits = compact_keys(slots);
+        }
+        self.one_round(slots, slots_cp, rand_bit_iter);
+        *key_bits += 1;
+    }
+
+    /// Performs a single round of the inverse riffle shuffle.
+    ///
+    /// Every slot gets a new random bit appended to its key, then
+    /// the slots are stably partitioned so that the ones whose new bit
+    /// is `0` come first. Only the slots are moved, the data is
+    /// rearranged by the caller once the keys are all distinct.
+    ///
+    /// Both buffers must have the same length, see [`Slots::reset`].
+    fn one_round<B>(&self, slots: &mut Vec<Slot>, slots_cp: &mut Vec<Slot>, rand_bit_iter: &mut B)
+    where
+        B: BitSource,
+    {
+        debug_assert_eq!(slots.len(), slots_cp.len(), "slot buffers out of sync");
+        let mut odd_count = 0;
+        for slot in slots.iter_mut() {
+            slot.key = (slot.key << 1) | (rand_bit_iter.next_bit() as u64);
+            odd_count += (slot.key & 1) as usize;
+        }
+
+        let mut odd_moved = 0;
+        let mut even_moved = 0;
+
+        let size = slots.len();
+
+        // The odd keys go to `size - 1, size - 2, ...` and the even ones
+        // to `size - odd_count - 1, ...`, so every position of `slots_cp`
+        // is written exactly once and its old contents never leak through,
+        // which is what makes reusing a context safe.
+        for i in (0..size).rev() {
+            let current = slots[i];
+            let position = if current.key % 2 == 1 {
+                odd_moved += 1;
+                size - odd_moved
+            } else {
+                even_moved += 1;
+                size - odd_count - even_moved
+            };
+            slots_cp[position] = current;
+        }
+
+        core::mem::swap(slots_cp, slots);
+    }
+}
+
+/// An `RngCore` that forwards to another one and records all
+/// the bytes that it hands out.
+struct RecordingRng<'a, R>
+where
+    R: ?Sized,
+{
+    inner: &'a mut R,
+    recorded: Vec<u8>,
+}
+
+impl<'a, R> RngCore for RecordingRng<'a, R>
+where
+    R: RngCore + ?Sized,
+{
+    fn next_u32(&mut self) -> u32 {
+        let value = self.inner.next_u32();
+        self.recorded.extend_from_slice(&value.to_le_bytes());
+        value
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let value = self.inner.next_u64();
+        self.recorded.extend_from_slice(&value.to_le_bytes());
+        value
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.inner.fill_bytes(dest);
+        self.recorded.extend_from_slice(dest);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.inner.try_fill_bytes(dest)?;
+        self.recorded.extend_from_slice(dest);
+        Ok(())
+    }
+}
+
+/// An `RngCore` that hands out bytes from a slice.
+///
+/// Once the slice runs out, zeros are returned and `exhausted` is set.
+struct ReplayRng<'a> {
+    bytes: &'a [u8],
+    exhausted: bool,
+}
+
+impl<'a> RngCore for ReplayRng<'a> {
+    fn next_u32(&mut self) -> u32 {
+        let mut buffer = [0; 4];
+        self.fill_bytes(&mut buffer);
+        u32::from_le_bytes(buffer)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut buffer = [0; 8];
+        self.fill_bytes(&mut buffer);
+        u64::from_le_bytes(buffer)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        let available = dest.len().min(self.bytes.len());
+        let (head, tail) = dest.split_at_mut(available);
+        head.copy_from_slice(&self.bytes[..available]);
+        tail.iter_mut().for_each(|b| *b = 0);
+        self.exhausted |= !tail.is_empty();
+        self.bytes = &self.bytes[available..];
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// Moves the elements of `data` that get a zero random bit to the front,
+/// in place, and returns how many of them there are.
+///
+/// The order of the elements on each side is not preserved.
+fn split_by_random_bits<T, R>(data: &mut [T], rng: &mut R) -> usize
+where
+    R: RngCore + ?Sized,
+{
+    let mut zeros = 0;
+    let mut bits = 0;
+    for i in 0..data.len() {
+        if i % 64 == 0 {
+            bits = rng.next_u64();
+        }
+        if bits & 1 == 0 {
+            data.swap(i, zeros);
+            zeros += 1;
+        }
+        bits >>= 1;
+    }
+    zeros
+}
+
+#[cfg(feature = "global-cache")]
+thread_local! {
+    static CACHED_IRS: std::cell::RefCell<Irs<()>> = std::cell::RefCell::new(Irs::default());
+}
+
+/// Shuffles `data` with an [`Irs`] cached per thread.
+///
+/// This saves passing an `Irs` around just to reuse its buffers: the
+/// buffers grow to the largest length shuffled so far and are reused by
+/// every later call on the same thread. The elements are moved in place
+/// rather than copied, so they don't have to implement `Clone`. For
+/// a given `rng` the result is the same as with
+/// [`Irs::shuffle`](Shuffler::shuffle) on a default `Irs`.
+///
+/// Every thread has its own cache, so there is no locking and no
+/// sharing between threads; the memory of a cache is only released when
+/// its thread exits. If `rng` itself calls `shuffle_cached`, the nested
+/// call uses a fresh `Irs` instead of the cached one.
+///
+/// Available with the `global-cache` feature.
+///
+/// # Examples
+/// ```
+/// use shuffle::irs;
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let mut rng = StdRng::seed_from_u64(42);
+/// let mut input = vec![1, 2, 3, 4, 5];
+///
+/// irs::shuffle_cached(&mut input, &mut rng).unwrap();
+/// input.sort();
+/// assert_eq!(&input, &[1, 2, 3, 4, 5]);
+/// ```
+#[cfg(feature = "global-cache")]
+pub fn shuffle_cached<T, R>(data: &mut [T], rng: &mut R) -> Result<(), ShuffleError>
+where
+    R: RngCore + ?Sized,
+{
+    CACHED_IRS.with(|cache| match cache.try_borrow_mut() {
+        Ok(mut irs) => irs.shuffle_in_place(data, rng),
+        Err(_) => Irs::<()>::default().shuffle_in_place(data, rng),
+    })
+}
+
+/// The smallest number of distinct values a good randomness source
+/// is expected to produce among the first bytes of a shuffle.
+///
+/// For 32 uniformly random bytes, seeing at most 7 distinct values has
+/// a probability far below `2^-100`, while counters such as `StepRng`
+/// with a small step produce mostly zero bytes.
+#[cfg(feature = "strict")]
+const MIN_DISTINCT_BYTES: usize = 8;
+
+/// Tells whether `bytes` look too regular to come from a good
+/// randomness source.
+#[cfg(feature = "strict")]
+fn looks_degenerate(bytes: &[u8]) -> bool {
+    let mut seen = [false; 256];
+    bytes.iter().for_each(|&b| seen[b as usize] = true);
+    seen.iter().filter(|&&s| s).count() < MIN_DISTINCT_BYTES
+}
+
+/// Replaces the keys by the smallest numbers that keep equal keys equal
+/// and different ones different, and returns the number of bits they
+/// now take.
+///
+/// The rounds keep equal keys next to each other: a round only ever
+/// splits a run of equal keys by the new bit, and the slots with the
+/// same bit stay in order. So the runs can simply be numbered in order,
+/// and the new keys are below `slots.len()`.
+fn compact_keys(slots: &mut [Slot]) -> u32 {
+    let mut previous = match slots.first() {
+        Some(slot) => slot.key,
+        None => return 0,
+    };
+    let mut run = 0u64;
+    for slot in slots.iter_mut() {
+        if slot.key != previous {
+            previous = slot.key;
+            run += 1;
+        }
+        slot.key = run;
+    }
+    u64::BITS - run.leading_zeros()
+}
+
+/// The cancellation check of the shuffles that can't be cancelled.
+fn never() -> bool {
+    false
+}
+
+/// Tells whether `len` keys built from `rounds` random bits each can be
+/// all distinct, i.e. whether there are at least `len` possible keys.
+///
+/// Before that many rounds the scan for duplicates can be skipped.
+fn can_be_distinct(rounds: usize, len: usize) -> bool {
+    rounds >= 64 || 1u64 << rounds >= len as u64
+}
+
+/// Tests whether all elements in a sorted sequence are unique.
+///
+/// For a sorted sequence this can be done in linear time, by
+/// comparing each two consequtive items in the sequence.
+///
+fn all_distinct<I>(sorted_data: I) -> bool
+where
+    I: IntoIterator,
+    I::Item: Eq,
+{
+    let mut iter = sorted_data.into_iter();
+    let mut previous = match iter.next() {
+        Some(first) => first,
+        None => return true,
+    };
+    for current in iter {
+        if current == previous {
+            return false;
+        }
+        previous = current;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{chi_square_critical_value, counts_chi_square};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::HashSet;
+
+    /// Panics on any draw.
+    struct NoRandomness;
+
+    impl RngCore for NoRandomness {
+        fn next_u32(&mut self) -> u32 {
+            unreachable!("no randomness may be drawn")
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            unreachable!("no randomness may be drawn")
+        }
+
+        fn fill_bytes(&mut self, _dest: &mut [u8]) {
+            unreachable!("no randomness may be drawn")
+        }
+
+        fn try_fill_bytes(&mut self, _dest: &mut [u8]) -> Result<(), rand::Error> {
+            unreachable!("no randomness may be drawn")
+        }
+    }
+
+    /// Repeats a 32-byte block of 16 different bytes, in which the bits
+    /// come in equal pairs, so it passes the `strict` checks.
+    ///
+    /// Two slots next to each other at an even position get the same bit
+    /// in every round, so they stay together and the keys of an even
+    /// number of slots never become distinct. The words of `next_u64`
+    /// come from a counter instead, so that sampling a number with them,
+    /// e.g. for a fallback, doesn't keep rejecting the same few words.
+    struct PairedBits {
+        position: usize,
+        words: u64,
+    }
+
+    impl PairedBits {
+        fn new() -> Self {
+            Self {
+                position: 0,
+                words: 0,
+            }
+        }
+
+        fn byte(index: usize) -> u8 {
+            let nibble = index % 16;
+            (0..4)
+                .filter(|bit| nibble >> bit & 1 == 1)
+                .map(|bit| 0b11 << (2 * bit))
+                .sum()
+        }
+    }
+
+    impl RngCore for PairedBits {
+        fn next_u32(&mut self) -> u32 {
+            self.next_u64() as u32
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            self.words += 1;
+            self.words
+                .wrapping_mul(0x9e37_79b9_7f4a_7c15)
+                .rotate_left(29)
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            for byte in dest {
+                *byte = Self::byte(self.position % 32);
+                self.position += 1;
+            }
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_small_inputs_draw_nothing() {
+        let mut irs = Irs::default();
+        let mut rng = NoRandomness;
+        for len in [0, 1] {
+            let input_data: Vec<u32> = (0..len).collect();
+            let mut target = input_data.clone();
+            irs.shuffle(&mut target, &mut rng).unwrap();
+            assert_eq!(irs.last_round_count(), Some(0));
+            assert_eq!(irs.last_bits_consumed(), Some(0));
+            assert_eq!(irs.last_run_mixed_well(), Some(true));
+            assert_eq!(irs.last_permutation().unwrap().len(), len as usize);
+
+            irs.shuffle_low_memory(&mut target, 1, &mut rng).unwrap();
+            irs.shuffle_fixed_rounds(&mut target, 10, &mut rng).unwrap();
+            irs.shuffle_cancellable(&mut target, &mut rng, || true)
+                .unwrap();
+            irs.shuffle_with_bit_fn(&mut target, || unreachable!())
+                .unwrap();
+            irs.shuffle_from_bytes(&mut target, &[]).unwrap();
+            assert_eq!(target, input_data);
+            assert_eq!(
+                irs.shuffle_indices(len as usize, &mut rng).unwrap().len(),
+                len as usize
+            );
+        }
+    }
+
+    #[test]
+    fn test_irs() {
+        let mut irs = Irs::default();
+        let mut rng = StdRng::seed_from_u64(1);
+
+        let input_data = vec![1, 2, 3, 4];
+        let mut target = input_data.clone();
+        irs.shuffle(&mut target, &mut rng).unwrap();
+        assert_eq!(target.len(), input_data.len());
+        assert_ne!(target, input_data);
+        assert!(target.iter().all(|n| input_data.contains(n)));
+    }
+
+    #[test]
+    fn test_minimal_trait_bounds() {
+        #[derive(Clone, Debug, PartialEq)]
+        struct NoDefault(u32);
+
+        let mut rng = StdRng::seed_from_u64(8);
+
+        let strings: Vec<String> = (0..20).map(|i| i.to_string()).collect();
+        let mut target = strings.clone();
+        Irs::default().shuffle(&mut target, &mut rng).unwrap();
+        assert!(strings.iter().all(|s| target.contains(s)));
+
+        let boxes: Vec<Box<i32>> = (0..20).map(Box::new).collect();
+        let mut target = boxes.clone();
+        Irs::default().shuffle(&mut target, &mut rng).unwrap();
+        assert_ne!(target, boxes);
+        target.sort();
+        assert_eq!(target, boxes);
+
+        let values: Vec<NoDefault> = (0..20).map(NoDefault).collect();
+        let mut target = values.clone();
+        Irs::default().shuffle(&mut target, &mut rng).unwrap();
+        assert!(values.iter().all(|v| target.contains(v)));
+    }
+
+    #[cfg(feature = "strict")]
+    #[test]
+    fn test_strict_rejects_degenerate_rng() {
+        let input_data: Vec<u32> = (0..20).collect();
+
+        let mut target = input_data.clone();
+        let mut rng = rand::rngs::mock::StepRng::new(2, 13);
+        assert_eq!(
+            Irs::default().shuffle(&mut target, &mut rng),
+            Err(ShuffleError::DegenerateRandomness)
+        );
+        assert_eq!(target, input_data);
+
+        let mut rng = StdRng::seed_from_u64(1);
+        assert_eq!(Irs::default().shuffle(&mut target, &mut rng), Ok(()));
+    }
+
+    #[test]
+    fn test_get_reset_context() {
+        let mut irs = Irs::default();
+
+        let input_data = vec![1, 2, 3, 4];
         // we don't want any context if there was no data yet.
         assert_eq!(irs.context, None);
         let context = irs.get_reset_context(&input_data);
-        assert_eq!(context.data_cp.len(), input_data.len());
-        assert!(context.bit_slots.iter().all(|s| *s == 0));
+        assert_eq!(context.data_cp, input_data);
+
+        let mut slots = Slots::default();
+        slots.reset(4);
+        slots.slots[2].key = 7;
+        slots.reset(3);
+        assert_eq!(slots.slots_cp.len(), 3);
+        assert!(slots
+            .slots
+            .iter()
+            .enumerate()
+            .all(|(i, s)| s.key == 0 && s.index == i));
+    }
+
+    #[test]
+    fn test_shrinking_then_growing_matches_fresh() {
+        let mut irs = Irs::default();
+        for (seed, len) in [(40, 10), (41, 3), (42, 10), (43, 1), (44, 0), (45, 10)] {
+            let input_data: Vec<u32> = (0..len).collect();
+
+            let mut target = input_data.clone();
+            irs.shuffle(&mut target, &mut StdRng::seed_from_u64(seed))
+                .unwrap();
+            let mut expected = input_data.clone();
+            Irs::default()
+                .shuffle(&mut expected, &mut StdRng::seed_from_u64(seed))
+                .unwrap();
+            assert_eq!(target, expected);
+
+            let expected = Irs::<()>::default()
+                .shuffle_indices(len as usize, &mut StdRng::seed_from_u64(seed))
+                .unwrap();
+            let reused = irs
+                .shuffle_indices(len as usize, &mut StdRng::seed_from_u64(seed))
+                .unwrap();
+            assert_eq!(reused, expected);
+
+            target.sort_unstable();
+            assert_eq!(target, input_data);
+        }
+    }
+
+    #[test]
+    fn test_fallback() {
+        let input_data: Vec<u32> = (0..100).collect();
+        let mut rng = PairedBits::new();
+        let mut irs = Irs::default().with_max_rounds(8);
+        let mut target = input_data.clone();
+        assert_eq!(
+            irs.shuffle(&mut target, &mut rng),
+            Err(ShuffleError::BadRandomness)
+        );
+        assert!(!irs.last_run_fell_back());
+
+        let mut irs = Irs::default().with_max_rounds(8).with_fallback();
+        irs.shuffle(&mut target, &mut rng).unwrap();
+        assert!(irs.last_run_fell_back());
+        assert_eq!(irs.last_round_count(), Some(8));
+        let permutation = irs.last_permutation().unwrap().to_vec();
+        assert!(crate::permutation::is_permutation(&permutation));
+        let gathered: Vec<u32> = permutation.iter().map(|&i| input_data[i]).collect();
+        assert_eq!(target, gathered);
+
+        let indices = irs.shuffle_indices(100, &mut rng).unwrap();
+        assert!(crate::permutation::is_permutation(&indices));
+        let mut target = input_data.clone();
+        irs.shuffle_low_memory(&mut target, 10, &mut rng).unwrap();
+        assert!(irs.last_run_fell_back());
+        target.sort_unstable();
+        assert_eq!(target, input_data);
+
+        let mut rng = StdRng::seed_from_u64(39);
+        let mut irs = Irs::default().with_fallback();
+        irs.shuffle(&mut target, &mut rng).unwrap();
+        assert!(!irs.last_run_fell_back());
+        assert!(irs.shuffle_with_bit_fn(&mut target, || false).is_err());
+    }
+
+    #[test]
+    fn test_shuffle_copy() {
+        let input_data: Vec<u64> = (0..2000).collect();
+        let mut irs = Irs::default();
+        for seed in 0..5 {
+            let mut expected = input_data.clone();
+            irs.shuffle(&mut expected, &mut StdRng::seed_from_u64(seed))
+                .unwrap();
+            let mut target = input_data.clone();
+            irs.shuffle_copy(&mut target, &mut StdRng::seed_from_u64(seed))
+                .unwrap();
+            assert_eq!(target, expected);
+        }
+    }
+
+    #[test]
+    fn test_alternating_lengths_reuse_the_buffers() {
+        let mut irs = Irs::default();
+        let mut first: Vec<String> = (0..1000).map(|i| i.to_string()).collect();
+        irs.shuffle(&mut first, &mut StdRng::seed_from_u64(33))
+            .unwrap();
+        let data_cp = irs.context.as_ref().unwrap().data_cp.as_ptr();
+        // The rounds swap the slots with the scratch buffer.
+        let slots = [irs.slots.slots.as_ptr(), irs.slots.slots_cp.as_ptr()];
+        for (seed, len) in [(34, 500), (35, 1000), (36, 7), (37, 999), (38, 500)] {
+            let input_data: Vec<String> = (0..len).map(|i| i.to_string()).collect();
+            let mut target = input_data.clone();
+            irs.shuffle(&mut target, &mut StdRng::seed_from_u64(seed))
+                .unwrap();
+            let mut expected = input_data.clone();
+            Irs::default()
+                .shuffle(&mut expected, &mut StdRng::seed_from_u64(seed))
+                .unwrap();
+            assert_eq!(target, expected);
+            assert_eq!(irs.context.as_ref().unwrap().data_cp.len(), len);
+            assert_eq!(irs.context.as_ref().unwrap().data_cp.as_ptr(), data_cp);
+            assert!(slots.contains(&irs.slots.slots.as_ptr()));
+            assert!(slots.contains(&irs.slots.slots_cp.as_ptr()));
+        }
+    }
+
+    #[test]
+    fn test_reused_context_matches_fresh() {
+        let mut rng = StdRng::seed_from_u64(31);
+        let mut bytes = vec![0; 4096];
+        rng.fill_bytes(&mut bytes);
+        let input_data: Vec<u32> = (0..200).collect();
+
+        let mut expected = input_data.clone();
+        Irs::default()
+            .shuffle_from_bytes(&mut expected, &bytes)
+            .unwrap();
+
+        // Leave the context dirty in several ways before reusing it.
+        let mut irs = Irs::default();
+        let mut other: Vec<u32> = (1000..1200).collect();
+        irs.shuffle(&mut other, &mut rng).unwrap();
+        irs.shuffle_fixed_rounds(&mut other, 3, &mut rng).unwrap();
+        let mut target = input_data.clone();
+        irs.shuffle_from_bytes(&mut target, &bytes).unwrap();
+        assert_eq!(target, expected);
+
+        assert!(irs.shuffle_with_bit_fn(&mut other, || true).is_err());
+        let mut target = input_data.clone();
+        irs.shuffle_from_bytes(&mut target, &bytes).unwrap();
+        assert_eq!(target, expected);
+
+        let mut target = input_data.clone();
+        irs.shuffle_from_bytes(&mut target, &bytes).unwrap();
+        assert_eq!(target, expected);
+    }
+
+    #[test]
+    fn test_bit_buffer_is_reused() {
+        let mut rng = StdRng::seed_from_u64(32);
+        let mut irs = Irs::default();
+        let mut target: Vec<u32> = (0..100).collect();
+        irs.shuffle(&mut target, &mut rng).unwrap();
+        let address = irs.bit_buffer.as_ptr();
+        for _ in 0..10 {
+            irs.shuffle(&mut target, &mut rng).unwrap();
+            irs.shuffle_indices(50, &mut rng).unwrap();
+            assert_eq!(irs.bit_buffer.as_ptr(), address);
+            assert_eq!(irs.bit_buffer.len(), BIT_BUFFER_CHUNK);
+        }
+
+        // A round of a large shuffle fits in the buffer.
+        let mut target: Vec<u32> = (0..10_000).collect();
+        irs.shuffle(&mut target, &mut rng).unwrap();
+        assert_eq!(irs.bit_buffer.len(), 1280);
+        assert!(irs.bit_buffer.len() * 8 >= 10_000);
+    }
+
+    #[test]
+    fn test_bit_buffer_len() {
+        assert_eq!(bit_buffer_len(0), 32);
+        assert_eq!(bit_buffer_len(256), 32);
+        assert_eq!(bit_buffer_len(257), 64);
+        assert_eq!(bit_buffer_len(10_000), 1280);
+    }
+
+    #[test]
+    fn test_buffers_follow_the_length() {
+        // Runs with debug assertions on, which check the buffer lengths
+        // every round.
+        let mut rng = StdRng::seed_from_u64(31);
+        let mut irs = Irs::default();
+        for len in [10u32, 0, 300, 1, 299, 2, 1000, 64] {
+            let input_data: Vec<u32> = (0..len).collect();
+            let mut target = input_data.clone();
+            irs.shuffle(&mut target, &mut rng).unwrap();
+            let len = len as usize;
+            assert_eq!(irs.slots.slots.len(), len);
+            assert_eq!(irs.slots.slots_cp.len(), len);
+            assert_eq!(irs.context.as_ref().unwrap().data_cp.len(), len);
+
+            irs.shuffle_low_memory(&mut target, 16, &mut rng).unwrap();
+            assert_eq!(irs.shuffle_indices(len, &mut rng).unwrap().len(), len);
+            assert_eq!(irs.slots.slots_cp.len(), len);
+            target.sort_unstable();
+            assert_eq!(target, input_data);
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "slot buffers out of sync")]
+    fn test_one_round_rejects_mismatched_buffers() {
+        let irs = Irs::<()>::default();
+        let mut slots = Slots::default();
+        slots.reset(8);
+        slots.slots_cp.truncate(4);
+        irs.one_round(&mut slots.slots, &mut slots.slots_cp, &mut || true);
+    }
+
+    /// The original implementation of the algorithm, which moved the data
+    /// together with the keys in every round. Kept to make sure the
+    /// index-based implementation produces exactly the same output.
+    fn lockstep_shuffle<T, R>(data: &mut Vec<T>, rng: &mut R) -> Result<(), ShuffleError>
+    where
+        T: Clone,
+        R: RngCore + ?Sized,
+    {
+        let mut data_cp = data.clone();
+        let mut bit_slots = vec![0u64; data.len()];
+        let mut bit_slots_cp = vec![0u64; data.len()];
+        let mut initial_buffer = vec![0; 32];
+        rng.fill_bytes(&mut initial_buffer);
+        let mut rand_bit_iter =
+            InfiniteBitIter::<Lsb0, R>::new(initial_buffer, ByteOrder::LittleEndian, rng);
+
+        let size = data.len();
+        for _ in 0..128 {
+            let mut odd_count = 0;
+            for slot in bit_slots.iter_mut() {
+                *slot = (*slot << 1) | (rand_bit_iter.next_bit() as u64);
+                odd_count += (*slot & 1) as usize;
+            }
+            let mut odd_moved = 0;
+            let mut even_moved = 0;
+            for i in (0..size).rev() {
+                let current = bit_slots[i];
+                let position = if current % 2 == 1 {
+                    odd_moved += 1;
+                    size - odd_moved
+                } else {
+                    even_moved += 1;
+                    size - odd_count - even_moved
+                };
+                bit_slots_cp[position] = current;
+                data_cp[position] = data[i].clone();
+            }
+            std::mem::swap(&mut data_cp, data);
+            std::mem::swap(&mut bit_slots_cp, &mut bit_slots);
+            if all_distinct(&bit_slots) {
+                return Ok(());
+            }
+        }
+        Err(ShuffleError::BadRandomness)
+    }
+
+    #[test]
+    fn test_shuffle_owned() {
+        let input_data: Vec<String> = (0..30).map(|i| i.to_string()).collect();
+        let mut irs = Irs::default();
+
+        let mut rng = StdRng::seed_from_u64(35);
+        let mut target = irs.shuffle_owned(input_data.clone(), &mut rng).unwrap();
+        assert_ne!(target, input_data);
+        target.sort();
+        let mut sorted = input_data.clone();
+        sorted.sort();
+        assert_eq!(target, sorted);
+
+        let mut rng = rand::rngs::mock::StepRng::new(0, 0);
+        let (error, target) = irs.shuffle_owned(input_data.clone(), &mut rng).unwrap_err();
+        assert!(matches!(
+            error,
+            ShuffleError::BadRandomness | ShuffleError::DegenerateRandomness
+        ));
+        assert_eq!(target, input_data);
+    }
+
+    #[test]
+    fn test_max_rounds() {
+        let mut rng = PairedBits::new();
+        let mut target = vec![1, 2, 3, 4, 5, 6];
+        let mut irs = Irs::default();
+        assert!(irs.shuffle(&mut target, &mut rng).is_err());
+        assert_eq!(irs.last_round_count(), Some(DEFAULT_MAX_ROUNDS));
+
+        let mut irs = Irs::default().with_max_rounds(8);
+        assert_eq!(
+            irs.shuffle(&mut target, &mut rng),
+            Err(ShuffleError::BadRandomness)
+        );
+        assert_eq!(irs.last_round_count(), Some(8));
+        assert!(irs.shuffle_low_memory(&mut target, 2, &mut rng).is_err());
+        target.sort_unstable();
+        assert_eq!(target, vec![1, 2, 3, 4, 5, 6]);
+
+        let mut rng = StdRng::seed_from_u64(38);
+        let mut irs = Irs::default().with_max_rounds(0);
+        assert!(irs.shuffle(&mut target, &mut rng).is_err());
+        assert_eq!(irs.last_round_count(), Some(0));
+
+        let mut irs = Irs::default().with_max_rounds(1000);
+        let mut target: Vec<u32> = (0..1000).collect();
+        irs.shuffle(&mut target, &mut rng).unwrap();
+        let rounds = irs.last_round_count().unwrap();
+        assert!((10..40).contains(&rounds), "rounds: {}", rounds);
+    }
+
+    #[test]
+    fn test_last_bits_consumed() {
+        let mut rng = StdRng::seed_from_u64(39);
+        let mut irs = Irs::default();
+        for len in [0, 1, 5, 300, 10_000] {
+            let mut target: Vec<u32> = (0..len).collect();
+            let bytes = irs.shuffle_recording(&mut target, &mut rng).unwrap();
+            assert_eq!(irs.last_bits_consumed(), Some(bytes.len() as u64 * 8));
+
+            let mut recording_rng = RecordingRng {
+                inner: &mut rng,
+                recorded: Vec::new(),
+            };
+            irs.shuffle_low_memory(&mut target, 64, &mut recording_rng)
+                .unwrap();
+            let drawn = recording_rng.recorded.len() as u64 * 8;
+            assert_eq!(irs.last_bits_consumed(), Some(drawn));
+        }
+
+        let mut target = vec![1, 2, 3];
+        irs.shuffle_with_bit_fn(&mut target, || rng.gen()).unwrap();
+        let rounds = irs.last_round_count().unwrap() as u64;
+        assert_eq!(irs.last_bits_consumed(), Some(rounds * 3));
+
+        let mut target = vec![1, 2, 3];
+        assert!(irs
+            .shuffle_cancellable(&mut target, &mut rng, || true)
+            .is_err());
+        assert_eq!(irs.last_bits_consumed(), None);
+    }
+
+    #[test]
+    fn test_shuffle_low_memory() {
+        let input_data: Vec<u32> = (0..20_000).collect();
+        for max_scratch in [0, 1, 64, 5000] {
+            let mut irs = Irs::default();
+            let mut rng = StdRng::seed_from_u64(38);
+            let mut target = input_data.clone();
+            irs.shuffle_low_memory(&mut target, max_scratch, &mut rng)
+                .unwrap();
+            assert_ne!(target, input_data);
+            // A `Vec` allocates room for at least four slots.
+            assert!(irs.slots.slots.capacity() <= max_scratch.max(4) * 2);
+            target.sort_unstable();
+            assert_eq!(target, input_data);
+        }
+
+        let mut irs = Irs::default();
+        let mut expected = input_data.clone();
+        irs.shuffle(&mut expected, &mut StdRng::seed_from_u64(38))
+            .unwrap();
+        let mut target = input_data.clone();
+        Irs::default()
+            .shuffle_low_memory(&mut target, 20_000, &mut StdRng::seed_from_u64(38))
+            .unwrap();
+        assert_eq!(target, expected);
+
+        let mut rng = rand::rngs::mock::StepRng::new(0, 0);
+        assert!(irs.shuffle_low_memory(&mut target, 10, &mut rng).is_err());
+    }
+
+    #[test]
+    fn test_shuffle_low_memory_is_uniform() {
+        let mut rng = StdRng::seed_from_u64(38);
+        let mut irs = Irs::default();
+        let trials = 24_000;
+        let mut counts = std::collections::HashMap::new();
+        for _ in 0..trials {
+            let mut target = vec![0, 1, 2, 3];
+            irs.shuffle_low_memory(&mut target, 1, &mut rng).unwrap();
+            *counts.entry(target).or_insert(0) += 1;
+        }
+        assert_eq!(counts.len(), 24);
+        let counts: Vec<usize> = counts.into_values().collect();
+        assert!(counts_chi_square(&counts) < chi_square_critical_value(23, 0.999));
+    }
+
+    #[test]
+    fn test_skipped_scans_never_find_distinct_keys() {
+        let mut rng = StdRng::seed_from_u64(26);
+        let irs = Irs::<()>::default();
+        for len in [0, 1, 2, 3, 4, 5, 1000, 1024, 1025] {
+            let mut slots = Slots::default();
+            slots.reset(len);
+            for rounds in 1..=70 {
+                irs.one_round(&mut slots.slots, &mut slots.slots_cp, &mut || rng.gen());
+                let distinct = all_distinct(slots.slots.iter().map(|s| s.key));
+                assert!(can_be_distinct(rounds, len) || !distinct);
+            }
+            assert!(can_be_distinct(70, len));
+        }
+        assert!(!can_be_distinct(9, 1000));
+        assert!(can_be_distinct(10, 1000));
+        assert!(can_be_distinct(10, 1024));
+        assert!(!can_be_distinct(10, 1025));
+    }
+
+    #[test]
+    fn test_shuffle_cancellable() {
+        let mut rng = StdRng::seed_from_u64(29);
+        let mut irs = Irs::default();
+        let input_data: Vec<usize> = (0..1000).collect();
+
+        let mut target = input_data.clone();
+        irs.shuffle(&mut target, &mut rng).unwrap();
+        let checks = std::cell::Cell::new(0);
+        let mut target = input_data.clone();
+        let result = irs.shuffle_cancellable(&mut target, &mut rng, || {
+            checks.set(checks.get() + 1);
+            checks.get() > 1
+        });
+        assert_eq!(result, Err(ShuffleError::Cancelled));
+        assert_eq!(checks.get(), 2);
+        assert_eq!(target, input_data);
+        assert_eq!(irs.last_run_mixed_well(), None);
+        assert_eq!(irs.last_permutation(), None);
+
+        let mut rng = StdRng::seed_from_u64(29);
+        let mut expected = input_data.clone();
+        Irs::default().shuffle(&mut expected, &mut rng).unwrap();
+        let mut rng = StdRng::seed_from_u64(29);
+        irs.shuffle_cancellable(&mut target, &mut rng, || false)
+            .unwrap();
+        assert_eq!(target, expected);
+    }
+
+    #[test]
+    fn test_compact_keys() {
+        let mut slots: Vec<Slot> = [7, 7, 3, 9, 9, 9, 1 << 63]
+            .iter()
+            .enumerate()
+            .map(|(index, &key)| Slot { key, index })
+            .collect();
+        assert_eq!(compact_keys(&mut slots), 2);
+        let keys: Vec<u64> = slots.iter().map(|s| s.key).collect();
+        assert_eq!(keys, vec![0, 0, 1, 2, 2, 2, 3]);
+        assert_eq!(compact_keys(&mut []), 0);
+    }
+
+    #[test]
+    fn test_runs_longer_than_the_keys() {
+        let mut rng = StdRng::seed_from_u64(28);
+        let len = 10_000;
+        // 20 random rounds set most of the elements apart, 80 rounds of
+        // zeros would push those bits out of 64-bit keys, and the random
+        // rounds after that only have to separate the few remaining pairs.
+        let mut calls = 0;
+        let mut bit_fn = || {
+            calls += 1;
+            let round = (calls - 1) / len;
+            !(20..100).contains(&round) && rng.gen()
+        };
+        let mut irs = Irs::default();
+        let mut target: Vec<usize> = (0..len).collect();
+        irs.shuffle_with_bit_fn(&mut target, &mut bit_fn).unwrap();
+
+        let run = irs.last_run.unwrap();
+        assert!(run.distinct);
+        assert!((101..=112).contains(&run.rounds), "rounds: {}", run.rounds);
+        target.sort_unstable();
+        assert_eq!(target, (0..len).collect::<Vec<_>>());
+
+        // Fixed runs compact their keys as well.
+        let mut rng = StdRng::seed_from_u64(28);
+        let mut target: Vec<usize> = (0..len).collect();
+        irs.shuffle_fixed_rounds(&mut target, 200, &mut rng)
+            .unwrap();
+        assert!(irs.last_run.unwrap().distinct);
+        target.sort_unstable();
+        assert_eq!(target, (0..len).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_same_output_as_lockstep_implementation() {
+        let mut irs = Irs::default();
+        for (seed, len) in [(0, 2), (1, 5), (2, 52), (3, 100), (4, 1000), (5, 5000)] {
+            let input_data: Vec<usize> = (0..len).collect();
+
+            let mut expected = input_data.clone();
+            let mut rng = StdRng::seed_from_u64(seed);
+            lockstep_shuffle(&mut expected, &mut rng).unwrap();
+
+            let mut target = input_data.clone();
+            let mut rng = StdRng::seed_from_u64(seed);
+            irs.shuffle(&mut target, &mut rng).unwrap();
+
+            assert_eq!(target, expected);
+        }
+    }
+
+    #[test]
+    fn test_shuffle_indices_same_as_shuffle() {
+        for (seed, len) in [(0, 0), (1, 1), (2, 7), (3, 52), (4, 2000)] {
+            let mut expected: Vec<usize> = (0..len).collect();
+            let mut rng = StdRng::seed_from_u64(seed);
+            Irs::default().shuffle(&mut expected, &mut rng).unwrap();
+
+            let mut rng = StdRng::seed_from_u64(seed);
+            let permutation = Irs::<()>::default().shuffle_indices(len, &mut rng).unwrap();
+            assert_eq!(permutation, expected);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "global-cache")]
+    fn test_shuffle_cached_same_as_shuffle() {
+        struct NoClone(usize);
+
+        for (seed, len) in [(0, 0), (1, 1), (2, 7), (3, 52), (4, 2000)] {
+            let mut expected: Vec<usize> = (0..len).collect();
+            let mut rng = StdRng::seed_from_u64(seed);
+            Irs::default().shuffle(&mut expected, &mut rng).unwrap();
+
+            let mut target: Vec<NoClone> = (0..len).map(NoClone).collect();
+            let mut rng = StdRng::seed_from_u64(seed);
+            shuffle_cached(&mut target, &mut rng).unwrap();
+            let target: Vec<usize> = target.into_iter().map(|x| x.0).collect();
+            assert_eq!(target, expected);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "global-cache")]
+    fn test_shuffle_cached_reuses_buffers() {
+        // The rounds swap the two buffers, so their order doesn't matter.
+        let buffers = || {
+            CACHED_IRS.with(|cache| {
+                let slots = &cache.borrow().slots;
+                let mut pointers = [slots.slots.as_ptr(), slots.slots_cp.as_ptr()];
+                pointers.sort();
+                pointers
+            })
+        };
+        let mut rng = StdRng::seed_from_u64(25);
+        let mut target: Vec<u32> = (0..500).collect();
+        shuffle_cached(&mut target, &mut rng).unwrap();
+        let first = buffers();
+        for _ in 0..10 {
+            shuffle_cached(&mut target, &mut rng).unwrap();
+            assert_eq!(buffers(), first);
+        }
+        target.sort_unstable();
+        assert_eq!(target, (0..500).collect::<Vec<_>>());
+    }
+
+    /// Extracts bit `index` of `bytes` in the `Lsb0` or `Msb0` order.
+    fn nth_bit(bytes: &[u8], index: usize, msb_first: bool) -> bool {
+        let offset = if msb_first { 7 - index % 8 } else { index % 8 };
+        (bytes[index / 8] >> offset) & 1 == 1
+    }
+
+    #[test]
+    fn test_bit_iter_follows_the_byte_stream() {
+        // The iterator used to compare its bit index against the length
+        // of the buffer in bytes times 8 again, reading far past the end
+        // of the 32-byte buffer instead of refilling it after 256 bits.
+        let mut rng = StdRng::seed_from_u64(201);
+        let mut bytes = vec![0; 32 * 5];
+        rng.fill_bytes(&mut bytes);
+
+        let mut replay_rng = ReplayRng {
+            bytes: &bytes[32..],
+            exhausted: false,
+        };
+        let mut iter = InfiniteBitIter::<Lsb0, _>::new(
+            bytes[..32].to_vec(),
+            ByteOrder::LittleEndian,
+            &mut replay_rng,
+        );
+        for index in 0..bytes.len() * 8 {
+            assert_eq!(iter.next_bit(), nth_bit(&bytes, index, false));
+        }
+        assert!(!replay_rng.exhausted);
+
+        let mut replay_rng = ReplayRng {
+            bytes: &bytes[32..],
+            exhausted: false,
+        };
+        let mut iter = InfiniteBitIter::<Msb0, _>::new(
+            bytes[..32].to_vec(),
+            ByteOrder::LittleEndian,
+            &mut replay_rng,
+        );
+        for index in 0..bytes.len() * 8 {
+            assert_eq!(iter.next_bit(), nth_bit(&bytes, index, true));
+        }
+    }
+
+    #[test]
+    fn test_shuffle_with_bit_fn() {
+        let bits = [
+            true, false, false, false, // 2, 3, 4, 1
+            true, false, true, false, // 3, 1, 2, 4, keys of 2 and 4 equal
+            false, false, false, true, // 3, 1, 2, 4, all keys distinct
+        ];
+        let mut bits = bits.iter();
+        let mut irs = Irs::default();
+        let mut target = vec![1, 2, 3, 4];
+        irs.shuffle_with_bit_fn(&mut target, || *bits.next().unwrap())
+            .unwrap();
+        assert_eq!(target, vec![3, 1, 2, 4]);
+        assert!(bits.next().is_none());
+
+        assert_eq!(
+            irs.shuffle_with_bit_fn(&mut target, || false),
+            Err(ShuffleError::BadRandomness)
+        );
+        assert_eq!(target, vec![3, 1, 2, 4]);
+    }
+
+    #[test]
+    fn test_last_run_mixed_well() {
+        let mut rng = StdRng::seed_from_u64(26);
+        let mut irs = Irs::default();
+        assert_eq!(irs.last_run_mixed_well(), None);
+
+        let mut target: Vec<usize> = (0..1000).collect();
+        irs.shuffle(&mut target, &mut rng).unwrap();
+        assert_eq!(irs.last_run_mixed_well(), Some(true));
+
+        irs.shuffle_fixed_rounds(&mut target, 1, &mut rng).unwrap();
+        assert_eq!(irs.last_run_mixed_well(), Some(false));
+
+        let enough = expected_rounds(target.len()) + MIXING_MARGIN;
+        irs.shuffle_fixed_rounds(&mut target, enough, &mut rng)
+            .unwrap();
+        assert_eq!(irs.last_run_mixed_well(), Some(true));
+
+        target.sort_unstable();
+        assert_eq!(target, (0..1000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_last_permutation() {
+        let mut rng = StdRng::seed_from_u64(27);
+        let mut irs = Irs::default();
+        assert_eq!(irs.last_permutation(), None);
+
+        let input_data: Vec<String> = (0..100).map(|i| i.to_string()).collect();
+        for len in [100, 10, 100] {
+            let mut target = input_data[..len].to_vec();
+            irs.shuffle(&mut target, &mut rng).unwrap();
+            let permutation = irs.last_permutation().unwrap();
+            let applied: Vec<String> = permutation.iter().map(|&i| input_data[i].clone()).collect();
+            assert_eq!(applied, target);
+        }
+
+        let mut target = input_data.clone();
+        irs.shuffle_low_memory(&mut target, 10, &mut rng).unwrap();
+        assert_eq!(irs.last_permutation(), None);
+
+        let mut target = input_data.clone();
+        irs.shuffle_fixed_rounds(&mut target, 20, &mut rng).unwrap();
+        assert!(irs.last_permutation().is_some());
+        assert!(irs.shuffle_with_bit_fn(&mut target, || false).is_err());
+        assert_eq!(irs.last_permutation(), None);
+    }
+
+    #[test]
+    fn test_bit_indexing() {
+        fn take_all<O: BitIndexing>(bytes: [u8; 8]) -> Vec<bool> {
+            let mut word = O::load(bytes);
+            (0..64).map(|_| O::take(&mut word)).collect()
+        }
+
+        let bytes = [0b0000_0010, 0, 0, 0, 0, 0, 0, 0b1000_0000];
+        let lsb0 = take_all::<Lsb0>(bytes);
+        assert_eq!(lsb0.iter().filter(|&&bit| bit).count(), 2);
+        assert!(lsb0[1] && lsb0[63]);
+
+        let bytes = [0b0100_0000, 0, 0, 0, 0, 0, 0, 0b0000_0001];
+        let msb0 = take_all::<Msb0>(bytes);
+        assert_eq!(msb0.iter().filter(|&&bit| bit).count(), 2);
+        assert!(msb0[1] && msb0[63]);
+    }
+
+    #[test]
+    fn test_bit_orders() {
+        let input_data: Vec<u32> = (0..30).collect();
+
+        let mut lsb = input_data.clone();
+        Irs::default()
+            .with_bit_order(BitOrder::Lsb0)
+            .shuffle(&mut lsb, &mut StdRng::seed_from_u64(17))
+            .unwrap();
+        let mut msb = input_data.clone();
+        Irs::default()
+            .with_bit_order(BitOrder::Msb0)
+            .shuffle(&mut msb, &mut StdRng::seed_from_u64(17))
+            .unwrap();
+
+        assert_ne!(lsb, msb);
+        for mut target in [lsb, msb] {
+            target.sort_unstable();
+            assert_eq!(target, input_data);
+        }
+    }
+
+    /// A straightforward IRS that reads the random 32-bit words MSB first,
+    /// the way implementations outside of `rand` usually do.
+    fn reference_permutation(len: usize, words: &[u32]) -> Vec<usize> {
+        let mut bits = words
+            .iter()
+            .flat_map(|&word| (0..32).rev().map(move |bit| (word >> bit) & 1 == 1));
+        let mut keyed: Vec<(u64, usize)> = (0..len).map(|index| (0, index)).collect();
+        loop {
+            for (key, _) in keyed.iter_mut() {
+                *key = (*key << 1) | (bits.next().unwrap() as u64);
+            }
+            keyed.sort_by_key(|&(key, _)| key & 1);
+            let keys: HashSet<u64> = keyed.iter().map(|&(key, _)| key).collect();
+            if keys.len() == len {
+                return keyed.iter().map(|&(_, index)| index).collect();
+            }
+        }
+    }
+
+    #[test]
+    fn test_big_endian_msb_first_test_vector() {
+        // The first hexadecimal digits of pi.
+        let words: [u32; 16] = [
+            0x243f_6a88,
+            0x85a3_08d3,
+            0x1319_8a2e,
+            0x0370_7344,
+            0xa409_3822,
+            0x299f_31d0,
+            0x082e_fa98,
+            0xec4e_6c89,
+            0x4528_21e6,
+            0x38d0_1377,
+            0xbe54_66cf,
+            0x34e9_0c6c,
+            0xc0ac_29b7,
+            0xc97c_50dd,
+            0x3f84_d5b5,
+            0xb547_0917,
+        ];
+        // The words as `rand` hands them out from `fill_bytes`.
+        let bytes: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+        let expected = vec![5, 6, 0, 4, 7, 3, 2, 1];
+        assert_eq!(reference_permutation(8, &words), expected);
+
+        let mut irs = IrsBuilder::new()
+            .bit_consumption(BitConsumption {
+                byte_order: ByteOrder::BigEndian,
+                bit_order: BitOrder::Msb0,
+            })
+            .build();
+        let mut target: Vec<usize> = (0..8).collect();
+        irs.shuffle_from_bytes(&mut target, &bytes).unwrap();
+        assert_eq!(target, expected);
+
+        let mut default: Vec<usize> = (0..8).collect();
+        Irs::default()
+            .shuffle_from_bytes(&mut default, &bytes)
+            .unwrap();
+        assert_ne!(default, expected);
+    }
+
+    #[test]
+    fn test_golden_vectors() {
+        let bytes: Vec<u8> = (0u32..4096)
+            .map(|i| (i.wrapping_mul(0x9e37_79b9) >> 24) as u8)
+            .collect();
+        let mut irs = Irs::default();
+        let mut target: Vec<u32> = (0..5).collect();
+        irs.shuffle_from_bytes(&mut target, &bytes).unwrap();
+        assert_eq!(target, vec![1, 0, 3, 2, 4]);
+
+        let mut target: Vec<u32> = (0..16).collect();
+        irs.shuffle_from_bytes(&mut target, &bytes).unwrap();
+        assert_eq!(
+            target,
+            vec![14, 0, 7, 8, 1, 10, 6, 5, 2, 15, 4, 12, 11, 9, 3, 13]
+        );
+
+        let mut irs = IrsBuilder::new()
+            .bit_consumption(BitConsumption {
+                byte_order: ByteOrder::BigEndian,
+                bit_order: BitOrder::Msb0,
+            })
+            .build();
+        let mut target: Vec<usize> = (0..16).collect();
+        irs.shuffle_from_bytes(&mut target, &bytes).unwrap();
+        assert_eq!(
+            target,
+            vec![12, 15, 14, 6, 3, 7, 5, 0, 9, 10, 4, 8, 13, 1, 2, 11]
+        );
+        let words: Vec<u32> = bytes
+            .chunks_exact(4)
+            .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+            .collect();
+        assert_eq!(reference_permutation(16, &words), target);
+    }
+
+    #[test]
+    fn test_bit_consumptions() {
+        let bytes: Vec<u8> = (0..=255).cycle().take(4096).collect();
+        let mut results = HashSet::new();
+        for &byte_order in &[ByteOrder::LittleEndian, ByteOrder::BigEndian] {
+            for &bit_order in &[BitOrder::Lsb0, BitOrder::Msb0] {
+                let consumption = BitConsumption {
+                    byte_order,
+                    bit_order,
+                };
+                let mut irs = IrsBuilder::new().bit_consumption(consumption).build();
+                let mut target: Vec<u32> = (0..30).collect();
+                irs.shuffle_from_bytes(&mut target, &bytes).unwrap();
+                results.insert(target);
+            }
+        }
+        assert_eq!(results.len(), 4);
+
+        let mut lsb: Vec<u32> = (0..30).collect();
+        Irs::default().shuffle_from_bytes(&mut lsb, &bytes).unwrap();
+        let mut msb: Vec<u32> = (0..30).collect();
+        Irs::default()
+            .with_bit_order(BitOrder::Msb0)
+            .shuffle_from_bytes(&mut msb, &bytes)
+            .unwrap();
+        assert!(results.contains(&lsb) && results.contains(&msb));
+    }
+
+    #[test]
+    fn test_record_then_replay() {
+        let mut irs = Irs::default();
+        let mut rng = StdRng::seed_from_u64(7);
+        let input_data: Vec<usize> = (0..300).collect();
+
+        let mut recorded = input_data.clone();
+        let bytes = irs.shuffle_recording(&mut recorded, &mut rng).unwrap();
+        assert_eq!(bytes.len() % 32, 0);
+
+        let mut replayed = input_data.clone();
+        Irs::default()
+            .shuffle_from_bytes(&mut replayed, &bytes)
+            .unwrap();
+        assert_eq!(replayed, recorded);
+    }
+
+    #[test]
+    fn test_replay_ignores_unused_bytes() {
+        let mut rng = StdRng::seed_from_u64(8);
+        let mut irs = Irs::default();
+        let input_data: Vec<usize> = (0..1000).collect();
+        let mut expected = input_data.clone();
+        let bytes = irs.shuffle_recording(&mut expected, &mut rng).unwrap();
+        let used = irs.last_bits_used.div_ceil(32) as usize * 4;
+        assert!(used < bytes.len());
+
+        let mut target = input_data.clone();
+        irs.shuffle_from_bytes(&mut target, &bytes[..used]).unwrap();
+        assert_eq!(target, expected);
+
+        let mut target = input_data.clone();
+        assert_eq!(
+            irs.shuffle_from_bytes(&mut target, &bytes[..used - 4]),
+            Err(ShuffleError::NotEnoughRandomBytes)
+        );
+        assert_eq!(target, input_data);
+    }
+
+    #[test]
+    fn test_replay_with_too_few_bytes() {
+        let mut irs = Irs::default();
+        let mut bytes = [0; 40];
+        StdRng::seed_from_u64(7).fill_bytes(&mut bytes);
+        let input_data: Vec<usize> = (0..300).collect();
+        let mut target = input_data.clone();
+        assert_eq!(
+            irs.shuffle_from_bytes(&mut target, &bytes),
+            Err(ShuffleError::NotEnoughRandomBytes)
+        );
+        assert_eq!(target, input_data);
+    }
+
+    #[test]
+    fn test_expected_rounds() {
+        for len in (2..10_000).chain([1 << 20, 1 << 21, 1_000_003]) {
+            let rounds = (1.5 * (len as f64).log2()).ceil() as usize;
+            assert_eq!(expected_rounds(len), rounds, "len {}", len);
+        }
+        assert_eq!(expected_rounds(usize::MAX), 96);
     }
 
     #[test]
     fn test_all_distinct() {
-        assert!(all_distinct(&[1, 2, 3, 4]));
-        assert!(!all_distinct(&[1, 1, 2, 3]));
+        assert!(all_distinct([1, 2, 3, 4]));
+        assert!(!all_distinct([1, 1, 2, 3]));
     }
 }
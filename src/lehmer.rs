@@ -0,0 +1,247 @@
+//! Permutations of up to 20 elements numbered by their Lehmer code.
+//!
+//! The `n!` permutations of `n` elements are numbered `0..n!` in
+//! lexicographic order, through the factorial number system: the digits
+//! of the index are the Lehmer code of the permutation, i.e. how many of
+//! the elements not placed yet are smaller than the one at each
+//! position. For `n <= 20` the index fits in a `u64`, so a permutation
+//! can be stored as a single number, and [`LehmerShuffler`] shuffles by
+//! drawing one uniform index.
+
+use rand::{Rng, RngCore};
+
+use crate::permutation::apply_permutation;
+use crate::shuffler::{ShuffleError, Shuffler};
+
+/// The largest number of elements supported, as `21!` overflows a `u64`.
+pub const MAX_LEN: usize = 20;
+
+/// A shuffler that draws a single uniform integer in `0..n!` and decodes
+/// it into the permutation with that index.
+///
+/// Every permutation has the same probability and the generator is
+/// called for one number only, bounded with [`Rng::gen_range`]. The index
+/// of the last shuffle is kept, so it can be stored and turned back into
+/// the permutation later with [`permutation_from_index`].
+///
+/// Shuffles of more than [`MAX_LEN`] elements fail with
+/// [`ShuffleError::InvalidArgument`], leaving the data unchanged.
+///
+/// # Examples
+/// ```
+/// use shuffle::lehmer::{permutation_from_index, LehmerShuffler};
+/// use shuffle::shuffler::Shuffler;
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let mut rng = StdRng::seed_from_u64(42);
+/// let mut shuffler = LehmerShuffler::default();
+///
+/// let mut input = vec![0, 1, 2, 3, 4];
+/// shuffler.shuffle(&mut input, &mut rng).unwrap();
+/// let index = shuffler.last_index().unwrap();
+/// assert_eq!(permutation_from_index(index, 5).unwrap(), input);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct LehmerShuffler {
+    last_index: Option<u64>,
+}
+
+impl LehmerShuffler {
+    /// Returns the index of the permutation applied by the last successful
+    /// shuffle, or `None` if there wasn't one.
+    pub fn last_index(&self) -> Option<u64> {
+        self.last_index
+    }
+}
+
+impl<T> Shuffler<T> for LehmerShuffler {
+    fn shuffle_slice<R>(&mut self, data: &mut [T], rng: &mut R) -> Result<(), ShuffleError>
+    where
+        T: Clone,
+        R: RngCore + ?Sized,
+    {
+        let permutation = Shuffler::<T>::shuffle_indices(self, data.len(), rng)?;
+        apply_permutation(data, &permutation)
+    }
+
+    fn shuffle_indices<R>(&mut self, len: usize, rng: &mut R) -> Result<Vec<usize>, ShuffleError>
+    where
+        R: RngCore + ?Sized,
+    {
+        self.last_index = None;
+        let count = factorial(len)?;
+        // Nothing to choose from, so no randomness is drawn.
+        let index = if count == 1 {
+            0
+        } else {
+            rng.gen_range(0..count)
+        };
+        let permutation = permutation_from_index(index, len)?;
+        self.last_index = Some(index);
+        Ok(permutation)
+    }
+}
+
+/// Returns the permutation of `0..n` with the given `index` in
+/// lexicographic order, so `0` is the identity and `n! - 1` the reversal.
+///
+/// Returns [`ShuffleError::InvalidArgument`] if `n` is more than
+/// [`MAX_LEN`], and [`ShuffleError::OutOfBounds`] if `index` is not below
+/// `n!`.
+///
+/// # Examples
+/// ```
+/// use shuffle::lehmer::permutation_from_index;
+///
+/// assert_eq!(permutation_from_index(0, 3).unwrap(), vec![0, 1, 2]);
+/// assert_eq!(permutation_from_index(3, 3).unwrap(), vec![1, 2, 0]);
+/// assert_eq!(permutation_from_index(5, 3).unwrap(), vec![2, 1, 0]);
+/// assert!(permutation_from_index(6, 3).is_err());
+/// ```
+pub fn permutation_from_index(index: u64, n: usize) -> Result<Vec<usize>, ShuffleError> {
+    if index >= factorial(n)? {
+        return Err(ShuffleError::OutOfBounds);
+    }
+    let mut unused: Vec<usize> = (0..n).collect();
+    let mut permutation = Vec::with_capacity(n);
+    let mut rest = index;
+    for i in (0..n).rev() {
+        // `i!` fits, as it is at most `n!`.
+        let place = factorial(i)?;
+        permutation.push(unused.remove((rest / place) as usize));
+        rest %= place;
+    }
+    Ok(permutation)
+}
+
+/// Returns the index of `perm` among the permutations of its length in
+/// lexicographic order, the inverse of [`permutation_from_index`].
+///
+/// Returns [`ShuffleError::InvalidArgument`] if `perm` has more than
+/// [`MAX_LEN`] elements, and [`ShuffleError::InvalidPermutation`] if it is
+/// not a permutation of `0..perm.len()`.
+///
+/// # Examples
+/// ```
+/// use shuffle::lehmer::index_of_permutation;
+///
+/// assert_eq!(index_of_permutation(&[1, 2, 0]), Ok(3));
+/// assert!(index_of_permutation(&[1, 1, 0]).is_err());
+/// ```
+pub fn index_of_permutation(perm: &[usize]) -> Result<u64, ShuffleError> {
+    let n = perm.len();
+    factorial(n)?;
+    let mut used = [false; MAX_LEN];
+    let mut index = 0;
+    for (i, &element) in perm.iter().enumerate() {
+        if element >= n || used[element] {
+            return Err(ShuffleError::InvalidPermutation);
+        }
+        used[element] = true;
+        let smaller_unused = used[..element].iter().filter(|&&u| !u).count();
+        index = index * (n - i) as u64 + smaller_unused as u64;
+    }
+    Ok(index)
+}
+
+/// Returns `n!`, or an error if `n` is more than [`MAX_LEN`].
+fn factorial(n: usize) -> Result<u64, ShuffleError> {
+    if n > MAX_LEN {
+        return Err(ShuffleError::InvalidArgument(
+            "at most 20 elements are supported",
+        ));
+    }
+    Ok((1..=n as u64).product())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{chi_square_critical_value, permutation_chi_square};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_index_round_trip() {
+        for n in 0..=6 {
+            let count = factorial(n).unwrap();
+            for index in 0..count {
+                let perm = permutation_from_index(index, n).unwrap();
+                assert!(crate::permutation::is_permutation(&perm));
+                assert_eq!(index_of_permutation(&perm), Ok(index));
+            }
+        }
+        let reversed: Vec<usize> = (0..MAX_LEN).rev().collect();
+        let last = factorial(MAX_LEN).unwrap() - 1;
+        assert_eq!(permutation_from_index(last, MAX_LEN).unwrap(), reversed);
+        assert_eq!(index_of_permutation(&reversed), Ok(last));
+        for index in [1, 1 << 40, last / 3] {
+            let perm = permutation_from_index(index, MAX_LEN).unwrap();
+            assert_eq!(index_of_permutation(&perm), Ok(index));
+        }
+    }
+
+    #[test]
+    fn test_lexicographic_order() {
+        let perms: Vec<Vec<usize>> = (0..24)
+            .map(|index| permutation_from_index(index, 4).unwrap())
+            .collect();
+        assert!(perms.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(
+            permutation_from_index(0, MAX_LEN + 1),
+            Err(ShuffleError::InvalidArgument(_))
+        ));
+        assert_eq!(
+            permutation_from_index(120, 5),
+            Err(ShuffleError::OutOfBounds)
+        );
+        assert_eq!(
+            index_of_permutation(&[0, 3, 1]),
+            Err(ShuffleError::InvalidPermutation)
+        );
+        let long: Vec<usize> = (0..=MAX_LEN).collect();
+        assert!(matches!(
+            index_of_permutation(&long),
+            Err(ShuffleError::InvalidArgument(_))
+        ));
+
+        let mut shuffler = LehmerShuffler::default();
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut target = long.clone();
+        assert!(shuffler.shuffle(&mut target, &mut rng).is_err());
+        assert_eq!(target, long);
+        assert_eq!(shuffler.last_index(), None);
+    }
+
+    #[test]
+    fn test_uniform() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut shuffler = LehmerShuffler::default();
+        for _ in 0..100 {
+            let mut target = vec![0, 1, 2, 3];
+            shuffler.shuffle(&mut target, &mut rng).unwrap();
+            assert_eq!(
+                index_of_permutation(&target),
+                Ok(shuffler.last_index().unwrap())
+            );
+        }
+        let statistic = permutation_chi_square(&mut shuffler, 4, 24_000, &mut rng);
+        assert!(statistic < chi_square_critical_value(23, 0.999));
+    }
+
+    #[test]
+    fn test_small_inputs_draw_nothing() {
+        let mut rng = rand::rngs::mock::StepRng::new(0, 1);
+        let mut shuffler = LehmerShuffler::default();
+        let mut single = vec![7];
+        shuffler.shuffle(&mut single, &mut rng).unwrap();
+        assert_eq!(single, vec![7]);
+        assert_eq!(shuffler.last_index(), Some(0));
+        assert_eq!(rng.next_u64(), 0);
+    }
+}
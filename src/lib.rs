@@ -20,21 +20,99 @@
 //! ```
 //! use shuffle::shuffler::Shuffler;
 //! use shuffle::irs::Irs;
-//! use rand::rngs::mock::StepRng;
+//! use rand::rngs::StdRng;
+//! use rand::SeedableRng;
 //!
-//! let mut rng = StepRng::new(2, 13);
+//! let mut rng = StdRng::seed_from_u64(42);
 //! let mut irs = Irs::default();
 //!
 //! let mut input = vec![1, 2, 3, 4, 5];
 //!
-//! irs.shuffle(&mut input, &mut rng);
-//! assert_eq!(&input, &[4, 1, 5, 3, 2]);
+//! irs.shuffle(&mut input, &mut rng).unwrap();
+//! assert_eq!(&input, &[5, 2, 4, 3, 1]);
 //! ```
+//!
+//! # Features
+//!
+//! The `std` feature is enabled by default. Without it the crate is
+//! `no_std`: the `alloc` feature keeps the [`Shuffler`](shuffler::Shuffler)
+//! trait and [`Irs`](irs::Irs), and with no features at all only the
+//! in-place methods of [`FisherYates`](fy::FisherYates),
+//! [`fy::shuffle_array`], and [`blocks::shuffle_chunks`] remain, which
+//! need nothing but `core`. The `os-rng` feature only needs `alloc` too,
+//! on the targets `getrandom` supports.
 
+#![cfg_attr(not(feature = "std"), no_std)]
 #![deny(missing_docs)]
 #![deny(missing_debug_implementations)]
 #![deny(rustdoc::broken_intra_doc_links)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod analysis;
+#[cfg(feature = "std")]
+pub mod annealed;
+#[cfg(feature = "std")]
+pub mod assign;
+pub mod blocks;
+#[cfg(feature = "std")]
+pub mod casino;
+#[cfg(feature = "std")]
+pub mod combinations;
+#[cfg(feature = "std")]
+pub mod constrained;
+#[cfg(feature = "std")]
+pub mod deck;
+#[cfg(feature = "std")]
+pub mod derangement;
+#[cfg(feature = "std")]
+pub mod design;
+pub mod feistel;
 pub mod fy;
+#[cfg(feature = "std")]
+pub mod grouped;
+#[cfg(feature = "alloc")]
 pub mod irs;
+#[cfg(feature = "std")]
+pub mod lehmer;
+#[cfg(feature = "std")]
+pub mod optimal;
+#[cfg(feature = "os-rng")]
+pub mod os;
+#[cfg(feature = "std")]
+pub mod parallel;
+#[cfg(feature = "std")]
+pub mod permutation;
+#[cfg(feature = "alloc")]
+pub mod pipeline;
+// The fingerprint depends on the width of `usize`.
+#[cfg(all(test, feature = "std", target_pointer_width = "64"))]
+mod regression;
+#[cfg(feature = "std")]
+pub mod riffle;
+#[cfg(feature = "std")]
+pub mod sample;
+#[cfg(feature = "std")]
+pub mod sattolo;
+#[cfg(feature = "seeded")]
+pub mod seeded;
+#[cfg(feature = "std")]
+pub mod select;
 pub mod shuffler;
+#[cfg(feature = "std")]
+pub mod smooth;
+#[cfg(feature = "sparse")]
+pub mod sparse;
+pub mod swap;
+#[cfg(any(test, feature = "test-util"))]
+pub mod testing;
+#[cfg(feature = "std")]
+pub mod topo;
+#[cfg(feature = "std")]
+pub mod verify;
+#[cfg(feature = "std")]
+pub mod view;
+#[cfg(feature = "std")]
+pub mod weighted;
@@ -0,0 +1,163 @@
+//! Shuffling with close to the minimal number of random bits.
+//!
+//! A uniform permutation of `n` elements carries `log2(n!)` bits of
+//! entropy, so that is the least any shuffle can consume on average.
+//! Fisher-Yates drawing each index with its own `gen_range` call wastes
+//! a lot more, which matters when random bits are expensive.
+
+use crate::shuffler::ShuffleError;
+
+/// The number of rejections after which a randomness source is
+/// considered broken. A fair source gets there with probability
+/// below `2^-128`.
+const MAX_REJECTIONS: usize = 128;
+
+/// Shuffles `data` using the bits returned by `bit_fn`, consuming close
+/// to `log2(n!)` of them.
+///
+/// The Fisher-Yates indices are combined into a mixed-radix number:
+/// the radixes `n, n - 1, ..., 2` are grouped into products that fit
+/// in a `u64`, and one uniform number is drawn per group with Lumbroso's
+/// Fast Dice Roller, which reads the bits lazily and recycles them on
+/// rejection. Each group wastes less than 2 bits on average, and a group
+/// covers about 63 bits of entropy, so the expected number of bits is at
+/// most `log2(n!) + 2 * ⌈log2(n!) / 63⌉`. For 100 elements that is less
+/// than 4% above the bound.
+///
+/// The result is a uniformly distributed permutation, provided the bits
+/// are fair and independent. If `bit_fn` looks broken (e.g. it only ever
+/// returns `true`), an error is returned and `data` is left unchanged.
+///
+/// *Lumbroso, Jérémie. "Optimal discrete uniform generation from coin
+/// flips, and applications." arXiv:1304.1916 (2013).*
+///
+/// # Examples
+/// ```
+/// use shuffle::optimal;
+///
+/// let mut bits = [true, false, false, true, true].iter().cycle();
+/// let mut input = vec![1, 2, 3, 4];
+///
+/// optimal::shuffle(&mut input, || *bits.next().unwrap()).unwrap();
+/// input.sort();
+/// assert_eq!(&input, &[1, 2, 3, 4]);
+/// ```
+pub fn shuffle<T, F>(data: &mut [T], mut bit_fn: F) -> Result<(), ShuffleError>
+where
+    F: FnMut() -> bool,
+{
+    // Every group is stored as (largest radix, smallest radix, value).
+    let mut groups = Vec::new();
+    let mut radix = data.len();
+    while radix >= 2 {
+        let largest = radix;
+        let mut modulus: u64 = 1;
+        while radix >= 2 {
+            match modulus.checked_mul(radix as u64) {
+                Some(product) => modulus = product,
+                None => break,
+            }
+            radix -= 1;
+        }
+        let value = uniform(modulus, &mut bit_fn)?;
+        groups.push((largest, radix + 1, value));
+    }
+
+    for (largest, smallest, mut value) in groups {
+        for radix in (smallest..=largest).rev() {
+            let j = (value % radix as u64) as usize;
+            value /= radix as u64;
+            data.swap(radix - 1, j);
+        }
+    }
+    Ok(())
+}
+
+/// Draws a uniform number from `0..n` with the Fast Dice Roller.
+///
+/// `c` is uniform in `0..v` at all times; when a rejection happens, the
+/// excess `c - n` is still uniform in `0..(v - n)` and is kept.
+fn uniform<F>(n: u64, bit_fn: &mut F) -> Result<u64, ShuffleError>
+where
+    F: FnMut() -> bool,
+{
+    let n = n as u128;
+    let mut v: u128 = 1;
+    let mut c: u128 = 0;
+    let mut rejections = 0;
+    loop {
+        v <<= 1;
+        c = (c << 1) | bit_fn() as u128;
+        if v >= n {
+            if c < n {
+                return Ok(c as u64);
+            }
+            v -= n;
+            c -= n;
+            rejections += 1;
+            if rejections > MAX_REJECTIONS {
+                return Err(ShuffleError::BadRandomness);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{chi_square_critical_value, counts_chi_square};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn log2_factorial(n: usize) -> f64 {
+        (2..=n).map(|k| (k as f64).log2()).sum()
+    }
+
+    #[test]
+    fn test_bits_consumed() {
+        let mut rng = StdRng::seed_from_u64(30);
+        let len = 100;
+        let trials = 1000;
+        let mut consumed = 0;
+        for _ in 0..trials {
+            let mut target: Vec<usize> = (0..len).collect();
+            shuffle(&mut target, || {
+                consumed += 1;
+                rng.gen()
+            })
+            .unwrap();
+            target.sort_unstable();
+            assert_eq!(target, (0..len).collect::<Vec<_>>());
+        }
+
+        let average = consumed as f64 / trials as f64;
+        let bound = log2_factorial(len);
+        assert!(average >= bound - 1.0);
+        assert!(average <= bound + 2.0 * (bound / 63.0).ceil());
+    }
+
+    #[test]
+    fn test_uniformity() {
+        let mut rng = StdRng::seed_from_u64(30);
+        let trials = 24_000;
+        let mut counts = std::collections::HashMap::new();
+        for _ in 0..trials {
+            let mut target = vec![0, 1, 2, 3];
+            shuffle(&mut target, || rng.gen()).unwrap();
+            *counts.entry(target).or_insert(0) += 1;
+        }
+        assert_eq!(counts.len(), 24);
+        let counts: Vec<usize> = counts.into_values().collect();
+        assert!(counts_chi_square(&counts) < chi_square_critical_value(23, 0.999));
+    }
+
+    #[test]
+    fn test_broken_bit_source() {
+        let mut target = vec![1, 2, 3, 4, 5];
+        assert_eq!(
+            shuffle(&mut target, || true),
+            Err(ShuffleError::BadRandomness)
+        );
+        assert_eq!(target, vec![1, 2, 3, 4, 5]);
+    }
+}
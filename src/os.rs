@@ -0,0 +1,163 @@
+//! Shuffling with the randomness of the operating system.
+//!
+//! [`shuffle_secure`] draws from [`OsRng`], which reads the system's
+//! entropy source through `getrandom`, so there is no generator to set
+//! up. This doesn't need `std`, only a target `getrandom` supports.
+//!
+//! Available with the `os-rng` feature.
+
+use rand::rngs::OsRng;
+use rand::RngCore;
+
+use crate::shuffler::{ShuffleError, Shuffler};
+
+/// Shuffles `data` with `shuffler`, drawing the randomness from the
+/// operating system.
+///
+/// Returns [`ShuffleError::BadRandomness`] if the entropy source fails
+/// at any point of the shuffle, leaving `data` in its original order.
+/// Errors of the shuffler itself are returned as they are.
+///
+/// # Examples
+/// ```
+/// use shuffle::fy::FisherYates;
+/// use shuffle::os::shuffle_secure;
+///
+/// let mut fy = FisherYates::default();
+/// let mut input = vec![1, 2, 3, 4, 5];
+///
+/// shuffle_secure(&mut fy, &mut input).unwrap();
+/// input.sort();
+/// assert_eq!(input, vec![1, 2, 3, 4, 5]);
+/// ```
+pub fn shuffle_secure<T, S>(shuffler: &mut S, data: &mut [T]) -> Result<(), ShuffleError>
+where
+    T: Clone,
+    S: Shuffler<T> + ?Sized,
+{
+    shuffle_checked(shuffler, data, OsRng)
+}
+
+fn shuffle_checked<T, S, R>(shuffler: &mut S, data: &mut [T], rng: R) -> Result<(), ShuffleError>
+where
+    T: Clone,
+    S: Shuffler<T> + ?Sized,
+    R: RngCore,
+{
+    let mut rng = CheckedRng { rng, failed: false };
+    // The shuffler can't tell the zeros handed out after a failure from
+    // random bytes, so it may well finish the shuffle.
+    let original = data.to_vec();
+    let result = shuffler.shuffle_slice(data, &mut rng);
+    if rng.failed {
+        data.clone_from_slice(&original);
+        return Err(ShuffleError::BadRandomness);
+    }
+    result
+}
+
+/// Forwards to `rng`, but records its errors instead of panicking and
+/// hands out zeros after one.
+struct CheckedRng<R> {
+    rng: R,
+    failed: bool,
+}
+
+impl<R> RngCore for CheckedRng<R>
+where
+    R: RngCore,
+{
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        if self.failed || self.rng.try_fill_bytes(dest).is_err() {
+            self.failed = true;
+            dest.iter_mut().for_each(|byte| *byte = 0);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fy::FisherYates;
+    use crate::irs::Irs;
+    use core::num::NonZeroU32;
+
+    /// Fails after handing out `left` bytes.
+    struct FailingRng {
+        left: usize,
+    }
+
+    impl RngCore for FailingRng {
+        fn next_u32(&mut self) -> u32 {
+            unimplemented!()
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            unimplemented!()
+        }
+
+        fn fill_bytes(&mut self, _dest: &mut [u8]) {
+            unimplemented!()
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+            if self.left < dest.len() {
+                return Err(NonZeroU32::new(rand::Error::CUSTOM_START).unwrap().into());
+            }
+            for byte in dest {
+                self.left -= 1;
+                *byte = (self.left as u8).wrapping_mul(0x9d);
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_shuffle_secure() {
+        let input_data: Vec<u32> = (0..100).collect();
+        let mut target = input_data.clone();
+        shuffle_secure(&mut Irs::default(), &mut target).unwrap();
+        assert_ne!(target, input_data);
+        target.sort_unstable();
+        assert_eq!(target, input_data);
+    }
+
+    #[test]
+    fn test_entropy_errors_are_reported() {
+        let input_data: Vec<u32> = (0..100).collect();
+        let mut data = input_data.clone();
+        for left in [0, 8, 100] {
+            let rng = FailingRng { left };
+            assert_eq!(
+                shuffle_checked(&mut FisherYates::default(), &mut data, rng),
+                Err(ShuffleError::BadRandomness)
+            );
+            assert_eq!(data, input_data);
+            let rng = FailingRng { left };
+            assert_eq!(
+                shuffle_checked(&mut Irs::default(), &mut data, rng),
+                Err(ShuffleError::BadRandomness)
+            );
+            assert_eq!(data, input_data);
+        }
+        let rng = FailingRng { left: 10_000 };
+        assert!(shuffle_checked(&mut FisherYates::default(), &mut data, rng).is_ok());
+    }
+}
@@ -0,0 +1,154 @@
+//! Shuffling several slices in lockstep.
+//!
+//! Useful for columnar data, where the rows are spread across
+//! separate slices that have to stay aligned.
+
+use rand::Rng;
+
+use crate::shuffler::ShuffleError;
+
+/// Shuffles `a` and `b` with the same permutation.
+///
+/// The same sequence of Fisher-Yates swaps is applied to both slices,
+/// so `a[i]` and `b[i]` still belong together afterwards.
+///
+/// Returns an error if the slices have different lengths.
+///
+/// # Examples
+/// ```
+/// use shuffle::parallel;
+/// use rand::rngs::mock::StepRng;
+///
+/// let mut rng = StepRng::new(2, 13);
+/// let mut names = ["a", "b", "c", "d"];
+/// let mut ages = [1, 2, 3, 4];
+///
+/// parallel::shuffle_two(&mut names, &mut ages, &mut rng).unwrap();
+/// for (name, age) in names.iter().zip(ages.iter()) {
+///     assert_eq!(name.as_bytes()[0] - b'a' + 1, *age);
+/// }
+/// ```
+pub fn shuffle_two<A, B, R>(a: &mut [A], b: &mut [B], rng: &mut R) -> Result<(), ShuffleError>
+where
+    R: Rng + ?Sized,
+{
+    (a, b).shuffle(rng)
+}
+
+/// Shuffling any number of slices in lockstep.
+///
+/// Implemented for tuples of two to six mutable slices. All the slices
+/// are shuffled with the same permutation, so the elements at equal
+/// indices stay together.
+///
+/// # Examples
+/// ```
+/// use shuffle::parallel::ParallelShuffle;
+/// use rand::rngs::mock::StepRng;
+///
+/// let mut rng = StepRng::new(2, 13);
+/// let mut ids = vec![1, 2, 3];
+/// let mut names = vec!["one", "two", "three"];
+/// let mut squares = vec![1, 4, 9];
+///
+/// (&mut ids[..], &mut names[..], &mut squares[..])
+///     .shuffle(&mut rng)
+///     .unwrap();
+/// for (id, square) in ids.iter().zip(squares.iter()) {
+///     assert_eq!(id * id, *square);
+/// }
+/// ```
+pub trait ParallelShuffle {
+    /// Shuffles all the slices with the same permutation.
+    ///
+    /// Returns an error, leaving the slices unchanged, if they
+    /// don't all have the same length.
+    fn shuffle<R>(&mut self, rng: &mut R) -> Result<(), ShuffleError>
+    where
+        R: Rng + ?Sized;
+}
+
+macro_rules! impl_parallel_shuffle {
+    ($first:ident 0 $(, $rest:ident $index:tt)*) => {
+        impl<'a, $first, $($rest),*> ParallelShuffle for (&'a mut [$first], $(&'a mut [$rest]),*) {
+            fn shuffle<R>(&mut self, rng: &mut R) -> Result<(), ShuffleError>
+            where
+                R: Rng + ?Sized,
+            {
+                let len = self.0.len();
+                if [$(self.$index.len()),*].iter().any(|&other| other != len) {
+                    return Err(ShuffleError::LengthMismatch);
+                }
+                for i in (1..len).rev() {
+                    let j = rng.gen_range(0..(i + 1));
+                    self.0.swap(i, j);
+                    $(self.$index.swap(i, j);)*
+                }
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_parallel_shuffle!(A 0, B 1);
+impl_parallel_shuffle!(A 0, B 1, C 2);
+impl_parallel_shuffle!(A 0, B 1, C 2, D 3);
+impl_parallel_shuffle!(A 0, B 1, C 2, D 3, E 4);
+impl_parallel_shuffle!(A 0, B 1, C 2, D 3, E 4, F 5);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_shuffle_two_keeps_pairs() {
+        let mut rng = StdRng::seed_from_u64(28);
+        let mut a: Vec<usize> = (0..100).collect();
+        let mut b: Vec<String> = (0..100).map(|i| i.to_string()).collect();
+        shuffle_two(&mut a, &mut b, &mut rng).unwrap();
+
+        assert_ne!(a, (0..100).collect::<Vec<_>>());
+        for (x, y) in a.iter().zip(b.iter()) {
+            assert_eq!(&x.to_string(), y);
+        }
+        a.sort_unstable();
+        assert_eq!(a, (0..100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_parallel_shuffle_three_slices() {
+        let mut rng = StdRng::seed_from_u64(29);
+        let mut a: Vec<u64> = (0..100).collect();
+        let mut b: Vec<u64> = (0..100).map(|i| i * i).collect();
+        let mut c: Vec<String> = (0..100).map(|i| i.to_string()).collect();
+        (&mut a[..], &mut b[..], &mut c[..])
+            .shuffle(&mut rng)
+            .unwrap();
+
+        assert_ne!(a, (0..100).collect::<Vec<_>>());
+        for ((x, y), z) in a.iter().zip(b.iter()).zip(c.iter()) {
+            assert_eq!(x * x, *y);
+            assert_eq!(&x.to_string(), z);
+        }
+
+        let mut d = [0u8; 99];
+        assert_eq!(
+            (&mut a[..], &mut b[..], &mut d[..]).shuffle(&mut rng),
+            Err(ShuffleError::LengthMismatch)
+        );
+    }
+
+    #[test]
+    fn test_shuffle_two_different_lengths() {
+        let mut rng = StdRng::seed_from_u64(28);
+        let mut a = [1, 2, 3];
+        let mut b = [1, 2];
+        assert_eq!(
+            shuffle_two(&mut a, &mut b, &mut rng),
+            Err(ShuffleError::LengthMismatch)
+        );
+        assert_eq!(a, [1, 2, 3]);
+    }
+}
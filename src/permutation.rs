@@ -0,0 +1,547 @@
+//! Utilities for working with permutations produced by the shufflers.
+//!
+//! A permutation of length `n` is represented as a slice `perm` holding
+//! every index of `0..n` exactly once, where `perm[i]` is the index of
+//! the element that ends up at position `i`. This is the same form as the
+//! one returned by [`Irs::shuffle_indices`](crate::irs::Irs::shuffle_indices).
+
+use rand::Rng;
+
+use crate::fy::FisherYates;
+use crate::shuffler::ShuffleError;
+
+/// Converts `perm` to a sparse permutation matrix.
+///
+/// Returns the `(row, column)` positions of the ones in the matrix `P`
+/// such that multiplying `P` by a column vector applies `perm` to it,
+/// that is, the `n` pairs `(i, perm[i])`.
+///
+/// # Examples
+/// ```
+/// use shuffle::permutation::to_sparse_matrix;
+///
+/// assert_eq!(to_sparse_matrix(&[2, 0, 1]), vec![(0, 2), (1, 0), (2, 1)]);
+/// ```
+pub fn to_sparse_matrix(perm: &[usize]) -> Vec<(usize, usize)> {
+    perm.iter().copied().enumerate().collect()
+}
+
+/// Decomposes `perm` into a sequence of adjacent transpositions.
+///
+/// Returns the positions `i` such that swapping the elements at `i`
+/// and `i + 1`, in the returned order, turns the identity `0..n` into `perm`.
+/// The decomposition is the one found by bubble sort, so its length
+/// is the number of inversions in `perm`, which is the shortest possible.
+///
+/// # Examples
+/// ```
+/// use shuffle::permutation::to_adjacent_transpositions;
+///
+/// let perm = [2, 0, 1];
+/// let transpositions = to_adjacent_transpositions(&perm);
+///
+/// let mut data = vec![0, 1, 2];
+/// for i in transpositions {
+///     data.swap(i, i + 1);
+/// }
+/// assert_eq!(data, perm);
+/// ```
+pub fn to_adjacent_transpositions(perm: &[usize]) -> Vec<usize> {
+    let mut sorted = perm.to_vec();
+    let mut transpositions = Vec::new();
+    for i in 1..sorted.len() {
+        let mut j = i;
+        while j > 0 && sorted[j - 1] > sorted[j] {
+            sorted.swap(j - 1, j);
+            transpositions.push(j - 1);
+            j -= 1;
+        }
+    }
+    // Sorting `perm` applies the transpositions in reverse, and each
+    // of them is its own inverse.
+    transpositions.reverse();
+    transpositions
+}
+
+/// Counts the inversions of `perm`, i.e. the pairs `i < j` with
+/// `perm[i] > perm[j]`.
+///
+/// This is how many adjacent swaps it takes to sort `perm`, from `0` for
+/// the identity to `n * (n - 1) / 2` for the reversal; a uniformly random
+/// permutation has `n * (n - 1) / 4` on average. It is computed by merge
+/// sort in `O(n log n)` time.
+///
+/// # Examples
+/// ```
+/// use shuffle::permutation::inversions;
+///
+/// assert_eq!(inversions(&[2, 0, 1]), 2);
+/// ```
+pub fn inversions(perm: &[usize]) -> usize {
+    let mut sorted = perm.to_vec();
+    let mut buffer = vec![0; perm.len()];
+    sort_counting_inversions(&mut sorted, &mut buffer)
+}
+
+/// The parity of a permutation, i.e. whether it is a product of an even
+/// or an odd number of transpositions.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Parity {
+    /// An even number of transpositions, e.g. the identity.
+    Even,
+    /// An odd number of transpositions, e.g. a single swap.
+    Odd,
+}
+
+/// Returns the [`Parity`] of `perm`, which has to be a permutation.
+///
+/// This is the parity of the number of [`inversions`], but it is computed
+/// from the cycles of `perm` in linear time: a cycle of length `l` is the
+/// product of `l - 1` transpositions.
+///
+/// # Examples
+/// ```
+/// use shuffle::permutation::{parity, Parity};
+///
+/// assert_eq!(parity(&[0, 1, 2]), Parity::Even);
+/// assert_eq!(parity(&[1, 0, 2]), Parity::Odd);
+/// assert_eq!(parity(&[2, 0, 1]), Parity::Even);
+/// ```
+pub fn parity(perm: &[usize]) -> Parity {
+    let mut visited = vec![false; perm.len()];
+    let mut cycles = 0;
+    for start in 0..perm.len() {
+        if visited[start] {
+            continue;
+        }
+        cycles += 1;
+        let mut position = start;
+        while !visited[position] {
+            visited[position] = true;
+            position = perm[position];
+        }
+    }
+    if (perm.len() - cycles).is_multiple_of(2) {
+        Parity::Even
+    } else {
+        Parity::Odd
+    }
+}
+
+/// Returns a uniformly random even permutation of `0..n`.
+///
+/// A uniformly random permutation is computed with
+/// [`FisherYates::shuffle_indices_vec`], and if it is odd its first two
+/// entries are swapped. Swapping them maps the odd permutations one to
+/// one onto the even ones, so every even permutation is equally likely.
+/// This is what puzzles like the 15-puzzle need, as only the even
+/// arrangements are solvable.
+///
+/// # Examples
+/// ```
+/// use shuffle::permutation::{parity, random_even, Parity};
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let mut rng = StdRng::seed_from_u64(42);
+/// let perm = random_even(15, &mut rng);
+/// assert_eq!(parity(&perm), Parity::Even);
+/// ```
+pub fn random_even<R>(n: usize, rng: &mut R) -> Vec<usize>
+where
+    R: Rng + ?Sized,
+{
+    let mut perm = FisherYates::default().shuffle_indices_vec(n, rng);
+    if parity(&perm) == Parity::Odd {
+        perm.swap(0, 1);
+    }
+    perm
+}
+
+/// Merge sorts `data` using `buffer` of the same length as scratch space,
+/// and returns the number of inversions it had.
+fn sort_counting_inversions(data: &mut [usize], buffer: &mut [usize]) -> usize {
+    if data.len() < 2 {
+        return 0;
+    }
+    let middle = data.len() / 2;
+    let mut count = {
+        let (left, right) = data.split_at_mut(middle);
+        let (left_buffer, right_buffer) = buffer.split_at_mut(middle);
+        sort_counting_inversions(left, left_buffer) + sort_counting_inversions(right, right_buffer)
+    };
+
+    let (mut i, mut j) = (0, middle);
+    for slot in buffer.iter_mut() {
+        if j == data.len() || (i < middle && data[i] <= data[j]) {
+            *slot = data[i];
+            i += 1;
+        } else {
+            // Every element left in the first half is larger.
+            *slot = data[j];
+            count += middle - i;
+            j += 1;
+        }
+    }
+    data.copy_from_slice(buffer);
+    count
+}
+
+/// Tells for every position of `perm` whether it is a fixed point,
+/// that is, whether `perm[i] == i`.
+///
+/// # Examples
+/// ```
+/// use shuffle::permutation::fixed_point_mask;
+///
+/// assert_eq!(fixed_point_mask(&[0, 2, 1, 3]), vec![true, false, false, true]);
+/// ```
+pub fn fixed_point_mask(perm: &[usize]) -> Vec<bool> {
+    perm.iter().enumerate().map(|(i, &p)| i == p).collect()
+}
+
+/// Run-length encodes the [`fixed_point_mask`] of `perm`.
+///
+/// Returns the consecutive runs as `(fixed, length)` pairs, where `fixed`
+/// tells whether the run consists of fixed points or of moved elements.
+/// Near-identity permutations have few, long runs, so this is a compact
+/// way to store which elements were moved.
+///
+/// # Examples
+/// ```
+/// use shuffle::permutation::rle_fixed_points;
+///
+/// let perm = [0, 1, 2, 4, 3, 5, 6];
+/// assert_eq!(rle_fixed_points(&perm), vec![(true, 3), (false, 2), (true, 2)]);
+/// ```
+pub fn rle_fixed_points(perm: &[usize]) -> Vec<(bool, usize)> {
+    let mut runs: Vec<(bool, usize)> = Vec::new();
+    for (i, &p) in perm.iter().enumerate() {
+        let fixed = i == p;
+        match runs.last_mut() {
+            Some((run_fixed, length)) if *run_fixed == fixed => *length += 1,
+            _ => runs.push((fixed, 1)),
+        }
+    }
+    runs
+}
+
+/// Tells whether `perm` is a permutation of `0..perm.len()`, i.e. whether
+/// it holds every index of that range exactly once.
+///
+/// The input doesn't have to be sorted: the indices are checked off in
+/// a bitset, one bit per index, in a single pass. This is the check
+/// [`apply_permutation`] does, for validating indices from outside
+/// before using them.
+///
+/// # Examples
+/// ```
+/// use shuffle::permutation::is_permutation;
+///
+/// assert!(is_permutation(&[2, 0, 3, 1]));
+/// assert!(is_permutation(&[]));
+/// assert!(!is_permutation(&[0, 5]));
+/// assert!(!is_permutation(&[1, 1]));
+/// ```
+pub fn is_permutation(perm: &[usize]) -> bool {
+    let mut seen = vec![0u64; perm.len().div_ceil(64)];
+    perm.iter().all(|&index| {
+        if index >= perm.len() {
+            return false;
+        }
+        let (word, bit) = (index / 64, 1 << (index % 64));
+        let fresh = seen[word] & bit == 0;
+        seen[word] |= bit;
+        fresh
+    })
+}
+
+/// Reorders `data` in place by `perm`, so that the element at position
+/// `i` is the one that was at position `perm[i]` before.
+///
+/// This mirrors a shuffle computed with
+/// [`shuffle_indices`](crate::shuffler::Shuffler::shuffle_indices) onto
+/// other data. The elements are moved along the cycles of `perm` by
+/// swaps, so they don't have to implement `Clone`; the only allocation
+/// is a flag per element, to check `perm` and track the cycles.
+///
+/// Returns an error and leaves `data` unchanged if `perm` is not
+/// a permutation of `0..data.len()`, i.e. if it has a different length,
+/// an index out of range or a repeated index.
+///
+/// # Examples
+/// ```
+/// use shuffle::permutation::apply_permutation;
+///
+/// let mut data = ['a', 'b', 'c', 'd'];
+/// apply_permutation(&mut data, &[2, 0, 3, 1]).unwrap();
+/// assert_eq!(data, ['c', 'a', 'd', 'b']);
+///
+/// assert!(apply_permutation(&mut data, &[0, 0, 1, 2]).is_err());
+/// ```
+pub fn apply_permutation<T>(data: &mut [T], perm: &[usize]) -> Result<(), ShuffleError> {
+    if perm.len() != data.len() {
+        return Err(ShuffleError::InvalidPermutation);
+    }
+    let mut done = vec![false; perm.len()];
+    for &source in perm {
+        match done.get_mut(source) {
+            Some(seen @ false) => *seen = true,
+            _ => return Err(ShuffleError::InvalidPermutation),
+        }
+    }
+    // All the flags are set now; clearing one marks its position as done.
+    for start in 0..perm.len() {
+        if !done[start] {
+            continue;
+        }
+        let mut position = start;
+        loop {
+            done[position] = false;
+            let source = perm[position];
+            if source == start {
+                break;
+            }
+            data.swap(position, source);
+            position = source;
+        }
+    }
+    Ok(())
+}
+
+/// Undoes [`apply_permutation`] with the same `perm`, restoring the
+/// order `data` had before it.
+///
+/// The element at position `perm[i]` afterwards is the one at position
+/// `i` now. So shuffling with a permutation from
+/// [`shuffle_indices`](crate::shuffler::Shuffler::shuffle_indices) and
+/// then calling this with it is the identity. The inverse of `perm` is
+/// computed internally; empty and single-element data is left as it is.
+///
+/// Returns an error and leaves `data` unchanged if `perm` is not
+/// a permutation of `0..data.len()`.
+///
+/// # Examples
+/// ```
+/// use shuffle::permutation::{apply_permutation, unshuffle};
+///
+/// let mut data = ['a', 'b', 'c', 'd'];
+/// let perm = [2, 0, 3, 1];
+/// apply_permutation(&mut data, &perm).unwrap();
+/// assert_eq!(data, ['c', 'a', 'd', 'b']);
+///
+/// unshuffle(&mut data, &perm).unwrap();
+/// assert_eq!(data, ['a', 'b', 'c', 'd']);
+/// ```
+pub fn unshuffle<T>(data: &mut [T], perm: &[usize]) -> Result<(), ShuffleError> {
+    if perm.len() != data.len() || !is_permutation(perm) {
+        return Err(ShuffleError::InvalidPermutation);
+    }
+    let mut inverse = vec![0; perm.len()];
+    for (position, &source) in perm.iter().enumerate() {
+        inverse[source] = position;
+    }
+    apply_permutation(data, &inverse)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::irs::Irs;
+    use crate::shuffler::Shuffler;
+    use crate::testing::{chi_square_critical_value, counts_chi_square};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_to_adjacent_transpositions() {
+        let mut rng = StdRng::seed_from_u64(12);
+        for n in 0..30 {
+            let perm = Irs::<()>::default().shuffle_indices(n, &mut rng).unwrap();
+            let mut data: Vec<usize> = (0..n).collect();
+            for i in to_adjacent_transpositions(&perm) {
+                data.swap(i, i + 1);
+            }
+            assert_eq!(data, perm);
+        }
+        assert!(to_adjacent_transpositions(&[0, 1, 2, 3]).is_empty());
+        assert_eq!(to_adjacent_transpositions(&[3, 2, 1, 0]).len(), 6);
+    }
+
+    #[test]
+    fn test_inversions() {
+        for n in [0, 1, 2, 5, 100, 1001] {
+            let identity: Vec<usize> = (0..n).collect();
+            assert_eq!(inversions(&identity), 0);
+            let reversal: Vec<usize> = (0..n).rev().collect();
+            assert_eq!(inversions(&reversal), n * n.saturating_sub(1) / 2);
+        }
+
+        let mut rng = StdRng::seed_from_u64(13);
+        for n in 0..30 {
+            let perm = Irs::<()>::default().shuffle_indices(n, &mut rng).unwrap();
+            assert_eq!(inversions(&perm), to_adjacent_transpositions(&perm).len());
+        }
+    }
+
+    #[test]
+    fn test_parity() {
+        let mut rng = StdRng::seed_from_u64(14);
+        for n in 0..30 {
+            let perm = Irs::<()>::default().shuffle_indices(n, &mut rng).unwrap();
+            let expected = if inversions(&perm).is_multiple_of(2) {
+                Parity::Even
+            } else {
+                Parity::Odd
+            };
+            assert_eq!(parity(&perm), expected);
+        }
+    }
+
+    #[test]
+    fn test_random_even() {
+        let mut rng = StdRng::seed_from_u64(14);
+        for n in [0, 1, 2, 3, 16, 100] {
+            for _ in 0..20 {
+                let perm = random_even(n, &mut rng);
+                assert_eq!(parity(&perm), Parity::Even);
+                let mut sorted = perm.clone();
+                sorted.sort_unstable();
+                assert_eq!(sorted, (0..n).collect::<Vec<_>>());
+            }
+        }
+
+        let mut counts = std::collections::HashMap::new();
+        for _ in 0..12_000 {
+            *counts.entry(random_even(4, &mut rng)).or_insert(0) += 1;
+        }
+        // There are 4! / 2 = 12 even permutations of 4 elements.
+        assert_eq!(counts.len(), 12);
+        let counts: Vec<usize> = counts.into_values().collect();
+        assert!(counts_chi_square(&counts) < chi_square_critical_value(11, 0.999));
+    }
+
+    #[test]
+    fn test_rle_fixed_points() {
+        let mut perm: Vec<usize> = (0..1000).collect();
+        perm.swap(100, 101);
+        perm.swap(500, 900);
+        perm.swap(998, 999);
+
+        let runs = rle_fixed_points(&perm);
+        assert_eq!(
+            runs,
+            vec![
+                (true, 100),
+                (false, 2),
+                (true, 398),
+                (false, 1),
+                (true, 399),
+                (false, 1),
+                (true, 97),
+                (false, 2)
+            ]
+        );
+        let decoded: Vec<bool> = runs
+            .iter()
+            .flat_map(|&(fixed, length)| std::iter::repeat_n(fixed, length))
+            .collect();
+        assert_eq!(decoded, fixed_point_mask(&perm));
+        assert!(rle_fixed_points(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_to_sparse_matrix() {
+        let mut rng = StdRng::seed_from_u64(11);
+        let n = 40;
+        let perm = Irs::<()>::default().shuffle_indices(n, &mut rng).unwrap();
+        let matrix = to_sparse_matrix(&perm);
+
+        assert_eq!(matrix.len(), n);
+        let mut rows = vec![0; n];
+        let mut columns = vec![0; n];
+        for &(row, column) in matrix.iter() {
+            rows[row] += 1;
+            columns[column] += 1;
+        }
+        assert!(rows.iter().all(|&count| count == 1));
+        assert!(columns.iter().all(|&count| count == 1));
+    }
+
+    #[test]
+    fn test_apply_permutation() {
+        /// An element that can't be cloned.
+        struct Handle(String);
+
+        let mut rng = StdRng::seed_from_u64(13);
+        for n in [0, 1, 2, 10, 257] {
+            let perm = Irs::<()>::default().shuffle_indices(n, &mut rng).unwrap();
+            let input_data: Vec<String> = (0..n).map(|i| i.to_string()).collect();
+            let expected: Vec<String> = perm.iter().map(|&i| input_data[i].clone()).collect();
+
+            let mut target: Vec<Handle> = input_data.into_iter().map(Handle).collect();
+            apply_permutation(&mut target, &perm).unwrap();
+            let target: Vec<String> = target.into_iter().map(|h| h.0).collect();
+            assert_eq!(target, expected);
+        }
+    }
+
+    #[test]
+    fn test_is_permutation() {
+        let mut rng = StdRng::seed_from_u64(14);
+        for n in [0, 1, 2, 63, 64, 65, 1000] {
+            let mut perm = Irs::<()>::default().shuffle_indices(n, &mut rng).unwrap();
+            assert!(is_permutation(&perm));
+            if n >= 2 {
+                perm[n / 2] = perm[0];
+                assert!(!is_permutation(&perm));
+                perm[n / 2] = n;
+                assert!(!is_permutation(&perm));
+            }
+        }
+        for perm in [&[0, 5][..], &[1], &[0, 0], &[usize::MAX, 0]] {
+            assert!(!is_permutation(perm));
+        }
+    }
+
+    #[test]
+    fn test_unshuffle_round_trip() {
+        let mut rng = StdRng::seed_from_u64(15);
+        for n in [0, 1, 2, 3, 10, 100, 1000] {
+            let input_data: Vec<String> = (0..n).map(|i| i.to_string()).collect();
+            let mut irs = Irs::default();
+            let mut target = input_data.clone();
+            irs.shuffle(&mut target, &mut rng).unwrap();
+            let perm = irs.last_permutation().unwrap().to_vec();
+            unshuffle(&mut target, &perm).unwrap();
+            assert_eq!(target, input_data);
+        }
+
+        let mut data = [1, 2, 3];
+        for perm in [&[0, 1][..], &[0, 1, 3], &[2, 0, 2]] {
+            assert_eq!(
+                unshuffle(&mut data, perm),
+                Err(ShuffleError::InvalidPermutation)
+            );
+            assert_eq!(data, [1, 2, 3]);
+        }
+    }
+
+    #[test]
+    fn test_apply_invalid_permutation() {
+        let mut data = [1, 2, 3];
+        for perm in [
+            &[0, 1][..],
+            &[0, 1, 2, 3],
+            &[0, 1, 3],
+            &[2, 0, 2],
+            &[1, 1, 1],
+        ] {
+            assert_eq!(
+                apply_permutation(&mut data, perm),
+                Err(ShuffleError::InvalidPermutation)
+            );
+            assert_eq!(data, [1, 2, 3]);
+        }
+    }
+}
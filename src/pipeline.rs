@@ -0,0 +1,243 @@
+//! Several shuffle passes applied one after the other.
+
+use core::fmt;
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use rand::RngCore;
+
+use crate::shuffler::{DynShuffler, ShuffleError, Shuffler};
+
+/// A sequence of shufflers, possibly of different algorithms, run one
+/// after the other on the same data with the same generator.
+///
+/// This makes composite strategies, e.g. a few rounds of a weak shuffle
+/// followed by a Fisher-Yates pass, a single [`Shuffler`], so they can be
+/// measured like any other. The passes are boxed [`DynShuffler`]s, so any
+/// `Shuffler` fits, including another `Pipeline`.
+///
+/// The first pass that fails stops the pipeline, and its error is
+/// returned with the data in its original order, as for every shuffler.
+/// For that the data is copied before the passes when there is more than
+/// one of them.
+///
+/// Available with the `alloc` feature.
+///
+/// # Examples
+/// ```
+/// use shuffle::fy::FisherYates;
+/// use shuffle::irs::Irs;
+/// use shuffle::pipeline::Pipeline;
+/// use shuffle::shuffler::Shuffler;
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let mut rng = StdRng::seed_from_u64(42);
+/// let mut pipeline = Pipeline::new()
+///     .then(Irs::default())
+///     .then(Irs::default())
+///     .then(Irs::default())
+///     .then(FisherYates::default());
+/// assert_eq!(pipeline.len(), 4);
+///
+/// let mut input: Vec<u32> = (0..100).collect();
+/// pipeline.shuffle(&mut input, &mut rng).unwrap();
+/// input.sort();
+/// assert_eq!(input, (0..100).collect::<Vec<_>>());
+/// ```
+pub struct Pipeline<T> {
+    passes: Vec<Box<dyn DynShuffler<T>>>,
+}
+
+impl<T> Pipeline<T> {
+    /// Creates a pipeline without passes, which leaves the data as it is.
+    pub fn new() -> Self {
+        Self { passes: Vec::new() }
+    }
+
+    /// Appends `shuffler` as the last pass.
+    pub fn then<S>(mut self, shuffler: S) -> Self
+    where
+        S: DynShuffler<T> + 'static,
+    {
+        self.passes.push(Box::new(shuffler));
+        self
+    }
+
+    /// Returns the number of passes.
+    pub fn len(&self) -> usize {
+        self.passes.len()
+    }
+
+    /// Returns `true` if there are no passes.
+    pub fn is_empty(&self) -> bool {
+        self.passes.is_empty()
+    }
+}
+
+// Not derived, as deriving would put the bounds on `T`.
+impl<T> Default for Pipeline<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Not derived, as the passes don't have to implement `Debug`.
+impl<T> fmt::Debug for Pipeline<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pipeline")
+            .field("passes", &self.passes.len())
+            .finish()
+    }
+}
+
+impl<T> Shuffler<T> for Pipeline<T> {
+    fn shuffle_slice<R>(&mut self, data: &mut [T], mut rng: &mut R) -> Result<(), ShuffleError>
+    where
+        T: Clone,
+        R: RngCore + ?Sized,
+    {
+        let original = if self.passes.len() > 1 {
+            Some(data.to_vec())
+        } else {
+            None
+        };
+        for pass in &mut self.passes {
+            if let Err(error) = pass.shuffle_dyn(data, &mut rng) {
+                if let Some(original) = &original {
+                    data.clone_from_slice(original);
+                }
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+
+    fn shuffle_indices<R>(
+        &mut self,
+        len: usize,
+        mut rng: &mut R,
+    ) -> Result<Vec<usize>, ShuffleError>
+    where
+        R: RngCore + ?Sized,
+    {
+        let mut permutation: Vec<usize> = (0..len).collect();
+        for pass in &mut self.passes {
+            let next = pass.shuffle_indices_dyn(len, &mut rng)?;
+            // The pass moves the element at `permutation[j]` to `i`.
+            permutation = next.iter().map(|&j| permutation[j]).collect();
+        }
+        Ok(permutation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fy::FisherYates;
+    use crate::irs::Irs;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    /// Fails the test if it is ever run.
+    struct Unreachable;
+
+    impl<T> Shuffler<T> for Unreachable {
+        fn shuffle_slice<R>(&mut self, _: &mut [T], _: &mut R) -> Result<(), ShuffleError>
+        where
+            T: Clone,
+            R: RngCore + ?Sized,
+        {
+            panic!("the pipeline went on after an error")
+        }
+
+        fn shuffle_indices<R>(&mut self, _: usize, _: &mut R) -> Result<Vec<usize>, ShuffleError>
+        where
+            R: RngCore + ?Sized,
+        {
+            panic!("the pipeline went on after an error")
+        }
+    }
+
+    fn irs_then_fy() -> Pipeline<u32> {
+        Pipeline::new()
+            .then(Irs::default())
+            .then(Irs::default())
+            .then(Irs::default())
+            .then(FisherYates::default())
+    }
+
+    #[test]
+    fn test_same_as_passes_in_sequence() {
+        let input_data: Vec<u32> = (0..200).collect();
+        let mut rng = StdRng::seed_from_u64(60);
+        let mut expected = input_data.clone();
+        for _ in 0..3 {
+            Irs::default().shuffle(&mut expected, &mut rng).unwrap();
+        }
+        FisherYates::default()
+            .shuffle(&mut expected, &mut rng)
+            .unwrap();
+
+        let mut rng = StdRng::seed_from_u64(60);
+        let mut target = input_data.clone();
+        irs_then_fy().shuffle(&mut target, &mut rng).unwrap();
+        assert_eq!(target, expected);
+
+        let mut rng = StdRng::seed_from_u64(60);
+        let permutation = irs_then_fy().shuffle_indices(200, &mut rng).unwrap();
+        let gathered: Vec<u32> = permutation.iter().map(|&i| input_data[i]).collect();
+        assert_eq!(gathered, expected);
+    }
+
+    #[test]
+    fn test_empty_pipeline() {
+        let mut rng = StdRng::seed_from_u64(61);
+        let mut pipeline = Pipeline::default();
+        assert!(pipeline.is_empty());
+        let mut target: Vec<u32> = (0..10).collect();
+        pipeline.shuffle(&mut target, &mut rng).unwrap();
+        assert_eq!(target, (0..10).collect::<Vec<_>>());
+        assert_eq!(
+            Shuffler::<u32>::shuffle_indices(&mut pipeline, 3, &mut rng),
+            Ok(vec![0, 1, 2])
+        );
+    }
+
+    #[test]
+    fn test_first_error_stops_the_pipeline() {
+        let input_data: Vec<u32> = (0..100).collect();
+        let mut rng = StdRng::seed_from_u64(62);
+        let mut pipeline = Pipeline::new()
+            .then(FisherYates::default())
+            .then(Irs::default().with_max_rounds(0))
+            .then(Unreachable);
+
+        let mut target = input_data.clone();
+        assert_eq!(
+            pipeline.shuffle(&mut target, &mut rng),
+            Err(ShuffleError::BadRandomness)
+        );
+        assert_eq!(target, input_data);
+        assert_eq!(
+            pipeline.shuffle_indices(100, &mut rng),
+            Err(ShuffleError::BadRandomness)
+        );
+    }
+
+    #[test]
+    fn test_nested_pipeline() {
+        let input_data: Vec<u32> = (0..50).collect();
+        let mut expected = input_data.clone();
+        irs_then_fy()
+            .shuffle(&mut expected, &mut StdRng::seed_from_u64(63))
+            .unwrap();
+
+        let mut nested = Pipeline::new().then(irs_then_fy());
+        let mut target = input_data.clone();
+        nested
+            .shuffle(&mut target, &mut StdRng::seed_from_u64(63))
+            .unwrap();
+        assert_eq!(target, expected);
+    }
+}
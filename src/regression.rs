@@ -0,0 +1,123 @@
+//! A fingerprint of the output of the shufflers, to catch accidental
+//! changes to the permutations they produce for a given seed.
+//!
+//! If a change to the output is intended, update [`FINGERPRINT`] and
+//! mention it in the release notes, as it breaks reproducibility.
+
+use rand::{Rng, RngCore};
+
+use crate::derangement::Derangement;
+use crate::fy::FisherYates;
+use crate::irs::{BitConsumption, BitOrder, ByteOrder, Irs, IrsBuilder};
+use crate::riffle::RiffleShuffle;
+use crate::sattolo::Sattolo;
+use crate::shuffler::Shuffler;
+use crate::{annealed, casino, derangement, optimal};
+
+/// The fingerprint of the current output for the seed `0`.
+///
+/// Some shufflers draw `usize` indices, so it only holds on 64-bit targets.
+const FINGERPRINT: u64 = 0xa09265d000d01e55;
+
+/// The SplitMix64 generator. Unlike the generators of `rand`, it is
+/// defined here, so its output can't change with a dependency update.
+struct SplitMix64(u64);
+
+impl RngCore for SplitMix64 {
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// The 64-bit FNV-1a hash, fed one permutation at a time.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn write(&mut self, data: &[u32]) {
+        for byte in data.iter().flat_map(|x| x.to_le_bytes()) {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3);
+        }
+        // Keeps the boundaries between the permutations in the hash.
+        self.0 = (self.0 ^ 0xff).wrapping_mul(0x0000_0100_0000_01b3);
+    }
+}
+
+/// Runs a fixed battery of shuffles of various lengths with the
+/// shufflers of the crate, all drawing from a generator seeded with
+/// `seed`, and hashes the results into a single value.
+fn fingerprint(seed: u64) -> u64 {
+    let mut rng = SplitMix64(seed);
+    let mut hash = Fnv1a(0xcbf2_9ce4_8422_2325);
+    let big_endian_msb = BitConsumption {
+        byte_order: ByteOrder::BigEndian,
+        bit_order: BitOrder::Msb0,
+    };
+    for len in [1u32, 2, 5, 52, 1000] {
+        let input: Vec<u32> = (0..len).collect();
+        let mut run = |shuffle: &mut dyn FnMut(&mut Vec<u32>, &mut SplitMix64)| {
+            let mut data = input.clone();
+            shuffle(&mut data, &mut rng);
+            hash.write(&data);
+        };
+
+        run(&mut |data, rng| FisherYates::default().shuffle(data, rng).unwrap());
+        run(&mut |data, rng| FisherYates::default().shuffle_rand_compatible(data, rng));
+        run(&mut |data, rng| Sattolo::default().shuffle(data, rng).unwrap());
+        run(&mut |data, rng| Irs::default().shuffle(data, rng).unwrap());
+        run(&mut |data, rng| {
+            Irs::default()
+                .with_bit_order(BitOrder::Msb0)
+                .shuffle(data, rng)
+                .unwrap()
+        });
+        run(&mut |data, rng| {
+            IrsBuilder::new()
+                .bit_consumption(big_endian_msb)
+                .build()
+                .shuffle(data, rng)
+                .unwrap()
+        });
+        run(&mut |data, rng| Irs::default().shuffle_low_memory(data, 64, rng).unwrap());
+        run(&mut |data, rng| optimal::shuffle(data, || rng.gen()).unwrap());
+        run(&mut |data, rng| casino::standard_procedure(data, rng));
+        run(&mut |data, rng| annealed::shuffle(data, 0.5, rng).unwrap());
+        run(&mut |data, rng| RiffleShuffle::new(3).shuffle(data, rng).unwrap());
+        if len > 1 {
+            run(&mut |data, rng| derangement::shuffle_recursive(data, rng).unwrap());
+            run(&mut |data, rng| Derangement::default().shuffle(data, rng).unwrap());
+        }
+    }
+    hash.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint() {
+        assert_eq!(fingerprint(0), FINGERPRINT);
+        assert_eq!(fingerprint(0), fingerprint(0));
+        assert_ne!(fingerprint(0), fingerprint(1));
+    }
+}
@@ -0,0 +1,268 @@
+//! Implementation of the (forward) Riffle Shuffle.
+//!
+//! This models the way people shuffle a deck of cards: cut it in two
+//! packets and riffle them together, a given number of times. Unlike
+//! [`Irs`](crate::irs::Irs), it doesn't run until the result is uniform,
+//! so it is meant for simulating real card mixing rather than for getting
+//! a random permutation.
+//!
+//! *Bayer, Dave, and Persi Diaconis. "Trailing the dovetail shuffle to its
+//! lair." The Annals of Applied Probability 2.2 (1992): 294-313.*
+
+use rand::{Rng, RngCore};
+
+use crate::permutation::apply_permutation;
+use crate::shuffler::{ShuffleError, Shuffler};
+
+/// The number of passes of [`RiffleShuffle::default`].
+const DEFAULT_PASSES: usize = 7;
+
+/// Implementation of the Riffle Shuffle in the Gilbert-Shannon-Reeds
+/// model.
+///
+/// Every pass cuts the deck after a binomially distributed number of
+/// cards, as if every card had been put on the left or right packet by
+/// a coin flip. Then it drops the cards one at a time from the bottom of
+/// either packet, with a probability proportional to the size of that
+/// packet, which keeps the relative order within each packet.
+///
+/// This is **not** a uniform shuffle for a small number of passes: after
+/// `k` passes the deck consists of at most `2^k` interleaved rising
+/// sequences of the original order (see
+/// [`rising_sequences`](crate::analysis::rising_sequences)). It takes
+/// about `(3/2)·log2(n)` passes, e.g. seven for a 52-card deck, to get
+/// close to uniform. [`Default`] runs seven passes.
+///
+/// # Examples
+/// ```
+/// use shuffle::analysis::rising_sequences;
+/// use shuffle::riffle::RiffleShuffle;
+/// use shuffle::shuffler::Shuffler;
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let mut rng = StdRng::seed_from_u64(42);
+/// let mut riffle = RiffleShuffle::new(1);
+///
+/// let mut deck: Vec<usize> = (0..52).collect();
+/// riffle.shuffle(&mut deck, &mut rng).unwrap();
+/// assert!(rising_sequences(&deck) <= 2);
+/// ```
+#[derive(Debug)]
+pub struct RiffleShuffle {
+    passes: usize,
+    perm: Vec<usize>,
+    scratch: Vec<usize>,
+}
+
+impl Default for RiffleShuffle {
+    fn default() -> Self {
+        Self::new(DEFAULT_PASSES)
+    }
+}
+
+impl RiffleShuffle {
+    /// Creates a shuffler running `passes` riffles per shuffle.
+    pub fn new(passes: usize) -> Self {
+        Self {
+            passes,
+            perm: Vec::new(),
+            scratch: Vec::new(),
+        }
+    }
+
+    /// Returns the number of riffles per shuffle.
+    pub fn passes(&self) -> usize {
+        self.passes
+    }
+
+    /// Computes the permutation of `0..len` into `self.perm`.
+    fn compute_permutation<R>(&mut self, len: usize, rng: &mut R)
+    where
+        R: RngCore + ?Sized,
+    {
+        self.perm.clear();
+        self.perm.extend(0..len);
+        for _ in 0..self.passes {
+            let cut = binomial_half(len, rng);
+            riffle(&self.perm, cut, &mut self.scratch, rng);
+            std::mem::swap(&mut self.perm, &mut self.scratch);
+        }
+    }
+}
+
+impl<T> Shuffler<T> for RiffleShuffle {
+    fn shuffle_slice<R>(&mut self, data: &mut [T], rng: &mut R) -> Result<(), ShuffleError>
+    where
+        T: Clone,
+        R: RngCore + ?Sized,
+    {
+        self.compute_permutation(data.len(), rng);
+        apply_permutation(data, &self.perm)
+    }
+
+    fn shuffle_indices<R>(&mut self, len: usize, rng: &mut R) -> Result<Vec<usize>, ShuffleError>
+    where
+        R: RngCore + ?Sized,
+    {
+        self.compute_permutation(len, rng);
+        Ok(self.perm.clone())
+    }
+
+    /// `k` passes riffle `2^k` packets together, and this is an
+    /// approximation for such a shuffle: `n (2a - 1) / (6a)` with
+    /// `a = 2^k`, which treats the positions as continuous and ignores
+    /// the random spread of the packet sizes. It is `n / 4` for a single
+    /// pass and tends to the uniform value as `k` grows, which caps it.
+    fn expected_mean_displacement(&self, n: usize) -> f64 {
+        if n < 2 || self.passes == 0 {
+            return 0.0;
+        }
+        let n = n as f64;
+        let packets = 2f64.powi(self.passes.min(64) as i32);
+        let uniform = (n * n - 1.0) / (3.0 * n);
+        (n * (2.0 * packets - 1.0) / (6.0 * packets)).min(uniform)
+    }
+}
+
+/// Returns the number of heads in `n` fair coin flips, i.e. draws from
+/// the binomial distribution `B(n, 1/2)`.
+pub(crate) fn binomial_half<R>(n: usize, rng: &mut R) -> usize
+where
+    R: RngCore + ?Sized,
+{
+    let mut heads = 0;
+    let mut remaining = n;
+    while remaining >= 64 {
+        heads += rng.next_u64().count_ones() as usize;
+        remaining -= 64;
+    }
+    if remaining > 0 {
+        let mask = (1u64 << remaining) - 1;
+        heads += (rng.next_u64() & mask).count_ones() as usize;
+    }
+    heads
+}
+
+/// Riffles `deck[..cut]` and `deck[cut..]` together into `out`.
+fn riffle<R>(deck: &[usize], cut: usize, out: &mut Vec<usize>, rng: &mut R)
+where
+    R: RngCore + ?Sized,
+{
+    let (mut left, mut right) = deck.split_at(cut);
+    out.clear();
+    while !left.is_empty() && !right.is_empty() {
+        let packet = if rng.gen_range(0..left.len() + right.len()) < left.len() {
+            &mut left
+        } else {
+            &mut right
+        };
+        out.push(packet[0]);
+        *packet = &packet[1..];
+    }
+    out.extend_from_slice(left);
+    out.extend_from_slice(right);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::rising_sequences;
+    use crate::fy::FisherYates;
+    use crate::testing::{chi_square_critical_value, counts_chi_square, permutation_chi_square};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_rising_sequences_bound() {
+        let mut rng = StdRng::seed_from_u64(42);
+        for passes in 0..6 {
+            let mut riffle = RiffleShuffle::new(passes);
+            for _ in 0..50 {
+                let mut deck: Vec<usize> = (0..52).collect();
+                riffle.shuffle(&mut deck, &mut rng).unwrap();
+                assert!(rising_sequences(&deck) <= 1 << passes);
+
+                let mut sorted = deck.clone();
+                sorted.sort_unstable();
+                assert_eq!(sorted, (0..52).collect::<Vec<_>>());
+            }
+        }
+    }
+
+    #[test]
+    fn test_single_pass_distribution() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut riffle = RiffleShuffle::new(1);
+        let trials = 16_000;
+        let mut counts = HashMap::new();
+        for _ in 0..trials {
+            let perm = Shuffler::<()>::shuffle_indices(&mut riffle, 4, &mut rng).unwrap();
+            *counts.entry(perm).or_insert(0) += 1;
+        }
+        // A single riffle of 4 cards gives one of 2^4 - 4 = 11 non-identity
+        // permutations with probability 1/16 each, or the identity with
+        // probability 5/16.
+        assert_eq!(counts.len(), 12);
+        let identity = counts.remove(&vec![0, 1, 2, 3]).unwrap();
+        assert!((4600..5400).contains(&identity), "count: {}", identity);
+        let counts: Vec<usize> = counts.into_values().collect();
+        assert!(counts_chi_square(&counts) < chi_square_critical_value(10, 0.999));
+    }
+
+    #[test]
+    fn test_many_passes_cover_everything() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut riffle = RiffleShuffle::default();
+        assert_eq!(riffle.passes(), 7);
+        let statistic = permutation_chi_square(&mut riffle, 4, 24_000, &mut rng);
+        assert!(statistic < chi_square_critical_value(23, 0.999));
+    }
+
+    #[test]
+    fn test_expected_mean_displacement() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let uniform = Shuffler::<u32>::expected_mean_displacement(&FisherYates::default(), 52);
+        assert_eq!(
+            Shuffler::<u32>::expected_mean_displacement(&RiffleShuffle::new(0), 52),
+            0.0
+        );
+        assert!(Shuffler::<u32>::expected_mean_displacement(&RiffleShuffle::new(1), 52) < uniform);
+        for passes in 1..4 {
+            let mut riffle = RiffleShuffle::new(passes);
+            let trials = 2000;
+            let mut total = 0.0;
+            for _ in 0..trials {
+                let perm = Shuffler::<()>::shuffle_indices(&mut riffle, 52, &mut rng).unwrap();
+                total += crate::analysis::mean_move_distance(&perm);
+            }
+            let expected = Shuffler::<()>::expected_mean_displacement(&riffle, 52);
+            let average = total / trials as f64;
+            assert!(
+                (average - expected).abs() < 0.1 * expected,
+                "passes: {}, average: {}, expected: {}",
+                passes,
+                average,
+                expected
+            );
+        }
+        let expected = Shuffler::<()>::expected_mean_displacement(&RiffleShuffle::new(100), 52);
+        assert_eq!(expected, uniform);
+    }
+
+    #[test]
+    fn test_no_passes() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut riffle = RiffleShuffle::new(0);
+        let mut target = vec![1, 2, 3, 4, 5];
+        riffle.shuffle(&mut target, &mut rng).unwrap();
+        assert_eq!(target, vec![1, 2, 3, 4, 5]);
+
+        let mut target: Vec<u32> = vec![];
+        RiffleShuffle::default()
+            .shuffle(&mut target, &mut rng)
+            .unwrap();
+        assert!(target.is_empty());
+    }
+}
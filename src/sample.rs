@@ -0,0 +1,189 @@
+//! Sampling from slices and from streams of unknown length.
+
+use std::collections::HashMap;
+
+use rand::Rng;
+
+use crate::shuffler::ShuffleError;
+
+/// Returns `k` distinct elements of `data` in a random order, leaving
+/// `data` as it is.
+///
+/// Every ordered selection of `k` of the elements is equally likely, so
+/// e.g. the first element of the sample is the winner of a draw among all
+/// of `data`, the second one among the rest and so on. This runs `k`
+/// steps of [`FisherYates::partial_shuffle`](crate::fy::FisherYates::partial_shuffle)
+/// over the indices of `data`, giving the same sample for the same `rng`,
+/// but only keeps the indices it moved in a map, so it takes `O(k)` time
+/// and memory whatever the length of `data`.
+///
+/// Returns an error if `k` is greater than the length of `data`.
+///
+/// # Examples
+/// ```
+/// use shuffle::sample::sample_k_ordered;
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let mut rng = StdRng::seed_from_u64(42);
+/// let entrants: Vec<String> = (0..10_000).map(|i| format!("entrant {}", i)).collect();
+///
+/// let podium = sample_k_ordered(&entrants, 3, &mut rng).unwrap();
+/// assert_eq!(podium.len(), 3);
+/// assert!(podium[0] != podium[1] && podium[1] != podium[2] && podium[0] != podium[2]);
+///
+/// assert!(sample_k_ordered(&entrants[..2], 3, &mut rng).is_err());
+/// ```
+pub fn sample_k_ordered<T, R>(data: &[T], k: usize, rng: &mut R) -> Result<Vec<T>, ShuffleError>
+where
+    T: Clone,
+    R: Rng + ?Sized,
+{
+    if k > data.len() {
+        return Err(ShuffleError::OutOfBounds);
+    }
+    // The positions of the virtual array of indices that no longer hold
+    // their own index.
+    let mut moved: HashMap<usize, usize> = HashMap::with_capacity(k);
+    let sample = (0..k)
+        .map(|i| {
+            let j = rng.gen_range(i..data.len());
+            let picked = moved.get(&j).copied().unwrap_or(j);
+            let displaced = moved.get(&i).copied().unwrap_or(i);
+            moved.insert(j, displaced);
+            data[picked].clone()
+        })
+        .collect();
+    Ok(sample)
+}
+
+/// Returns a uniformly random sample of `k` of the items of `iter`,
+/// consuming it but keeping only `k` items in memory at a time.
+///
+/// This is Algorithm R: the first `k` items fill the reservoir, and the
+/// `i`-th item after them (counting from `0`) replaces a random one with
+/// probability `k / (k + i + 1)`. Every `k`-subset of the items is then
+/// equally likely to be the sample. It draws a single `gen_range` per
+/// item past the first `k`, with integers only, so the probabilities are
+/// exact.
+///
+/// If `iter` yields fewer than `k` items, all of them are returned, in
+/// their original order. Otherwise the order of the sample is not
+/// uniformly random, e.g. the earliest items that stay in the reservoir
+/// keep their positions; shuffle it if that matters.
+///
+/// # Examples
+/// ```
+/// use shuffle::sample::reservoir_sample;
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let mut rng = StdRng::seed_from_u64(42);
+/// let lines = (0..1000).map(|i| format!("line {}", i));
+///
+/// let sample = reservoir_sample(lines, 5, &mut rng);
+/// assert_eq!(sample.len(), 5);
+///
+/// assert_eq!(reservoir_sample(0..3, 5, &mut rng), vec![0, 1, 2]);
+/// ```
+pub fn reservoir_sample<T, I, R>(iter: I, k: usize, rng: &mut R) -> Vec<T>
+where
+    I: IntoIterator<Item = T>,
+    R: Rng + ?Sized,
+{
+    let mut iter = iter.into_iter();
+    let mut reservoir: Vec<T> = iter.by_ref().take(k).collect();
+    if reservoir.len() < k {
+        return reservoir;
+    }
+    for (seen, item) in (k..).zip(iter) {
+        // `seen` is the number of items before this one.
+        let j = rng.gen_range(0..=seen);
+        if j < k {
+            reservoir[j] = item;
+        }
+    }
+    reservoir
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fy::FisherYates;
+    use crate::testing::{chi_square_critical_value, counts_chi_square};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_sample_k_ordered() {
+        let mut rng = StdRng::seed_from_u64(44);
+        let data: Vec<u32> = (0..100).collect();
+        assert!(sample_k_ordered(&data, 0, &mut rng).unwrap().is_empty());
+        assert!(sample_k_ordered::<u32, _>(&[], 0, &mut rng)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            sample_k_ordered(&data, 101, &mut rng),
+            Err(ShuffleError::OutOfBounds)
+        );
+
+        for k in [1, 10, 99, 100] {
+            let sample = sample_k_ordered(&data, k, &mut StdRng::seed_from_u64(k as u64)).unwrap();
+            // The same as the head of a partial shuffle.
+            let mut expected = data.clone();
+            let (head, _) = FisherYates::default().partial_shuffle(
+                &mut expected,
+                k,
+                &mut StdRng::seed_from_u64(k as u64),
+            );
+            assert_eq!(sample, head);
+        }
+        assert_eq!(data, (0..100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_sample_k_ordered_is_uniform() {
+        let mut rng = StdRng::seed_from_u64(44);
+        let trials = 24_000;
+        let mut counts: HashMap<Vec<u32>, usize> = HashMap::new();
+        for _ in 0..trials {
+            let sample = sample_k_ordered(&[0, 1, 2, 3], 2, &mut rng).unwrap();
+            *counts.entry(sample).or_insert(0) += 1;
+        }
+        // 4 * 3 ordered pairs.
+        assert_eq!(counts.len(), 12);
+        let counts: Vec<usize> = counts.into_values().collect();
+        assert!(counts_chi_square(&counts) < chi_square_critical_value(11, 0.999));
+    }
+
+    #[test]
+    fn test_reservoir_sample() {
+        let mut rng = StdRng::seed_from_u64(43);
+        assert!(reservoir_sample(0..100, 0, &mut rng).is_empty());
+        assert!(reservoir_sample(std::iter::empty::<u32>(), 3, &mut rng).is_empty());
+        assert_eq!(reservoir_sample(0..4, 4, &mut rng), vec![0, 1, 2, 3]);
+
+        let sample = reservoir_sample(0..10_000, 50, &mut rng);
+        assert_eq!(sample.len(), 50);
+        let mut sorted = sample.clone();
+        sorted.sort_unstable();
+        sorted.dedup();
+        assert_eq!(sorted.len(), 50);
+        assert!(sorted.iter().all(|&x| x < 10_000));
+        assert_ne!(sample, reservoir_sample(0..10_000, 50, &mut rng));
+    }
+
+    #[test]
+    fn test_reservoir_sample_is_uniform() {
+        let mut rng = StdRng::seed_from_u64(43);
+        let trials = 20_000;
+        let mut counts = [0usize; 10];
+        for _ in 0..trials {
+            for x in reservoir_sample(0..10, 3, &mut rng) {
+                counts[x] += 1;
+            }
+        }
+        // Every item lands in the sample with probability 3 / 10.
+        assert!(counts_chi_square(&counts) < chi_square_critical_value(9, 0.999));
+    }
+}
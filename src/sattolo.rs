@@ -0,0 +1,153 @@
+//! Implementation of Sattolo's algorithm.
+
+use rand::{Rng, RngCore};
+
+use crate::shuffler::{ShuffleError, Shuffler};
+
+/// Implementation of Sattolo's algorithm, which produces a uniformly
+/// random cyclic permutation.
+///
+/// The algorithm is Fisher-Yates, except that the element at position `i`
+/// is always swapped with one strictly before it. The result is one of
+/// the `(n - 1)!` permutations that consist of a single cycle through all
+/// the `n` elements, each with the same probability. So for `n >= 2` no
+/// element stays in place, and following the positions from any element
+/// visits all of them before coming back.
+///
+/// This is **not** a uniform shuffle: most permutations, e.g. all the
+/// ones with a fixed point, never come out. Use
+/// [`FisherYates`](crate::fy::FisherYates) for that.
+///
+/// # Examples
+/// ```
+/// use shuffle::shuffler::Shuffler;
+/// use shuffle::sattolo::Sattolo;
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let mut rng = StdRng::seed_from_u64(42);
+/// let mut sattolo = Sattolo::default();
+///
+/// let mut input = vec![0, 1, 2, 3, 4];
+/// sattolo.shuffle(&mut input, &mut rng).unwrap();
+/// for (i, &x) in input.iter().enumerate() {
+///     assert_ne!(i, x);
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct Sattolo {}
+
+impl<T> Shuffler<T> for Sattolo {
+    fn shuffle_slice<R>(&mut self, data: &mut [T], rng: &mut R) -> Result<(), ShuffleError>
+    where
+        T: Clone,
+        R: RngCore + ?Sized,
+    {
+        sattolo(data, rng);
+        Ok(())
+    }
+
+    fn shuffle_indices<R>(&mut self, len: usize, rng: &mut R) -> Result<Vec<usize>, ShuffleError>
+    where
+        R: RngCore + ?Sized,
+    {
+        let mut permutation: Vec<usize> = (0..len).collect();
+        sattolo(&mut permutation, rng);
+        Ok(permutation)
+    }
+
+    /// Every element moves to a uniformly random other position, so
+    /// this is `(n + 1) / 3`, a bit more than for a uniform shuffle.
+    fn expected_mean_displacement(&self, n: usize) -> f64 {
+        if n < 2 {
+            return 0.0;
+        }
+        (n + 1) as f64 / 3.0
+    }
+}
+
+fn sattolo<T, R>(data: &mut [T], rng: &mut R)
+where
+    R: Rng + ?Sized,
+{
+    for i in (1..data.len()).rev() {
+        let j = rng.gen_range(0..i);
+        data.swap(i, j);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{chi_square_critical_value, counts_chi_square};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::collections::HashMap;
+
+    /// Returns the length of the cycle of `perm` that contains `0`.
+    fn cycle_length(perm: &[usize]) -> usize {
+        let mut length = 1;
+        let mut position = perm[0];
+        while position != 0 {
+            position = perm[position];
+            length += 1;
+        }
+        length
+    }
+
+    #[test]
+    fn test_single_cycle() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut sattolo = Sattolo::default();
+        for n in [2, 3, 10, 1000] {
+            for _ in 0..20 {
+                let perm = Shuffler::<()>::shuffle_indices(&mut sattolo, n, &mut rng).unwrap();
+                assert!(perm.iter().enumerate().all(|(i, &p)| i != p));
+                assert_eq!(cycle_length(&perm), n);
+
+                let mut target: Vec<usize> = (0..n).collect();
+                sattolo.shuffle(&mut target, &mut rng).unwrap();
+                assert_eq!(cycle_length(&target), n);
+            }
+        }
+
+        let mut target: Vec<u32> = vec![];
+        sattolo.shuffle(&mut target, &mut rng).unwrap();
+        let mut target = vec![7];
+        sattolo.shuffle(&mut target, &mut rng).unwrap();
+        assert_eq!(target, vec![7]);
+    }
+
+    #[test]
+    fn test_expected_mean_displacement() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut sattolo = Sattolo::default();
+        let trials = 2000;
+        let mut total = 0.0;
+        for _ in 0..trials {
+            let perm = Shuffler::<()>::shuffle_indices(&mut sattolo, 20, &mut rng).unwrap();
+            total += crate::analysis::mean_move_distance(&perm);
+        }
+        let expected = Shuffler::<()>::expected_mean_displacement(&sattolo, 20);
+        assert_eq!(expected, 7.0);
+        assert!((total / trials as f64 - expected).abs() < 0.1);
+        assert_eq!(Shuffler::<()>::expected_mean_displacement(&sattolo, 1), 0.0);
+    }
+
+    #[test]
+    fn test_uniform_over_cycles() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut sattolo = Sattolo::default();
+        let trials = 12_000;
+        let mut counts = HashMap::new();
+        for _ in 0..trials {
+            let mut target = vec![0, 1, 2, 3];
+            sattolo.shuffle(&mut target, &mut rng).unwrap();
+            *counts.entry(target).or_insert(0) += 1;
+        }
+        // There are 3! = 6 cycles through 4 elements.
+        assert_eq!(counts.len(), 6);
+        let counts: Vec<usize> = counts.into_values().collect();
+        assert!(counts_chi_square(&counts) < chi_square_critical_value(5, 0.999));
+    }
+}
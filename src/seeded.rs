@@ -0,0 +1,104 @@
+//! Shuffling with nothing but a `u64` seed.
+//!
+//! All the functions here use the same generator, `ChaCha8Rng` from
+//! `rand_chacha`, seeded with [`SeedableRng::seed_from_u64`]. Both the
+//! stream of `ChaCha8Rng` and the way `seed_from_u64` expands the seed are
+//! fixed by `rand_chacha` and `rand_core`, unlike `StdRng`, which may
+//! change to another algorithm in any release of `rand`. So the random
+//! bytes for a given seed are the same everywhere, and with a shuffler
+//! whose output only depends on those bytes, such as
+//! [`Irs`](crate::irs::Irs), so is the permutation.
+//!
+//! Available with the `seeded` feature.
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+#[cfg(feature = "alloc")]
+use crate::shuffler::{ShuffleError, Shuffler};
+
+/// Returns the generator the functions of this module use for `seed`.
+///
+/// # Examples
+/// ```
+/// use shuffle::seeded::rng_from_seed;
+/// use rand::RngCore;
+///
+/// assert_eq!(rng_from_seed(7).next_u64(), rng_from_seed(7).next_u64());
+/// ```
+pub fn rng_from_seed(seed: u64) -> ChaCha8Rng {
+    ChaCha8Rng::seed_from_u64(seed)
+}
+
+/// Shuffles `data` with `shuffler`, drawing the randomness from the
+/// generator returned by [`rng_from_seed`].
+///
+/// The same seed always gives the same order, which makes it easy to
+/// reproduce a shuffle from a bug report.
+///
+/// Available with the `seeded` and `alloc` features.
+///
+/// # Examples
+/// ```
+/// use shuffle::irs::Irs;
+/// use shuffle::seeded::shuffle_seeded;
+///
+/// let mut irs = Irs::default();
+///
+/// let mut first = vec![1, 2, 3, 4, 5];
+/// let mut second = vec![1, 2, 3, 4, 5];
+/// shuffle_seeded(&mut irs, &mut first, 7).unwrap();
+/// shuffle_seeded(&mut irs, &mut second, 7).unwrap();
+/// assert_eq!(first, second);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn shuffle_seeded<T, S>(shuffler: &mut S, data: &mut [T], seed: u64) -> Result<(), ShuffleError>
+where
+    T: Clone,
+    S: Shuffler<T> + ?Sized,
+{
+    shuffler.shuffle_slice(data, &mut rng_from_seed(seed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fy::FisherYates;
+    use crate::irs::Irs;
+    use rand::RngCore;
+
+    #[test]
+    fn test_generator_is_pinned() {
+        // The first output of `ChaCha8Rng::seed_from_u64(0)`; if this
+        // changes, so do all the seeded permutations.
+        assert_eq!(rng_from_seed(0).next_u64(), 0xb585_f767_a79a_3b6c);
+    }
+
+    #[test]
+    fn test_same_seed_same_order() {
+        let mut irs = Irs::default();
+        let mut first: Vec<u32> = (0..100).collect();
+        shuffle_seeded(&mut irs, &mut first, 42).unwrap();
+        let mut second: Vec<u32> = (0..100).collect();
+        shuffle_seeded(&mut irs, &mut second, 42).unwrap();
+        assert_eq!(first, second);
+
+        let mut other: Vec<u32> = (0..100).collect();
+        shuffle_seeded(&mut irs, &mut other, 43).unwrap();
+        assert_ne!(first, other);
+
+        let mut irs_output: Vec<u32> = (0..5).collect();
+        shuffle_seeded(&mut irs, &mut irs_output, 7).unwrap();
+        assert_eq!(irs_output, vec![0, 2, 1, 4, 3]);
+    }
+
+    #[test]
+    fn test_matches_fy_seeded_in_place() {
+        let mut fy = FisherYates::default();
+        let mut expected: Vec<u32> = (0..50).collect();
+        fy.shuffle_seeded_in_place(&mut expected, 9);
+        let mut target: Vec<u32> = (0..50).collect();
+        shuffle_seeded(&mut fy, &mut target, 9).unwrap();
+        assert_eq!(target, expected);
+    }
+}
@@ -0,0 +1,120 @@
+//! Choosing the shuffling algorithm at compile time.
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use rand::RngCore;
+
+use crate::fy::FisherYates;
+use crate::irs::Irs;
+use crate::shuffler::{ShuffleError, Shuffler};
+
+/// A marker type naming a shuffling algorithm, for use with [`Shuffle`].
+pub trait Algorithm {
+    /// The shuffler that implements the algorithm for elements of type `T`.
+    type Shuffler<T>: Shuffler<T> + Default;
+}
+
+/// The marker of [`FisherYates`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct FisherYatesMarker;
+
+impl Algorithm for FisherYatesMarker {
+    type Shuffler<T> = FisherYates;
+}
+
+/// The marker of [`Irs`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct IrsMarker;
+
+impl Algorithm for IrsMarker {
+    type Shuffler<T> = Irs<T>;
+}
+
+/// A shuffle with the algorithm given by the marker type `A`.
+///
+/// The algorithm is part of the type, so generic code can be written
+/// once over `Shuffle<A>` and is compiled separately for every algorithm,
+/// without any dynamic dispatch. Every [`run`](Shuffle::run) creates
+/// a fresh default shuffler of the algorithm; keep a shuffler around
+/// instead where reusing its buffers matters.
+///
+/// # Examples
+/// ```
+/// use shuffle::select::{Algorithm, IrsMarker, Shuffle};
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// fn deal<A: Algorithm>(deck: &mut [u32]) {
+///     let mut rng = StdRng::seed_from_u64(42);
+///     Shuffle::<A>::default().run(deck, &mut rng).unwrap();
+/// }
+///
+/// let mut deck: Vec<u32> = (0..52).collect();
+/// deal::<IrsMarker>(&mut deck);
+/// deck.sort();
+/// assert_eq!(deck, (0..52).collect::<Vec<_>>());
+/// ```
+pub struct Shuffle<A> {
+    algorithm: PhantomData<A>,
+}
+
+// Not derived, as deriving would put the bounds on `A`.
+impl<A> Default for Shuffle<A> {
+    fn default() -> Self {
+        Self {
+            algorithm: PhantomData,
+        }
+    }
+}
+
+impl<A> fmt::Debug for Shuffle<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Shuffle").finish()
+    }
+}
+
+impl<A: Algorithm> Shuffle<A> {
+    /// Shuffles `data` in place with the algorithm `A`, like
+    /// [`shuffle_slice`](Shuffler::shuffle_slice) of its shuffler.
+    pub fn run<T, R>(&self, data: &mut [T], rng: &mut R) -> Result<(), ShuffleError>
+    where
+        T: Clone,
+        R: RngCore + ?Sized,
+    {
+        A::Shuffler::<T>::default().shuffle_slice(data, rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_dispatch() {
+        let input_data: Vec<u32> = (0..100).collect();
+
+        let mut target = input_data.clone();
+        Shuffle::<IrsMarker>::default()
+            .run(&mut target, &mut StdRng::seed_from_u64(41))
+            .unwrap();
+        let mut irs_target = input_data.clone();
+        Irs::default()
+            .shuffle(&mut irs_target, &mut StdRng::seed_from_u64(41))
+            .unwrap();
+        assert_eq!(target, irs_target);
+
+        let mut target = input_data.clone();
+        Shuffle::<FisherYatesMarker>::default()
+            .run(&mut target, &mut StdRng::seed_from_u64(41))
+            .unwrap();
+        let mut fy_target = input_data;
+        FisherYates::default()
+            .shuffle(&mut fy_target, &mut StdRng::seed_from_u64(41))
+            .unwrap();
+        assert_eq!(target, fy_target);
+        assert_ne!(target, irs_target);
+    }
+}
@@ -1,14 +1,734 @@
 //! The `Shuffler` trait.
 
+use core::fmt;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+#[cfg(feature = "alloc")]
 use rand::RngCore;
 
+/// The errors returned by the shufflers and the other functions of
+/// this crate.
+///
+/// New variants may be added in the future, so matching on it needs
+/// a wildcard arm.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum ShuffleError {
+    /// The randomness source didn't produce a permutation, e.g. because
+    /// it kept returning the same bits.
+    BadRandomness,
+    /// The output of the randomness source looks degenerate. Only
+    /// returned with the `strict` feature.
+    DegenerateRandomness,
+    /// The given random bytes ran out before the shuffle was done.
+    NotEnoughRandomBytes,
+    /// A slice that should be a permutation of `0..n` isn't one.
+    InvalidPermutation,
+    /// Inputs that should have the same length don't.
+    LengthMismatch,
+    /// An index, range or count reaches past the end of the data.
+    OutOfBounds,
+    /// An argument is outside of its allowed values, as described.
+    InvalidArgument(&'static str),
+    /// No arrangement of the data satisfies the requirements, for the
+    /// reason described.
+    Infeasible(&'static str),
+    /// A shuffle lost, duplicated or altered elements of the data.
+    VerificationFailed,
+    /// The shuffle was cancelled by the caller.
+    Cancelled,
+    /// A dependency graph that should be acyclic has a cycle.
+    Cyclic,
+}
+
+impl fmt::Display for ShuffleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShuffleError::BadRandomness => f.write_str("bad randomness source"),
+            ShuffleError::DegenerateRandomness => f.write_str("degenerate randomness source"),
+            ShuffleError::NotEnoughRandomBytes => f.write_str("not enough random bytes"),
+            ShuffleError::InvalidPermutation => f.write_str("invalid permutation"),
+            ShuffleError::LengthMismatch => f.write_str("lengths do not match"),
+            ShuffleError::OutOfBounds => f.write_str("out of bounds"),
+            ShuffleError::InvalidArgument(reason) | ShuffleError::Infeasible(reason) => {
+                f.write_str(reason)
+            }
+            ShuffleError::VerificationFailed => {
+                f.write_str("shuffled data does not match the input")
+            }
+            ShuffleError::Cancelled => f.write_str("shuffle cancelled"),
+            ShuffleError::Cyclic => f.write_str("dependency graph has a cycle"),
+        }
+    }
+}
+
+impl core::error::Error for ShuffleError {}
+
+/// Describes what a shuffle did to the data.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ShuffleOutcome {
+    /// The data had at least two elements and was shuffled.
+    Shuffled,
+    /// The data had at most one element, so there was nothing to shuffle.
+    Trivial,
+}
+
 /// A trait defining `Shuffler` objects that can be used for shuffling data
 /// in various manners
+///
+/// All the methods take the randomness source as `R: RngCore + ?Sized`,
+/// and the implementations use exactly that bound, so any generator
+/// works, including a `&mut dyn RngCore`. Implementations that need
+/// random numbers in a range can still use the methods of [`rand::Rng`],
+/// which is implemented for every `RngCore`.
+///
+/// # Errors
+///
+/// When a shuffle fails, the implementations in this crate leave the data
+/// in its original order, never partially shuffled: the elements are only
+/// rearranged once the whole permutation is known. Implementations of
+/// other crates should do the same. The methods that can fail are marked
+/// `#[must_use]`, so the error can't be dropped by accident.
+///
+/// Available with the `alloc` feature.
+#[cfg(feature = "alloc")]
 pub trait Shuffler<T> {
+    /// Shuffle the passed slice in-place using randomness from the provided
+    /// `RngCore`.
+    ///
+    /// Any slice works, e.g. a part of a larger buffer or an array on the
+    /// stack, so no `Vec` is needed.
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::shuffler::Shuffler;
+    /// use shuffle::fy::FisherYates;
+    /// use rand::rngs::mock::StepRng;
+    ///
+    /// let mut rng = StepRng::new(2, 13);
+    /// let mut fy = FisherYates::default();
+    ///
+    /// let mut input = [1, 2, 3, 4, 5, 6];
+    /// fy.shuffle_slice(&mut input[1..], &mut rng).unwrap();
+    /// assert_eq!(&input, &[1, 3, 4, 5, 6, 2]);
+    /// ```
+    #[must_use = "on error the data is left unshuffled"]
+    fn shuffle_slice<R>(&mut self, data: &mut [T], rng: &mut R) -> Result<(), ShuffleError>
+    where
+        T: Clone,
+        R: RngCore + ?Sized;
+
     /// Shuffle the passed data in-place using randomness from the provided
     /// `RngCore`.
-    fn shuffle<R>(&mut self, data: &mut Vec<T>, rng: &mut R) -> Result<(), &str>
+    ///
+    /// This is the same as [`shuffle_slice`](Shuffler::shuffle_slice).
+    #[must_use = "on error the data is left unshuffled"]
+    fn shuffle<R>(&mut self, data: &mut Vec<T>, rng: &mut R) -> Result<(), ShuffleError>
     where
         T: Clone,
+        R: RngCore + ?Sized,
+    {
+        self.shuffle_slice(data, rng)
+    }
+
+    /// Return a shuffled copy of `data`, leaving `data` itself as it is.
+    ///
+    /// This clones `data` into a new `Vec` and shuffles that with
+    /// [`shuffle`](Shuffler::shuffle), so the result is the same as
+    /// shuffling a clone with the same `rng`.
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::shuffler::Shuffler;
+    /// use shuffle::fy::FisherYates;
+    /// use rand::rngs::mock::StepRng;
+    ///
+    /// let mut rng = StepRng::new(2, 13);
+    /// let mut fy = FisherYates::default();
+    ///
+    /// let input = [1, 2, 3, 4, 5, 6];
+    /// let shuffled = fy.shuffle_cloned(&input, &mut rng).unwrap();
+    /// assert_eq!(input, [1, 2, 3, 4, 5, 6]);
+    /// assert_eq!(shuffled, vec![2, 3, 4, 5, 6, 1]);
+    /// ```
+    fn shuffle_cloned<R>(&mut self, data: &[T], rng: &mut R) -> Result<Vec<T>, ShuffleError>
+    where
+        T: Clone,
+        R: RngCore + ?Sized,
+    {
+        let mut shuffled = data.to_vec();
+        self.shuffle(&mut shuffled, rng)?;
+        Ok(shuffled)
+    }
+
+    /// Compute a random permutation of `0..len` without touching any data.
+    ///
+    /// Gathering `data` by the result, i.e. putting `data[permutation[i]]`
+    /// at position `i`, gives the same order as [`shuffle`](Shuffler::shuffle)
+    /// would for the same `rng`. It does not depend on `T`, so it works for
+    /// elements of any type.
+    fn shuffle_indices<R>(&mut self, len: usize, rng: &mut R) -> Result<Vec<usize>, ShuffleError>
+    where
         R: RngCore + ?Sized;
+
+    /// Shuffle the passed data like [`shuffle`](Shuffler::shuffle),
+    /// additionally telling whether there was anything to shuffle.
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::shuffler::{ShuffleOutcome, Shuffler};
+    /// use shuffle::fy::FisherYates;
+    /// use rand::rngs::mock::StepRng;
+    ///
+    /// let mut rng = StepRng::new(2, 13);
+    /// let mut fy = FisherYates::default();
+    ///
+    /// let mut input = vec![1];
+    /// assert_eq!(fy.shuffle_reporting(&mut input, &mut rng), Ok(ShuffleOutcome::Trivial));
+    /// ```
+    fn shuffle_reporting<R>(
+        &mut self,
+        data: &mut Vec<T>,
+        rng: &mut R,
+    ) -> Result<ShuffleOutcome, ShuffleError>
+    where
+        T: Clone,
+        R: RngCore + ?Sized,
+    {
+        let outcome = if data.len() <= 1 {
+            ShuffleOutcome::Trivial
+        } else {
+            ShuffleOutcome::Shuffled
+        };
+        self.shuffle(data, rng)?;
+        Ok(outcome)
+    }
+
+    /// Shuffle everything but the first `keep` elements of `data`,
+    /// which are left untouched.
+    ///
+    /// Returns an error if `keep` is larger than the length of `data`.
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::shuffler::Shuffler;
+    /// use shuffle::fy::FisherYates;
+    /// use rand::rngs::mock::StepRng;
+    ///
+    /// let mut rng = StepRng::new(2, 13);
+    /// let mut fy = FisherYates::default();
+    ///
+    /// let mut input = vec![1, 2, 3, 4, 5, 6];
+    ///
+    /// fy.shuffle_tail(&mut input, 2, &mut rng).unwrap();
+    /// assert_eq!(&input[..2], &[1, 2]);
+    /// ```
+    fn shuffle_tail<R>(
+        &mut self,
+        data: &mut [T],
+        keep: usize,
+        rng: &mut R,
+    ) -> Result<(), ShuffleError>
+    where
+        T: Clone,
+        R: RngCore + ?Sized,
+    {
+        if keep > data.len() {
+            return Err(ShuffleError::OutOfBounds);
+        }
+        self.shuffle_slice(&mut data[keep..], rng)
+    }
+
+    /// Sort `data`, putting elements that compare equal in a random order.
+    ///
+    /// This is done by shuffling the data and then stable-sorting it,
+    /// so the relative order of equal elements is the one given by the
+    /// shuffle. Note that the stable sort allocates a buffer of up to
+    /// half the length of `data`.
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::shuffler::Shuffler;
+    /// use shuffle::fy::FisherYates;
+    /// use rand::rngs::mock::StepRng;
+    ///
+    /// let mut rng = StepRng::new(2, 13);
+    /// let mut fy = FisherYates::default();
+    ///
+    /// let mut input = vec![3, 1, 2, 1, 3];
+    /// fy.shuffle_within_equal(&mut input, &mut rng).unwrap();
+    /// assert_eq!(input, vec![1, 1, 2, 3, 3]);
+    /// ```
+    fn shuffle_within_equal<R>(
+        &mut self,
+        data: &mut Vec<T>,
+        rng: &mut R,
+    ) -> Result<(), ShuffleError>
+    where
+        T: Clone + Ord,
+        R: RngCore + ?Sized,
+    {
+        self.shuffle(data, rng)?;
+        data.sort();
+        Ok(())
+    }
+
+    /// Return a shuffled copy of `data` together with the permutation
+    /// that was applied to it.
+    ///
+    /// The permutation is such that `shuffled[i] == data[permutation[i]]`,
+    /// which makes it easy to map results computed on the shuffled data
+    /// back to the original elements.
+    ///
+    /// The permutation comes from [`shuffle_indices`](Shuffler::shuffle_indices),
+    /// so the data itself is only cloned once, into its final position.
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::shuffler::Shuffler;
+    /// use shuffle::fy::FisherYates;
+    /// use rand::rngs::mock::StepRng;
+    ///
+    /// let mut rng = StepRng::new(2, 13);
+    /// let mut fy = FisherYates::default();
+    ///
+    /// let input = vec!['a', 'b', 'c', 'd'];
+    /// let (shuffled, permutation) = fy.shuffle_tracked_owned(&input, &mut rng).unwrap();
+    /// for (i, &source) in permutation.iter().enumerate() {
+    ///     assert_eq!(shuffled[i], input[source]);
+    /// }
+    /// ```
+    fn shuffle_tracked_owned<R>(
+        &mut self,
+        data: &[T],
+        rng: &mut R,
+    ) -> Result<(Vec<T>, Vec<usize>), ShuffleError>
+    where
+        T: Clone,
+        R: RngCore + ?Sized,
+    {
+        let permutation = self.shuffle_indices(data.len(), rng)?;
+        let shuffled = permutation.iter().map(|&i| data[i].clone()).collect();
+        Ok((shuffled, permutation))
+    }
+
+    /// Return the expected [`mean_move_distance`](crate::analysis::mean_move_distance)
+    /// of a shuffle of `n` elements, i.e. how far an element moves on
+    /// average.
+    ///
+    /// The default is the value for a uniformly random permutation,
+    /// `(n * n - 1) / (3 * n)`, which is right for the uniform shufflers.
+    /// Shufflers with a different distribution override it.
+    ///
+    /// # Examples
+    /// ```
+    /// use shuffle::shuffler::Shuffler;
+    /// use shuffle::fy::FisherYates;
+    ///
+    /// let fy = FisherYates::default();
+    /// assert_eq!(Shuffler::<u32>::expected_mean_displacement(&fy, 52), 17.326923076923077);
+    /// ```
+    fn expected_mean_displacement(&self, n: usize) -> f64 {
+        if n == 0 {
+            return 0.0;
+        }
+        let n = n as f64;
+        (n * n - 1.0) / (3.0 * n)
+    }
+}
+
+/// An object-safe version of [`Shuffler`], to pick the algorithm at
+/// runtime through a `dyn DynShuffler<T>`.
+///
+/// The methods of `Shuffler` are generic over the randomness source,
+/// which makes `dyn Shuffler<T>` impossible. These take a `dyn RngCore`
+/// instead, and every `Shuffler` implements them by forwarding to its own
+/// methods, so nothing has to be implemented by hand.
+///
+/// Available with the `alloc` feature.
+///
+/// # Examples
+/// ```
+/// use shuffle::shuffler::DynShuffler;
+/// use shuffle::fy::FisherYates;
+/// use shuffle::irs::Irs;
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let mut rng = StdRng::seed_from_u64(42);
+/// let use_irs = true;
+/// let mut shuffler: Box<dyn DynShuffler<u32>> = if use_irs {
+///     Box::new(Irs::default())
+/// } else {
+///     Box::new(FisherYates::default())
+/// };
+///
+/// let mut input = vec![1, 2, 3, 4, 5];
+/// shuffler.shuffle_dyn(&mut input, &mut rng).unwrap();
+/// input.sort();
+/// assert_eq!(input, vec![1, 2, 3, 4, 5]);
+/// ```
+#[cfg(feature = "alloc")]
+pub trait DynShuffler<T> {
+    /// Shuffle `data` in place, like [`Shuffler::shuffle_slice`].
+    fn shuffle_dyn(&mut self, data: &mut [T], rng: &mut dyn RngCore) -> Result<(), ShuffleError>;
+
+    /// Compute a random permutation of `0..len`, like
+    /// [`Shuffler::shuffle_indices`].
+    fn shuffle_indices_dyn(
+        &mut self,
+        len: usize,
+        rng: &mut dyn RngCore,
+    ) -> Result<Vec<usize>, ShuffleError>;
+}
+
+#[cfg(feature = "alloc")]
+impl<T, S> DynShuffler<T> for S
+where
+    T: Clone,
+    S: Shuffler<T>,
+{
+    fn shuffle_dyn(&mut self, data: &mut [T], rng: &mut dyn RngCore) -> Result<(), ShuffleError> {
+        self.shuffle_slice(data, rng)
+    }
+
+    fn shuffle_indices_dyn(
+        &mut self,
+        len: usize,
+        rng: &mut dyn RngCore,
+    ) -> Result<Vec<usize>, ShuffleError> {
+        self.shuffle_indices(len, rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fy::FisherYates;
+    use crate::irs::Irs;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_shuffle_error() {
+        assert_eq!(
+            ShuffleError::BadRandomness.to_string(),
+            "bad randomness source"
+        );
+        assert_eq!(ShuffleError::Infeasible("no way").to_string(), "no way");
+        assert_eq!(
+            ShuffleError::Cyclic.to_string(),
+            "dependency graph has a cycle"
+        );
+        let error: Box<dyn core::error::Error> = Box::new(ShuffleError::LengthMismatch);
+        assert_eq!(error.to_string(), "lengths do not match");
+    }
+
+    #[test]
+    fn test_errors_leave_data_unchanged() {
+        let input_data: Vec<u32> = (0..1000).collect();
+        let mut rng = rand::rngs::mock::StepRng::new(0, 0);
+        let mut irs = Irs::default();
+
+        let mut target = input_data.clone();
+        assert!(irs.shuffle(&mut target, &mut rng).is_err());
+        assert_eq!(target, input_data);
+        assert!(irs.shuffle_slice(&mut target[100..], &mut rng).is_err());
+        assert_eq!(target, input_data);
+        assert!(irs.shuffle_tail(&mut target, 10, &mut rng).is_err());
+        assert_eq!(target, input_data);
+
+        let mut single = vec![1];
+        assert!(crate::derangement::Derangement::default()
+            .shuffle(&mut single, &mut rng)
+            .is_err());
+        assert_eq!(single, vec![1]);
+    }
+
+    #[test]
+    fn test_dyn_rng() {
+        let input_data: Vec<u32> = (0..30).collect();
+        let mut expected = input_data.clone();
+        FisherYates::default()
+            .shuffle(&mut expected, &mut StdRng::seed_from_u64(7))
+            .unwrap();
+
+        let mut seeded = StdRng::seed_from_u64(7);
+        let rng: &mut dyn RngCore = &mut seeded;
+        let mut target = input_data.clone();
+        FisherYates::default().shuffle(&mut target, rng).unwrap();
+        assert_eq!(target, expected);
+
+        let mut target = input_data.clone();
+        Irs::default().shuffle(&mut target, rng).unwrap();
+        crate::sattolo::Sattolo::default()
+            .shuffle(&mut target, rng)
+            .unwrap();
+        crate::derangement::Derangement::default()
+            .shuffle(&mut target, rng)
+            .unwrap();
+        target.sort_unstable();
+        assert_eq!(target, input_data);
+    }
+
+    #[test]
+    fn test_dyn_shuffler() {
+        let input_data: Vec<u32> = (0..40).collect();
+        let mut shufflers: Vec<(Box<dyn DynShuffler<u32>>, Vec<u32>)> = Vec::new();
+
+        let mut expected = input_data.clone();
+        FisherYates::default()
+            .shuffle(&mut expected, &mut StdRng::seed_from_u64(8))
+            .unwrap();
+        shufflers.push((Box::new(FisherYates::default()), expected));
+        let mut expected = input_data.clone();
+        Irs::default()
+            .shuffle(&mut expected, &mut StdRng::seed_from_u64(8))
+            .unwrap();
+        shufflers.push((Box::new(Irs::default()), expected));
+
+        for (shuffler, expected) in shufflers.iter_mut() {
+            let mut target = input_data.clone();
+            shuffler
+                .shuffle_dyn(&mut target, &mut StdRng::seed_from_u64(8))
+                .unwrap();
+            assert_eq!(&target, expected);
+
+            let permutation = shuffler
+                .shuffle_indices_dyn(40, &mut StdRng::seed_from_u64(8))
+                .unwrap();
+            let gathered: Vec<u32> = permutation.iter().map(|&i| input_data[i]).collect();
+            assert_eq!(&gathered, expected);
+        }
+    }
+
+    #[test]
+    fn test_shuffle_reporting() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let mut fy = FisherYates::default();
+        let mut irs = Irs::default();
+        for (len, expected) in [
+            (0, ShuffleOutcome::Trivial),
+            (1, ShuffleOutcome::Trivial),
+            (2, ShuffleOutcome::Shuffled),
+            (10, ShuffleOutcome::Shuffled),
+        ] {
+            let mut target: Vec<u32> = (0..len).collect();
+            assert_eq!(fy.shuffle_reporting(&mut target, &mut rng), Ok(expected));
+            assert_eq!(irs.shuffle_reporting(&mut target, &mut rng), Ok(expected));
+        }
+    }
+
+    #[test]
+    fn test_expected_mean_displacement() {
+        let fy = FisherYates::default();
+        let irs = Irs::<usize>::default();
+        for n in [0, 1, 2, 10, 52] {
+            let expected = Shuffler::<usize>::expected_mean_displacement(&fy, n);
+            assert_eq!(irs.expected_mean_displacement(n), expected);
+        }
+        assert_eq!(Shuffler::<usize>::expected_mean_displacement(&fy, 0), 0.0);
+        assert_eq!(Shuffler::<usize>::expected_mean_displacement(&fy, 1), 0.0);
+
+        let mut rng = StdRng::seed_from_u64(6);
+        let mut fy = FisherYates::default();
+        let trials = 2000;
+        let mut total = 0.0;
+        for _ in 0..trials {
+            let perm = fy.shuffle_indices_vec(20, &mut rng);
+            total += crate::analysis::mean_move_distance(&perm);
+        }
+        let expected = Shuffler::<usize>::expected_mean_displacement(&fy, 20);
+        assert!((total / trials as f64 - expected).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_shuffle_within_equal() {
+        /// An element ordered by its `key` only.
+        #[derive(Clone, Debug)]
+        struct Item {
+            key: u32,
+            id: u32,
+        }
+
+        impl PartialEq for Item {
+            fn eq(&self, other: &Self) -> bool {
+                self.key == other.key
+            }
+        }
+
+        impl Eq for Item {}
+
+        impl PartialOrd for Item {
+            fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for Item {
+            fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+                self.key.cmp(&other.key)
+            }
+        }
+
+        let input_data: Vec<Item> = (0..60).map(|id| Item { key: id % 3, id }).collect();
+        let mut orders = Vec::new();
+        for seed in 0..2 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut target = input_data.clone();
+            FisherYates::default()
+                .shuffle_within_equal(&mut target, &mut rng)
+                .unwrap();
+            assert!(target.windows(2).all(|w| w[0].key <= w[1].key));
+            let mut ids: Vec<u32> = target.iter().map(|item| item.id).collect();
+            orders.push(ids.clone());
+            ids.sort_unstable();
+            assert_eq!(ids, (0..60).collect::<Vec<_>>());
+        }
+        assert_ne!(orders[0], orders[1]);
+    }
+
+    #[test]
+    fn test_shuffle_tail() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let input_data: Vec<u32> = (0..50).collect();
+
+        let mut fy_target = input_data.clone();
+        FisherYates::default()
+            .shuffle_tail(&mut fy_target, 3, &mut rng)
+            .unwrap();
+        let mut irs_target = input_data.clone();
+        Irs::default()
+            .shuffle_tail(&mut irs_target, 3, &mut rng)
+            .unwrap();
+
+        for target in [fy_target, irs_target] {
+            assert_eq!(target[..3], input_data[..3]);
+            assert_ne!(target[3..], input_data[3..]);
+            let mut tail = target[3..].to_vec();
+            tail.sort_unstable();
+            assert_eq!(tail, input_data[3..]);
+        }
+    }
+
+    #[test]
+    fn test_shuffle_cloned() {
+        let input_data: Vec<String> = (0..40).map(|i| format!("sample-{}", i)).collect();
+        let original = input_data.clone();
+
+        let mut rng = StdRng::seed_from_u64(4);
+        let shuffled = Irs::default()
+            .shuffle_cloned(&input_data, &mut rng)
+            .unwrap();
+        assert_eq!(input_data, original);
+        let mut rng = StdRng::seed_from_u64(4);
+        let mut expected = input_data.clone();
+        Irs::default().shuffle(&mut expected, &mut rng).unwrap();
+        assert_eq!(shuffled, expected);
+
+        let shuffled = FisherYates::default()
+            .shuffle_cloned(&input_data, &mut rng)
+            .unwrap();
+        assert_eq!(input_data, original);
+        assert_ne!(shuffled, input_data);
+        let mut sorted = shuffled;
+        sorted.sort();
+        let mut expected = input_data;
+        expected.sort();
+        assert_eq!(sorted, expected);
+    }
+
+    #[test]
+    fn test_shuffle_tracked_owned() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let input_data: Vec<String> = (0..40).map(|i| format!("sample-{}", i)).collect();
+
+        let (shuffled, permutation) = FisherYates::default()
+            .shuffle_tracked_owned(&input_data, &mut rng)
+            .unwrap();
+        assert_eq!(shuffled.len(), input_data.len());
+        assert_ne!(shuffled, input_data);
+        for (i, &source) in permutation.iter().enumerate() {
+            assert_eq!(shuffled[i], input_data[source]);
+        }
+
+        let (shuffled, permutation) = Irs::<String>::default()
+            .shuffle_tracked_owned(&input_data, &mut rng)
+            .unwrap();
+        assert_ne!(shuffled, input_data);
+        for (i, &source) in permutation.iter().enumerate() {
+            assert_eq!(shuffled[i], input_data[source]);
+        }
+    }
+
+    #[test]
+    fn test_shuffle_indices_matches_shuffle() {
+        let input_data: Vec<String> = (0..40).map(|i| format!("sample-{}", i)).collect();
+
+        let mut rng = StdRng::seed_from_u64(5);
+        let permutation = FisherYates::default().shuffle_indices_vec(input_data.len(), &mut rng);
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut target = input_data.clone();
+        FisherYates::default()
+            .shuffle(&mut target, &mut rng)
+            .unwrap();
+        for (i, &source) in permutation.iter().enumerate() {
+            assert_eq!(target[i], input_data[source]);
+        }
+
+        let mut rng = StdRng::seed_from_u64(5);
+        let permutation =
+            Shuffler::<String>::shuffle_indices(&mut Irs::default(), input_data.len(), &mut rng)
+                .unwrap();
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut target = input_data.clone();
+        Irs::default().shuffle(&mut target, &mut rng).unwrap();
+        for (i, &source) in permutation.iter().enumerate() {
+            assert_eq!(target[i], input_data[source]);
+        }
+    }
+
+    #[test]
+    fn test_shuffle_slice() {
+        fn check<S: Shuffler<u32>>(mut shuffler: S) {
+            let input_data: [u32; 32] = std::array::from_fn(|i| i as u32);
+
+            let mut vec_target = input_data.to_vec();
+            shuffler
+                .shuffle(&mut vec_target, &mut StdRng::seed_from_u64(6))
+                .unwrap();
+            let mut array_target = input_data;
+            shuffler
+                .shuffle_slice(&mut array_target, &mut StdRng::seed_from_u64(6))
+                .unwrap();
+            assert_eq!(&vec_target[..], &array_target[..]);
+            assert_ne!(array_target, input_data);
+
+            let mut target = input_data;
+            shuffler
+                .shuffle_slice(&mut target[8..24], &mut StdRng::seed_from_u64(6))
+                .unwrap();
+            assert_eq!(target[..8], input_data[..8]);
+            assert_eq!(target[24..], input_data[24..]);
+            target[8..24].sort_unstable();
+            assert_eq!(target, input_data);
+        }
+
+        check(FisherYates::default());
+        check(Irs::default());
+    }
+
+    #[test]
+    fn test_shuffle_tail_keep_out_of_range() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut target = vec![1, 2, 3];
+        assert!(FisherYates::default()
+            .shuffle_tail(&mut target, 3, &mut rng)
+            .is_ok());
+        assert!(FisherYates::default()
+            .shuffle_tail(&mut target, 4, &mut rng)
+            .is_err());
+        assert_eq!(target, vec![1, 2, 3]);
+    }
 }
@@ -0,0 +1,98 @@
+//! Shuffling in small visible steps, e.g. for animations.
+
+use rand::Rng;
+
+/// Returns snapshots of `data` being shuffled one transposition at a time.
+///
+/// The first snapshot is `data` itself and every next one swaps exactly
+/// two elements of the previous one (not necessarily neighbouring ones).
+/// The swaps are the non-trivial swaps of a Fisher-Yates shuffle, of
+/// which there are at most `data.len() - 1`, so the result has at most
+/// `steps` snapshots, and fewer if the shuffle needs fewer swaps.
+///
+/// If `steps` is at least `data.len()`, the last snapshot is a uniformly
+/// random permutation of `data`. With fewer steps the shuffle is cut
+/// short; no shorter sequence can end uniformly anyway, as a random
+/// permutation of `n` elements is on average about `n - ln(n)`
+/// transpositions away from the identity. The intermediate snapshots
+/// are **not** uniformly distributed either.
+///
+/// # Examples
+/// ```
+/// use shuffle::smooth;
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let mut rng = StdRng::seed_from_u64(42);
+/// let snapshots = smooth::shuffle_sequence(&[1, 2, 3, 4, 5], 5, &mut rng);
+///
+/// assert_eq!(&snapshots[0], &[1, 2, 3, 4, 5]);
+/// for pair in snapshots.windows(2) {
+///     let changed = pair[0].iter().zip(&pair[1]).filter(|(a, b)| a != b).count();
+///     assert_eq!(changed, 2);
+/// }
+/// ```
+pub fn shuffle_sequence<T, R>(data: &[T], steps: usize, rng: &mut R) -> Vec<Vec<T>>
+where
+    T: Clone,
+    R: Rng + ?Sized,
+{
+    let mut snapshots = Vec::new();
+    if steps == 0 {
+        return snapshots;
+    }
+    snapshots.push(data.to_vec());
+    let mut current = data.to_vec();
+    for i in (1..data.len()).rev() {
+        if snapshots.len() == steps {
+            break;
+        }
+        let j = rng.gen_range(0..(i + 1));
+        if i != j {
+            current.swap(i, j);
+            snapshots.push(current.clone());
+        }
+    }
+    snapshots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{chi_square_critical_value, counts_chi_square};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_snapshots_differ_by_one_transposition() {
+        let mut rng = StdRng::seed_from_u64(34);
+        let input_data: Vec<u32> = (0..50).collect();
+        for &steps in &[0, 1, 10, 50, 100] {
+            let snapshots = shuffle_sequence(&input_data, steps, &mut rng);
+            assert!(snapshots.len() <= steps);
+            if steps > 0 {
+                assert_eq!(snapshots[0], input_data);
+            }
+            for pair in snapshots.windows(2) {
+                let changed = pair[0].iter().zip(&pair[1]).filter(|(a, b)| a != b);
+                assert_eq!(changed.count(), 2);
+            }
+        }
+        assert_eq!(shuffle_sequence(&[1], 5, &mut rng), vec![vec![1]]);
+    }
+
+    #[test]
+    fn test_last_snapshot_is_uniform() {
+        let mut rng = StdRng::seed_from_u64(34);
+        let trials = 18_000;
+        let mut counts = HashMap::new();
+        for _ in 0..trials {
+            let snapshots = shuffle_sequence(&[0, 1, 2], 3, &mut rng);
+            *counts.entry(snapshots.last().unwrap().clone()).or_insert(0) += 1;
+        }
+        assert_eq!(counts.len(), 6);
+        let counts: Vec<usize> = counts.into_values().collect();
+        assert!(counts_chi_square(&counts) < chi_square_critical_value(5, 0.999));
+    }
+}
@@ -0,0 +1,84 @@
+//! Shuffling sets of positions stored densely as bits.
+//!
+//! Available with the `sparse` feature.
+
+use bitvec::vec::BitVec;
+use rand::Rng;
+
+/// Moves the set bits of `bits` to random positions, keeping their number.
+///
+/// The bits are treated as the occupancy of `bits.len()` slots: afterwards
+/// the same number of slots is occupied, and every choice of which ones
+/// is equally likely, as if the bits were shuffled with Fisher-Yates. That
+/// is what this does, in place, so no memory is needed beyond the bits
+/// themselves, but the time is linear in `bits.len()` rather than in the
+/// number of set bits.
+///
+/// # Examples
+/// ```
+/// use shuffle::sparse::shuffle_set_bits;
+/// use bitvec::vec::BitVec;
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let mut rng = StdRng::seed_from_u64(42);
+/// let mut bits: BitVec = BitVec::repeat(false, 100);
+/// bits.set(3, true);
+/// bits.set(50, true);
+///
+/// shuffle_set_bits(&mut bits, &mut rng);
+/// assert_eq!(bits.len(), 100);
+/// assert_eq!(bits.count_ones(), 2);
+/// ```
+pub fn shuffle_set_bits<R>(bits: &mut BitVec, rng: &mut R)
+where
+    R: Rng + ?Sized,
+{
+    for i in (1..bits.len()).rev() {
+        let j = rng.gen_range(0..=i);
+        let (a, b) = (bits[i], bits[j]);
+        if a != b {
+            bits.set(i, b);
+            bits.set(j, a);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn set_positions(bits: &BitVec) -> Vec<usize> {
+        (0..bits.len()).filter(|&i| bits[i]).collect()
+    }
+
+    #[test]
+    fn test_shuffle_set_bits() {
+        let mut original: BitVec = BitVec::repeat(false, 1000);
+        for i in (0..1000).step_by(37) {
+            original.set(i, true);
+        }
+
+        let mut outcomes = Vec::new();
+        for seed in 0..4 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut bits = original.clone();
+            shuffle_set_bits(&mut bits, &mut rng);
+            assert_eq!(bits.len(), original.len());
+            assert_eq!(bits.count_ones(), original.count_ones());
+            outcomes.push(set_positions(&bits));
+        }
+        assert!(outcomes.iter().all(|o| *o != set_positions(&original)));
+        assert!(outcomes.windows(2).all(|w| w[0] != w[1]));
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut empty = BitVec::new();
+        shuffle_set_bits(&mut empty, &mut rng);
+        assert!(empty.is_empty());
+        let mut full: BitVec = BitVec::repeat(true, 10);
+        shuffle_set_bits(&mut full, &mut rng);
+        assert_eq!(full.count_ones(), 10);
+    }
+}
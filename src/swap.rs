@@ -0,0 +1,140 @@
+//! Containers whose elements can be shuffled by swapping them.
+
+#[cfg(feature = "alloc")]
+use alloc::collections::VecDeque;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// A sequence of elements addressed by an index in `0..len()`, that can
+/// swap any two of them.
+///
+/// This is all [`FisherYates::shuffle_in_place`](crate::fy::FisherYates::shuffle_in_place)
+/// needs, so it can shuffle any such container without copying it into
+/// a slice first. A container shuffles the same as a slice holding its
+/// elements in index order, e.g. a [`VecDeque`] in the order of its
+/// iterator, however they are laid out in its ring buffer.
+///
+/// # Examples
+/// ```
+/// use std::collections::VecDeque;
+///
+/// use shuffle::fy::FisherYates;
+/// use rand::rngs::mock::StepRng;
+///
+/// let mut rng = StepRng::new(2, 13);
+/// let mut fy = FisherYates::default();
+///
+/// let mut queue: VecDeque<_> = vec![2, 3, 4, 5].into();
+/// queue.push_front(1);
+/// fy.shuffle_in_place(&mut queue, &mut rng);
+/// assert_eq!(queue, [2, 3, 4, 5, 1]);
+/// ```
+pub trait SwapSlice {
+    /// Returns the number of elements.
+    fn len(&self) -> usize;
+
+    /// Swaps the elements at the indices `i` and `j`.
+    ///
+    /// Panics if either of them is out of bounds.
+    fn swap(&mut self, i: usize, j: usize);
+
+    /// Returns `true` if there are no elements.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> SwapSlice for [T] {
+    fn len(&self) -> usize {
+        <[T]>::len(self)
+    }
+
+    fn swap(&mut self, i: usize, j: usize) {
+        <[T]>::swap(self, i, j)
+    }
+}
+
+impl<T, const N: usize> SwapSlice for [T; N] {
+    fn len(&self) -> usize {
+        N
+    }
+
+    fn swap(&mut self, i: usize, j: usize) {
+        <[T]>::swap(self, i, j)
+    }
+}
+
+/// Available with the `alloc` feature.
+#[cfg(feature = "alloc")]
+impl<T> SwapSlice for Vec<T> {
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn swap(&mut self, i: usize, j: usize) {
+        <[T]>::swap(self, i, j)
+    }
+}
+
+/// Available with the `alloc` feature.
+#[cfg(feature = "alloc")]
+impl<T> SwapSlice for VecDeque<T> {
+    fn len(&self) -> usize {
+        VecDeque::len(self)
+    }
+
+    fn swap(&mut self, i: usize, j: usize) {
+        VecDeque::swap(self, i, j)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fy::FisherYates;
+    use crate::shuffler::Shuffler;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_same_as_slice() {
+        let mut fy = FisherYates::default();
+        let mut expected: Vec<u32> = (0..100).collect();
+        fy.shuffle(&mut expected, &mut StdRng::seed_from_u64(40))
+            .unwrap();
+
+        let mut array = [0u32; 100];
+        array.iter_mut().zip(0..).for_each(|(x, i)| *x = i);
+        fy.shuffle_in_place(&mut array, &mut StdRng::seed_from_u64(40));
+        assert_eq!(array.to_vec(), expected);
+
+        let mut vec: Vec<u32> = (0..100).collect();
+        fy.shuffle_in_place(&mut vec, &mut StdRng::seed_from_u64(40));
+        assert_eq!(vec, expected);
+    }
+
+    #[test]
+    fn test_vec_deque_wraparound() {
+        let mut fy = FisherYates::default();
+        for front in [0, 1, 37, 99, 100] {
+            // The first `front` elements are pushed to the front, so they
+            // wrap around to the end of the ring buffer.
+            let mut queue: VecDeque<u32> = VecDeque::with_capacity(100);
+            queue.extend(front..100);
+            for i in (0..front).rev() {
+                queue.push_front(i);
+            }
+
+            let mut expected: Vec<u32> = (0..100).collect();
+            let mut rng = StdRng::seed_from_u64(41);
+            fy.shuffle_in_place(&mut expected, &mut rng);
+            let mut rng = StdRng::seed_from_u64(41);
+            fy.shuffle_in_place(&mut queue, &mut rng);
+            assert_eq!(queue.iter().copied().collect::<Vec<_>>(), expected);
+        }
+
+        let mut empty: VecDeque<u32> = VecDeque::new();
+        fy.shuffle_in_place(&mut empty, &mut StdRng::seed_from_u64(41));
+        assert!(SwapSlice::is_empty(&empty));
+    }
+}
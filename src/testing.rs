@@ -0,0 +1,453 @@
+//! Helpers for testing shufflers empirically.
+//!
+//! Available with the `test-util` feature.
+
+use rand::RngCore;
+
+use crate::lehmer::index_of_permutation;
+use crate::shuffler::Shuffler;
+
+/// Tests whether the first element lands at a uniformly random position
+/// when `shuffler` shuffles `n` elements, and returns a p-value.
+///
+/// The data `0..n` is shuffled `trials` times and the landing positions
+/// of `0` are compared with the uniform distribution by the
+/// Kolmogorov-Smirnov statistic, i.e. the largest difference between
+/// the empirical and the expected cumulative distribution. The result is
+/// the asymptotic probability of a difference at least this large if
+/// the shuffler were uniform.
+///
+/// A uniform shuffler gives a p-value uniformly distributed in `[0, 1]`,
+/// so it falls below `0.01` in one run out of a hundred; a shuffler
+/// that keeps the first element in place or near the front gives values
+/// close to `0`. As the positions are discrete the test is somewhat
+/// conservative, and it needs at least a few hundred `trials` to be
+/// meaningful.
+///
+/// Only a single metric is checked, so passing the test doesn't prove
+/// uniformity: e.g. a random rotation of the data passes it, although
+/// it produces just `n` of the `n!` permutations. If `shuffler` fails,
+/// `0.0` is returned. With fewer than two elements or no trials there
+/// is nothing to test and the result is `1.0`.
+///
+/// # Examples
+/// ```
+/// use shuffle::fy::FisherYates;
+/// use shuffle::testing::ks_uniformity;
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let mut rng = StdRng::seed_from_u64(42);
+/// let p_value = ks_uniformity(&mut FisherYates::default(), 10, 1000, &mut rng);
+/// assert!(p_value > 0.01);
+/// ```
+pub fn ks_uniformity<S, R>(shuffler: &mut S, n: usize, trials: usize, rng: &mut R) -> f64
+where
+    S: Shuffler<usize>,
+    R: RngCore + ?Sized,
+{
+    if n < 2 || trials == 0 {
+        return 1.0;
+    }
+    let mut counts = vec![0usize; n];
+    let mut data = Vec::with_capacity(n);
+    for _ in 0..trials {
+        data.clear();
+        data.extend(0..n);
+        if shuffler.shuffle(&mut data, rng).is_err() {
+            return 0.0;
+        }
+        match data.iter().position(|&x| x == 0) {
+            Some(position) => counts[position] += 1,
+            None => return 0.0,
+        }
+    }
+
+    let mut cumulative = 0;
+    let mut statistic: f64 = 0.0;
+    for (position, &count) in counts.iter().enumerate() {
+        cumulative += count;
+        let empirical = cumulative as f64 / trials as f64;
+        let expected = (position + 1) as f64 / n as f64;
+        statistic = statistic.max((empirical - expected).abs());
+    }
+    kolmogorov_survival(statistic, trials)
+}
+
+/// The asymptotic probability that the Kolmogorov-Smirnov statistic of
+/// `trials` samples exceeds `statistic`, with Stephens' correction for
+/// small samples.
+fn kolmogorov_survival(statistic: f64, trials: usize) -> f64 {
+    let sqrt_trials = (trials as f64).sqrt();
+    let lambda = (sqrt_trials + 0.12 + 0.11 / sqrt_trials) * statistic;
+    if lambda < 0.2 {
+        // The series converges too slowly here, and the result is 1 anyway.
+        return 1.0;
+    }
+    let mut sum = 0.0;
+    let mut sign = 1.0;
+    for j in 1..=100 {
+        let term = (-2.0 * (j * j) as f64 * lambda * lambda).exp();
+        sum += sign * term;
+        if term < 1e-12 {
+            break;
+        }
+        sign = -sign;
+    }
+    (2.0 * sum).clamp(0.0, 1.0)
+}
+
+/// Shuffles `0..n` `trials` times with `shuffler` and returns the
+/// chi-square statistic of how often every element landed at every
+/// position.
+///
+/// For a uniform shuffler each of the `n * n` counts is expected to be
+/// about `trials / n`. The counts from a single shuffle are dependent, as
+/// every element lands at exactly one position and every position gets
+/// exactly one element, so the plain statistic is scaled by
+/// `(n - 1) / n`. That makes it chi-square distributed with `(n - 1)^2`
+/// degrees of freedom; compare it with [`chi_square_critical_value`] to
+/// decide whether the shuffler looks biased. The expected counts should
+/// be at least 5 or so, i.e. `trials >= 5 * n`, for the approximation to
+/// hold.
+///
+/// Like for [`ks_uniformity`], passing doesn't prove uniformity, as only
+/// the positions of single elements are checked. For a small `n`,
+/// [`permutation_chi_square`] tests the whole distribution. If `shuffler`
+/// fails, `f64::INFINITY` is returned. With fewer than two elements or no
+/// trials there is nothing to test and the result is `0.0`.
+///
+/// # Examples
+/// ```
+/// use shuffle::fy::FisherYates;
+/// use shuffle::testing::{chi_square_critical_value, uniformity_chi_square};
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let mut rng = StdRng::seed_from_u64(42);
+/// let statistic = uniformity_chi_square(&mut FisherYates::default(), 10, 1000, &mut rng);
+/// assert!(statistic < chi_square_critical_value(81, 0.999));
+/// ```
+pub fn uniformity_chi_square<S, R>(shuffler: &mut S, n: usize, trials: usize, rng: &mut R) -> f64
+where
+    S: Shuffler<usize>,
+    R: RngCore + ?Sized,
+{
+    if n < 2 || trials == 0 {
+        return 0.0;
+    }
+    let mut counts = vec![0usize; n * n];
+    let mut data = Vec::with_capacity(n);
+    for _ in 0..trials {
+        data.clear();
+        data.extend(0..n);
+        if shuffler.shuffle(&mut data, rng).is_err() {
+            return f64::INFINITY;
+        }
+        for (position, &element) in data.iter().enumerate() {
+            match counts.get_mut(element * n + position) {
+                Some(count) => *count += 1,
+                None => return f64::INFINITY,
+            }
+        }
+    }
+    chi_square(&counts, trials as f64 / n as f64) * (n - 1) as f64 / n as f64
+}
+
+/// The largest `n` accepted by [`permutation_chi_square`].
+const MAX_EXACT_LEN: usize = 8;
+
+/// Shuffles `0..n` `trials` times with `shuffler` and returns the
+/// chi-square statistic of how often every one of the `n!` permutations
+/// came out.
+///
+/// Unlike [`uniformity_chi_square`] this tests exactly what a uniform
+/// shuffler promises, but it is only feasible for a small `n`. The
+/// statistic has `n! - 1` degrees of freedom, and `trials` should be at
+/// least `5 * n!`. If `shuffler` fails or doesn't return a permutation,
+/// `f64::INFINITY` is returned. With fewer than two elements or no trials
+/// there is nothing to test and the result is `0.0`.
+///
+/// # Panics
+///
+/// If `n` is more than 8, as there would be too many permutations.
+///
+/// # Examples
+/// ```
+/// use shuffle::irs::Irs;
+/// use shuffle::testing::{chi_square_critical_value, permutation_chi_square};
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let mut rng = StdRng::seed_from_u64(42);
+/// let statistic = permutation_chi_square(&mut Irs::default(), 4, 2400, &mut rng);
+/// assert!(statistic < chi_square_critical_value(23, 0.999));
+/// ```
+pub fn permutation_chi_square<S, R>(shuffler: &mut S, n: usize, trials: usize, rng: &mut R) -> f64
+where
+    S: Shuffler<usize>,
+    R: RngCore + ?Sized,
+{
+    assert!(
+        n <= MAX_EXACT_LEN,
+        "at most {} elements are supported",
+        MAX_EXACT_LEN
+    );
+    if n < 2 || trials == 0 {
+        return 0.0;
+    }
+    let permutations: usize = (1..=n).product();
+    let mut counts = vec![0usize; permutations];
+    let mut data = Vec::with_capacity(n);
+    for _ in 0..trials {
+        data.clear();
+        data.extend(0..n);
+        if shuffler.shuffle(&mut data, rng).is_err() {
+            return f64::INFINITY;
+        }
+        match index_of_permutation(&data) {
+            // The index fits, as there are at most `8!` permutations.
+            Ok(index) => counts[index as usize] += 1,
+            Err(_) => return f64::INFINITY,
+        }
+    }
+    chi_square(&counts, trials as f64 / permutations as f64)
+}
+
+/// Returns the chi-square statistic of `counts` of outcomes that are all
+/// equally likely, e.g. how often every permutation a shuffler can return
+/// came out.
+///
+/// This tests shuffles that [`permutation_chi_square`] can't drive, such
+/// as partial shuffles or shufflers over a subset of the permutations.
+/// The statistic has `counts.len() - 1` degrees of freedom. Outcomes that
+/// never came out have to be counted as zeros, and without any counts the
+/// result is `0.0`.
+///
+/// # Examples
+/// ```
+/// use shuffle::testing::{chi_square_critical_value, counts_chi_square};
+///
+/// let critical = chi_square_critical_value(4, 0.999);
+/// assert!(counts_chi_square(&[98, 103, 101, 95, 103]) < critical);
+/// assert!(counts_chi_square(&[150, 50, 100, 100, 100]) > critical);
+/// ```
+pub fn counts_chi_square(counts: &[usize]) -> f64 {
+    let total: usize = counts.iter().sum();
+    if total == 0 {
+        return 0.0;
+    }
+    chi_square(counts, total as f64 / counts.len() as f64)
+}
+
+/// Returns the critical value of the chi-square distribution with
+/// `degrees_of_freedom` at the `confidence` level, i.e. the value that
+/// a sample exceeds with probability `1 - confidence`.
+///
+/// A statistic above it rejects uniformity at that level; e.g. with
+/// a `confidence` of `0.999` a uniform shuffler fails once in a thousand
+/// runs. The value is the Wilson-Hilferty approximation, which is within
+/// a few percent of the exact one for any `degrees_of_freedom` of at
+/// least 3, and closer for more.
+///
+/// # Panics
+///
+/// If `degrees_of_freedom` is `0` or `confidence` is not between `0` and
+/// `1`, exclusive.
+///
+/// # Examples
+/// ```
+/// use shuffle::testing::chi_square_critical_value;
+///
+/// assert!((chi_square_critical_value(10, 0.95) - 18.307).abs() < 0.1);
+/// ```
+pub fn chi_square_critical_value(degrees_of_freedom: usize, confidence: f64) -> f64 {
+    assert!(degrees_of_freedom > 0, "there must be a degree of freedom");
+    assert!(
+        confidence > 0.0 && confidence < 1.0,
+        "the confidence must be between 0 and 1"
+    );
+    let k = degrees_of_freedom as f64;
+    let z = normal_quantile(confidence);
+    let variance = 2.0 / (9.0 * k);
+    k * (1.0 - variance + z * variance.sqrt()).powi(3)
+}
+
+/// Returns the chi-square statistic of `counts` that are all expected
+/// to be `expected`.
+fn chi_square(counts: &[usize], expected: f64) -> f64 {
+    counts
+        .iter()
+        .map(|&count| {
+            let difference = count as f64 - expected;
+            difference * difference / expected
+        })
+        .sum()
+}
+
+/// The quantile function of the standard normal distribution, by the
+/// rational approximation of Acklam, with a relative error below
+/// `1.2e-9`.
+fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969_683_028_665_376e1,
+        2.209_460_984_245_205e2,
+        -2.759_285_104_469_687e2,
+        1.383_577_518_672_69e2,
+        -3.066_479_806_614_716e1,
+        2.506_628_277_459_239,
+    ];
+    const B: [f64; 5] = [
+        -5.447_609_879_822_406e1,
+        1.615_858_368_580_409e2,
+        -1.556_989_798_598_866e2,
+        6.680_131_188_771_972e1,
+        -1.328_068_155_288_572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784_894_002_430_293e-3,
+        -3.223_964_580_411_365e-1,
+        -2.400_758_277_161_838,
+        -2.549_732_539_343_734,
+        4.374_664_141_464_968,
+        2.938_163_982_698_783,
+    ];
+    const D: [f64; 4] = [
+        7.784_695_709_041_462e-3,
+        3.224_671_290_700_398e-1,
+        2.445_134_137_142_996,
+        3.754_408_661_907_416,
+    ];
+    const LOW: f64 = 0.02425;
+
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+    if p < LOW {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p > 1.0 - LOW {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fy::FisherYates;
+    use crate::irs::Irs;
+    use crate::sattolo::Sattolo;
+    use crate::shuffler::ShuffleError;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    /// A "shuffler" that leaves the data unchanged.
+    struct NoOpShuffle;
+
+    impl<T> Shuffler<T> for NoOpShuffle {
+        fn shuffle_slice<R>(&mut self, _data: &mut [T], _rng: &mut R) -> Result<(), ShuffleError>
+        where
+            T: Clone,
+            R: RngCore + ?Sized,
+        {
+            Ok(())
+        }
+
+        fn shuffle_indices<R>(
+            &mut self,
+            len: usize,
+            _rng: &mut R,
+        ) -> Result<Vec<usize>, ShuffleError>
+        where
+            R: RngCore + ?Sized,
+        {
+            Ok((0..len).collect())
+        }
+    }
+
+    #[test]
+    fn test_ks_uniformity() {
+        let mut rng = StdRng::seed_from_u64(38);
+        assert!(ks_uniformity(&mut FisherYates::default(), 20, 2000, &mut rng) > 0.01);
+        assert!(ks_uniformity(&mut Irs::default(), 20, 2000, &mut rng) > 0.01);
+        assert!(ks_uniformity(&mut NoOpShuffle, 20, 2000, &mut rng) < 1e-6);
+        assert_eq!(ks_uniformity(&mut NoOpShuffle, 1, 2000, &mut rng), 1.0);
+    }
+
+    #[test]
+    fn test_uniformity_chi_square() {
+        let mut rng = StdRng::seed_from_u64(39);
+        let critical = chi_square_critical_value(19 * 19, 0.999);
+        for _ in 0..5 {
+            assert!(
+                uniformity_chi_square(&mut FisherYates::default(), 20, 2000, &mut rng) < critical
+            );
+            assert!(uniformity_chi_square(&mut Irs::default(), 20, 2000, &mut rng) < critical);
+        }
+        assert!(uniformity_chi_square(&mut NoOpShuffle, 20, 2000, &mut rng) > critical);
+        // A single cycle never leaves an element in place.
+        let statistic = uniformity_chi_square(&mut Sattolo::default(), 20, 2000, &mut rng);
+        assert!(statistic > critical);
+        assert_eq!(
+            uniformity_chi_square(&mut NoOpShuffle, 1, 2000, &mut rng),
+            0.0
+        );
+
+        // The mean of a chi-square distribution is its degrees of freedom.
+        let runs = 200;
+        let mean = (0..runs)
+            .map(|_| uniformity_chi_square(&mut FisherYates::default(), 6, 300, &mut rng))
+            .sum::<f64>()
+            / runs as f64;
+        assert!((mean - 25.0).abs() < 2.0, "mean: {}", mean);
+    }
+
+    #[test]
+    fn test_permutation_chi_square() {
+        let mut rng = StdRng::seed_from_u64(40);
+        let critical = chi_square_critical_value(119, 0.999);
+        assert!(permutation_chi_square(&mut FisherYates::default(), 5, 6000, &mut rng) < critical);
+        assert!(permutation_chi_square(&mut Irs::default(), 5, 6000, &mut rng) < critical);
+        assert!(permutation_chi_square(&mut Sattolo::default(), 5, 6000, &mut rng) > critical);
+        assert!(permutation_chi_square(&mut NoOpShuffle, 5, 6000, &mut rng) > critical);
+    }
+
+    #[test]
+    fn test_counts_chi_square() {
+        assert_eq!(counts_chi_square(&[]), 0.0);
+        assert_eq!(counts_chi_square(&[0, 0]), 0.0);
+        assert_eq!(counts_chi_square(&[5, 5, 5]), 0.0);
+        assert_eq!(counts_chi_square(&[10, 0]), 10.0);
+        assert!((counts_chi_square(&[12, 8, 10]) - 0.8).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_chi_square_critical_value() {
+        // Exact values from a table.
+        let table = [(3, 0.95, 7.815), (10, 0.99, 23.209), (100, 0.999, 149.449)];
+        for &(degrees_of_freedom, confidence, exact) in &table {
+            let value = chi_square_critical_value(degrees_of_freedom, confidence);
+            assert!(
+                (value - exact).abs() / exact < 0.01,
+                "{} vs {}",
+                value,
+                exact
+            );
+        }
+        assert!((normal_quantile(0.975) - 1.959_964).abs() < 1e-6);
+        assert!((normal_quantile(0.001) + 3.090_232).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_kolmogorov_survival() {
+        assert_eq!(kolmogorov_survival(0.0, 100), 1.0);
+        // The critical values of the statistic for large samples.
+        assert!((kolmogorov_survival(1.36 / 1000f64.sqrt(), 1000) - 0.05).abs() < 0.005);
+        assert!((kolmogorov_survival(1.63 / 1000f64.sqrt(), 1000) - 0.01).abs() < 0.002);
+    }
+}
@@ -0,0 +1,122 @@
+//! Random orders respecting dependencies.
+
+use rand::Rng;
+
+use crate::shuffler::ShuffleError;
+
+/// Returns a random topological order of the graph with vertices `0..n`.
+///
+/// Every pair `(before, after)` in `edges` means that `before` has to come
+/// earlier than `after` in the result. This is randomized Kahn's algorithm:
+/// at every step the next vertex is picked uniformly among the ones whose
+/// dependencies are all placed already.
+///
+/// Every topological order may be returned, but in general they are
+/// **not** equally likely; e.g. for the edges `(0, 1)` and `(0, 2)` plus an
+/// independent vertex `3`, the orders starting with `3` are more likely
+/// than a uniform choice would make them. Without any edges the result is
+/// a uniformly random permutation.
+///
+/// Returns [`ShuffleError::Cyclic`] if the graph has a cycle, and
+/// [`ShuffleError::OutOfBounds`] if an edge refers to a vertex out of
+/// bounds.
+///
+/// # Examples
+/// ```
+/// use shuffle::topo;
+/// use rand::rngs::mock::StepRng;
+///
+/// let mut rng = StepRng::new(2, 13);
+/// let order = topo::random_order(3, &[(2, 0), (0, 1)], &mut rng).unwrap();
+/// assert_eq!(&order, &[2, 0, 1]);
+/// ```
+pub fn random_order<R>(
+    n: usize,
+    edges: &[(usize, usize)],
+    rng: &mut R,
+) -> Result<Vec<usize>, ShuffleError>
+where
+    R: Rng + ?Sized,
+{
+    if edges
+        .iter()
+        .any(|&(before, after)| before >= n || after >= n)
+    {
+        return Err(ShuffleError::OutOfBounds);
+    }
+    let mut successors = vec![Vec::new(); n];
+    let mut pending = vec![0usize; n];
+    for &(before, after) in edges {
+        successors[before].push(after);
+        pending[after] += 1;
+    }
+
+    let mut ready: Vec<usize> = (0..n).filter(|&v| pending[v] == 0).collect();
+    let mut order = Vec::with_capacity(n);
+    while !ready.is_empty() {
+        let vertex = ready.swap_remove(rng.gen_range(0..ready.len()));
+        order.push(vertex);
+        for &next in &successors[vertex] {
+            pending[next] -= 1;
+            if pending[next] == 0 {
+                ready.push(next);
+            }
+        }
+    }
+
+    if order.len() < n {
+        return Err(ShuffleError::Cyclic);
+    }
+    Ok(order)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::collections::HashSet;
+
+    const EDGES: [(usize, usize); 7] = [(0, 2), (1, 2), (2, 5), (3, 4), (4, 5), (0, 4), (6, 1)];
+
+    #[test]
+    fn test_random_order_respects_edges() {
+        let mut orders = HashSet::new();
+        for seed in 0..50 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let order = random_order(8, &EDGES, &mut rng).unwrap();
+
+            let mut position = vec![0; order.len()];
+            for (i, &vertex) in order.iter().enumerate() {
+                position[vertex] = i;
+            }
+            for &(before, after) in EDGES.iter() {
+                assert!(position[before] < position[after]);
+            }
+            let mut sorted = order.clone();
+            sorted.sort_unstable();
+            assert_eq!(sorted, (0..8).collect::<Vec<_>>());
+
+            orders.insert(order);
+        }
+        assert!(orders.len() > 10);
+    }
+
+    #[test]
+    fn test_invalid_graphs() {
+        let mut rng = StdRng::seed_from_u64(27);
+        assert_eq!(
+            random_order(3, &[(0, 1), (1, 2), (2, 0)], &mut rng),
+            Err(ShuffleError::Cyclic)
+        );
+        assert_eq!(
+            random_order(2, &[(1, 1)], &mut rng),
+            Err(ShuffleError::Cyclic)
+        );
+        assert_eq!(
+            random_order(3, &[(0, 3)], &mut rng),
+            Err(ShuffleError::OutOfBounds)
+        );
+        assert_eq!(random_order(0, &[], &mut rng), Ok(vec![]));
+    }
+}
@@ -0,0 +1,62 @@
+//! Runtime correctness guards for shuffling.
+
+use rand::Rng;
+
+use crate::fy::FisherYates;
+use crate::irs::Irs;
+use crate::shuffler::{ShuffleError, Shuffler};
+
+/// Shuffles copies of `data` with both [`Irs`] and [`FisherYates`]
+/// and checks that both results contain exactly the elements of `data`.
+///
+/// The two shuffles are independent, so their orders will differ,
+/// but neither of them may ever lose, duplicate or alter an element.
+/// A disagreement therefore indicates a bug and is returned as an error.
+///
+/// # Examples
+/// ```
+/// use shuffle::verify::cross_check;
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let mut rng = StdRng::seed_from_u64(42);
+/// assert!(cross_check(&[1, 2, 3, 4, 5], &mut rng).is_ok());
+/// ```
+pub fn cross_check<T, R>(data: &[T], rng: &mut R) -> Result<(), ShuffleError>
+where
+    T: Clone + Ord,
+    R: Rng + ?Sized,
+{
+    let mut irs_result = data.to_vec();
+    Irs::default().shuffle(&mut irs_result, rng)?;
+    let mut fy_result = data.to_vec();
+    FisherYates::default().shuffle(&mut fy_result, rng)?;
+
+    let mut expected = data.to_vec();
+    expected.sort();
+    irs_result.sort();
+    fy_result.sort();
+    if irs_result != expected || fy_result != expected {
+        return Err(ShuffleError::VerificationFailed);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_cross_check() {
+        let mut rng = StdRng::seed_from_u64(9);
+        assert_eq!(cross_check::<u8, _>(&[], &mut rng), Ok(()));
+        assert_eq!(cross_check(&[1], &mut rng), Ok(()));
+        assert_eq!(cross_check(&[3, 1, 2, 2, 5, 1], &mut rng), Ok(()));
+        let words = ["one", "two", "three", "four"];
+        assert_eq!(cross_check(&words, &mut rng), Ok(()));
+        let large: Vec<u32> = (0..5000).map(|i| i % 37).collect();
+        assert_eq!(cross_check(&large, &mut rng), Ok(()));
+    }
+}
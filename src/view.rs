@@ -0,0 +1,90 @@
+//! Iterating over data in a random order without changing it.
+
+use std::vec;
+
+use rand::Rng;
+
+use crate::fy::FisherYates;
+
+/// An iterator over the elements of a slice in a random order.
+///
+/// Created by [`shuffled_view`].
+#[derive(Clone, Debug)]
+pub struct ShuffledView<'a, T> {
+    data: &'a [T],
+    indices: vec::IntoIter<usize>,
+}
+
+impl<'a, T> Iterator for ShuffledView<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        self.indices.next().map(|i| &self.data[i])
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.indices.size_hint()
+    }
+}
+
+impl<'a, T> ExactSizeIterator for ShuffledView<'a, T> {}
+
+/// Returns an iterator over the elements of `data` in a uniformly random
+/// order.
+///
+/// `data` is neither modified nor cloned: a random permutation of its
+/// indices is computed up front, with
+/// [`FisherYates::shuffle_indices_vec`], and the iterator yields references
+/// to the elements in that order.
+///
+/// # Examples
+/// ```
+/// use shuffle::view::shuffled_view;
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let mut rng = StdRng::seed_from_u64(42);
+/// let data = [1, 2, 3, 4, 5];
+///
+/// let mut seen: Vec<&i32> = shuffled_view(&data, &mut rng).collect();
+/// seen.sort();
+/// assert_eq!(seen, [&1, &2, &3, &4, &5]);
+/// ```
+pub fn shuffled_view<'a, T, R>(data: &'a [T], rng: &mut R) -> ShuffledView<'a, T>
+where
+    R: Rng + ?Sized,
+{
+    let indices = FisherYates::default().shuffle_indices_vec(data.len(), rng);
+    ShuffledView {
+        data,
+        indices: indices.into_iter(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_shuffled_view() {
+        let mut rng = StdRng::seed_from_u64(40);
+        let data: Vec<String> = (0..50).map(|i| i.to_string()).collect();
+
+        let view = shuffled_view(&data, &mut rng);
+        assert_eq!(view.len(), 50);
+        let order: Vec<&String> = view.collect();
+        assert!(order.iter().zip(&data).any(|(a, b)| *a != b));
+
+        let mut sorted = order.clone();
+        sorted.sort();
+        let mut expected: Vec<&String> = data.iter().collect();
+        expected.sort();
+        assert_eq!(sorted, expected);
+
+        let order_again: Vec<&String> = shuffled_view(&data, &mut rng).collect();
+        assert_ne!(order, order_again);
+        assert_eq!(shuffled_view::<u8, _>(&[], &mut rng).next(), None);
+    }
+}
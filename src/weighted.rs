@@ -0,0 +1,378 @@
+//! Weighted shuffling, i.e. weighted sampling without replacement.
+
+use rand::distributions::Open01;
+use rand::Rng;
+
+use crate::shuffler::ShuffleError;
+
+/// Shuffles `data` so that heavier elements tend to come first.
+///
+/// The element at index `i` has the integer weight `weights[i]`. The output
+/// is built position by position: every position is filled with one of the
+/// remaining elements, chosen with probability proportional to its weight.
+/// This is the same distribution as the Efraimidis-Spirakis method, which
+/// sorts by the keys `u^(1/w)`, but here every choice is a single
+/// [`gen_range`](rand::Rng::gen_range) over the total remaining weight, so
+/// no floating point numbers are involved and the probabilities are exact.
+/// The remaining weights are kept in a Fenwick tree, so the whole shuffle
+/// takes `O(n log n)` time.
+///
+/// Elements with weight zero are never chosen while a positive weight
+/// remains; they end up at the back of `data` in uniformly random order.
+///
+/// Returns an error, leaving `data` unchanged, if `weights` and `data`
+/// have different lengths or if the sum of the weights does not fit
+/// in a `u64`.
+///
+/// # Examples
+/// ```
+/// use shuffle::weighted;
+/// use rand::rngs::mock::StepRng;
+///
+/// let mut rng = StepRng::new(2, 13);
+/// let mut input = vec!['a', 'b', 'c', 'd'];
+///
+/// weighted::shuffle_exact_int(&mut input, &[0, 5, 1, 0], &mut rng).unwrap();
+/// assert!(input[2] == 'a' || input[2] == 'd');
+/// assert!(input[3] == 'a' || input[3] == 'd');
+/// ```
+pub fn shuffle_exact_int<T, R>(
+    data: &mut [T],
+    weights: &[u64],
+    rng: &mut R,
+) -> Result<(), ShuffleError>
+where
+    R: Rng + ?Sized,
+{
+    if weights.len() != data.len() {
+        return Err(ShuffleError::LengthMismatch);
+    }
+    let mut total: u64 = 0;
+    for &w in weights {
+        total = total
+            .checked_add(w)
+            .ok_or(ShuffleError::InvalidArgument("total weight overflows"))?;
+    }
+
+    let len = data.len();
+    let mut tree = FenwickTree::new(weights);
+    let mut order = Vec::with_capacity(len);
+    while total > 0 {
+        let index = tree.find(rng.gen_range(0..total));
+        total -= weights[index];
+        tree.sub(index, weights[index]);
+        order.push(index);
+    }
+
+    let first_zero = order.len();
+    order.extend((0..len).filter(|&i| weights[i] == 0));
+    for i in (first_zero + 1..len).rev() {
+        let j = rng.gen_range(first_zero..(i + 1));
+        order.swap(i, j);
+    }
+
+    gather_in_place(data, &order);
+    Ok(())
+}
+
+/// A shuffler with floating point weights, using the Efraimidis-Spirakis
+/// method.
+///
+/// Every element with a positive weight `w` gets the key `u^(1/w)`, with
+/// `u` uniform in `(0, 1)`, and the elements are sorted by their keys in
+/// descending order. This is the same distribution as drawing the
+/// elements one by one with probabilities proportional to their weights,
+/// so in particular heavier elements tend to come first, and each element
+/// comes first with probability `w / total`. The keys are compared by
+/// their logarithms `ln(u) / w`, which keeps them apart for large weights.
+///
+/// Unlike [`shuffle_exact_int`] this works with any weights, but the
+/// probabilities are only as exact as floating point numbers allow. The
+/// keys are kept in the shuffler and reused across calls.
+///
+/// # Examples
+/// ```
+/// use shuffle::weighted::WeightedShuffler;
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+///
+/// let mut rng = StdRng::seed_from_u64(42);
+/// let mut shuffler = WeightedShuffler::default();
+/// let mut loot = vec!["common", "rare", "legendary", "nothing"];
+///
+/// shuffler.shuffle(&mut loot, &[10.0, 2.5, 0.5, 0.0], &mut rng).unwrap();
+/// assert_eq!(loot[3], "nothing");
+/// ```
+#[derive(Debug, Default)]
+pub struct WeightedShuffler {
+    keys: Vec<(f64, usize)>,
+}
+
+impl WeightedShuffler {
+    /// Shuffles `data` so that the element at index `i` has the weight
+    /// `weights[i]`.
+    ///
+    /// Elements with weight zero are never put in front of one with
+    /// a positive weight; they end up at the back of `data` in uniformly
+    /// random order.
+    ///
+    /// Returns an error, leaving `data` unchanged, if `weights` and `data`
+    /// have different lengths, or if a weight is negative, infinite or NaN.
+    pub fn shuffle<T, R>(
+        &mut self,
+        data: &mut [T],
+        weights: &[f64],
+        rng: &mut R,
+    ) -> Result<(), ShuffleError>
+    where
+        R: Rng + ?Sized,
+    {
+        if weights.len() != data.len() {
+            return Err(ShuffleError::LengthMismatch);
+        }
+        if weights.iter().any(|w| !w.is_finite() || *w < 0.0) {
+            return Err(ShuffleError::InvalidArgument(
+                "weights must be finite and non-negative",
+            ));
+        }
+
+        self.keys.clear();
+        for (index, &w) in weights.iter().enumerate() {
+            if w > 0.0 {
+                let u: f64 = rng.sample(Open01);
+                self.keys.push((u.ln() / w, index));
+            }
+        }
+        self.keys.sort_unstable_by(|a, b| b.0.total_cmp(&a.0));
+
+        let mut order: Vec<usize> = self.keys.iter().map(|&(_, index)| index).collect();
+        let first_zero = order.len();
+        order.extend((0..data.len()).filter(|&i| weights[i] == 0.0));
+        for i in (first_zero + 1..order.len()).rev() {
+            let j = rng.gen_range(first_zero..(i + 1));
+            order.swap(i, j);
+        }
+
+        gather_in_place(data, &order);
+        Ok(())
+    }
+}
+
+/// Rearranges `data` so that `data[i]` becomes the old `data[perm[i]]`.
+fn gather_in_place<T>(data: &mut [T], perm: &[usize]) {
+    let mut destination = vec![0; perm.len()];
+    for (position, &source) in perm.iter().enumerate() {
+        destination[source] = position;
+    }
+    for i in 0..data.len() {
+        while destination[i] != i {
+            let d = destination[i];
+            data.swap(i, d);
+            destination.swap(i, d);
+        }
+    }
+}
+
+/// Prefix sums over the weights supporting updates in `O(log n)`.
+struct FenwickTree {
+    tree: Vec<u64>,
+}
+
+impl FenwickTree {
+    fn new(weights: &[u64]) -> Self {
+        let mut tree = vec![0; weights.len() + 1];
+        tree[1..].copy_from_slice(weights);
+        for k in 1..tree.len() {
+            let parent = k + (k & k.wrapping_neg());
+            if parent < tree.len() {
+                tree[parent] += tree[k];
+            }
+        }
+        FenwickTree { tree }
+    }
+
+    fn sub(&mut self, index: usize, w: u64) {
+        let mut k = index + 1;
+        while k < self.tree.len() {
+            self.tree[k] -= w;
+            k += k & k.wrapping_neg();
+        }
+    }
+
+    /// Returns the smallest index whose prefix sum (inclusive) exceeds `target`.
+    fn find(&self, mut target: u64) -> usize {
+        let mut position = 0;
+        let mut step = (self.tree.len()).next_power_of_two();
+        while step > 0 {
+            let next = position + step;
+            if next < self.tree.len() && self.tree[next] <= target {
+                position = next;
+                target -= self.tree[next];
+            }
+            step /= 2;
+        }
+        position
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_selection_order_frequencies() {
+        let mut rng = StdRng::seed_from_u64(23);
+        let weights = [1, 2, 3];
+        let orders = [
+            [0, 1, 2],
+            [0, 2, 1],
+            [1, 0, 2],
+            [1, 2, 0],
+            [2, 0, 1],
+            [2, 1, 0],
+        ];
+        let trials = 60_000;
+        let mut counts = [0usize; 6];
+        for _ in 0..trials {
+            let mut target = [0, 1, 2];
+            shuffle_exact_int(&mut target, &weights, &mut rng).unwrap();
+            counts[orders.iter().position(|o| *o == target).unwrap()] += 1;
+        }
+        for (order, &count) in orders.iter().zip(counts.iter()) {
+            let (first, second) = (weights[order[0]], weights[order[1]]);
+            let expected = first as f64 / 6.0 * second as f64 / (6 - first) as f64;
+            let observed = count as f64 / trials as f64;
+            assert!(
+                (observed - expected).abs() < 0.01,
+                "order {:?}: observed {}, expected {}",
+                order,
+                observed,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_zero_weights_go_last() {
+        let mut rng = StdRng::seed_from_u64(23);
+        let weights = [0, 4, 0, 1, 7, 0, 2];
+        for _ in 0..100 {
+            let mut target: Vec<usize> = (0..weights.len()).collect();
+            shuffle_exact_int(&mut target, &weights, &mut rng).unwrap();
+            assert!(target[..4].iter().all(|&i| weights[i] > 0));
+            assert!(target[4..].iter().all(|&i| weights[i] == 0));
+            target.sort_unstable();
+            assert_eq!(target, (0..weights.len()).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn test_invalid_weights() {
+        let mut rng = StdRng::seed_from_u64(23);
+        let mut target = vec![1, 2, 3];
+        assert_eq!(
+            shuffle_exact_int(&mut target, &[1, 2], &mut rng),
+            Err(ShuffleError::LengthMismatch)
+        );
+        assert_eq!(
+            shuffle_exact_int(&mut target, &[u64::MAX, 1, 0], &mut rng),
+            Err(ShuffleError::InvalidArgument("total weight overflows"))
+        );
+        assert_eq!(target, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_weighted_shuffler_first_position() {
+        let mut rng = StdRng::seed_from_u64(24);
+        let mut shuffler = WeightedShuffler::default();
+        let weights = [1.0, 2.0, 3.0, 0.5, 1.5];
+        let total: f64 = weights.iter().sum();
+        let trials = 40_000;
+        let mut counts = [0usize; 5];
+        for _ in 0..trials {
+            let mut target = [0, 1, 2, 3, 4];
+            shuffler.shuffle(&mut target, &weights, &mut rng).unwrap();
+            counts[target[0]] += 1;
+        }
+        for (&w, &count) in weights.iter().zip(counts.iter()) {
+            let observed = count as f64 / trials as f64;
+            let expected = w / total;
+            assert!(
+                (observed - expected).abs() < 0.01,
+                "weight {}: observed {}, expected {}",
+                w,
+                observed,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_weighted_shuffler_matches_exact_int() {
+        // The second position has to follow the sequential draws too.
+        let mut rng = StdRng::seed_from_u64(24);
+        let mut shuffler = WeightedShuffler::default();
+        let trials = 60_000;
+        let mut counts = std::collections::HashMap::new();
+        for _ in 0..trials {
+            let mut target = [0, 1, 2];
+            shuffler
+                .shuffle(&mut target, &[1.0, 2.0, 3.0], &mut rng)
+                .unwrap();
+            *counts.entry(target).or_insert(0) += 1;
+        }
+        for (order, &count) in counts.iter() {
+            let (first, second) = ((order[0] + 1) as f64, (order[1] + 1) as f64);
+            let expected = first / 6.0 * second / (6.0 - first);
+            let observed = count as f64 / trials as f64;
+            assert!((observed - expected).abs() < 0.01, "order {:?}", order);
+        }
+    }
+
+    #[test]
+    fn test_weighted_shuffler_zero_and_invalid_weights() {
+        let mut rng = StdRng::seed_from_u64(24);
+        let mut shuffler = WeightedShuffler::default();
+        let weights = [0.0, 4.0, 0.0, 1e-9, 7.0, 0.0, 2.0];
+        for _ in 0..100 {
+            let mut target: Vec<usize> = (0..weights.len()).collect();
+            shuffler.shuffle(&mut target, &weights, &mut rng).unwrap();
+            assert!(target[..4].iter().all(|&i| weights[i] > 0.0));
+            assert!(target[4..].iter().all(|&i| weights[i] == 0.0));
+            target.sort_unstable();
+            assert_eq!(target, (0..weights.len()).collect::<Vec<_>>());
+        }
+
+        let mut target = vec![1, 2, 3];
+        assert_eq!(
+            shuffler.shuffle(&mut target, &[1.0, 2.0], &mut rng),
+            Err(ShuffleError::LengthMismatch)
+        );
+        for bad in [-1.0, f64::INFINITY, f64::NAN] {
+            assert!(matches!(
+                shuffler.shuffle(&mut target, &[1.0, bad, 0.0], &mut rng),
+                Err(ShuffleError::InvalidArgument(_))
+            ));
+        }
+        assert_eq!(target, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_fenwick_tree() {
+        let weights = [3, 0, 1, 4, 1, 5, 9, 2, 6];
+        let mut tree = FenwickTree::new(&weights);
+        let expected: Vec<usize> = weights
+            .iter()
+            .enumerate()
+            .flat_map(|(i, &w)| std::iter::repeat_n(i, w as usize))
+            .collect();
+        for (target, &index) in expected.iter().enumerate() {
+            assert_eq!(tree.find(target as u64), index);
+        }
+        tree.sub(5, 5);
+        assert_eq!(tree.find(8), 4);
+        assert_eq!(tree.find(9), 6);
+    }
+}